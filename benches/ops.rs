@@ -0,0 +1,165 @@
+//! Benchmarks of the basic operations across node orders, with `Vec` and `im::Vector` as baselines. Run with `cargo bench`; pass a filter such as `cargo bench -- get` to run one group.
+
+use catvec::CatVec;
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+
+const N: usize = 100_000;
+
+/// Pseudo-random positions below `len`, the same on every run.
+fn positions(len: usize, count: usize) -> Vec<usize> {
+    let mut state = 0x2545_f491_4f6c_dd1du64;
+    (0..count)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state as usize % len
+        })
+        .collect()
+}
+
+/// Adds the CatVec benchmarks of every group for one order.
+fn catvec<const ORD: usize>(c: &mut Criterion) {
+    let name = format!("catvec/{}", ORD);
+    let items: Vec<u64> = (0..N as u64).collect();
+    let v: CatVec<u64, ORD> = items.as_slice().into();
+    let at = positions(N, 1000);
+
+    c.benchmark_group("push_back")
+        .bench_function(BenchmarkId::new(&name, N), |b| {
+            b.iter(|| {
+                let mut v: CatVec<u64, ORD> = CatVec::new();
+                for i in 0..N as u64 {
+                    v.push_back(i);
+                }
+                v
+            })
+        });
+    c.benchmark_group("get")
+        .bench_function(BenchmarkId::new(&name, N), |b| {
+            b.iter(|| at.iter().map(|&i| *v.get(i).unwrap()).sum::<u64>())
+        });
+    c.benchmark_group("insert")
+        .bench_function(BenchmarkId::new(&name, N), |b| {
+            b.iter(|| {
+                let mut v = v.clone();
+                for &i in &at {
+                    v.insert(i, 0);
+                }
+                v
+            })
+        });
+    c.benchmark_group("append")
+        .bench_function(BenchmarkId::new(&name, N), |b| {
+            b.iter(|| {
+                let mut w = v.clone();
+                w.append(v.clone());
+                w
+            })
+        });
+    c.benchmark_group("slice")
+        .bench_function(BenchmarkId::new(&name, N), |b| {
+            b.iter(|| {
+                let mut w = v.clone();
+                w.slice_into(N / 3..2 * N / 3);
+                w
+            })
+        });
+    c.benchmark_group("iter")
+        .bench_function(BenchmarkId::new(&name, N), |b| {
+            b.iter(|| black_box(&v).iter().sum::<u64>())
+        });
+}
+
+fn vec(c: &mut Criterion) {
+    let items: Vec<u64> = (0..N as u64).collect();
+    let at = positions(N, 1000);
+    c.benchmark_group("push_back")
+        .bench_function(BenchmarkId::new("vec", N), |b| {
+            b.iter(|| {
+                let mut v = Vec::new();
+                for i in 0..N as u64 {
+                    v.push(i);
+                }
+                v
+            })
+        });
+    c.benchmark_group("get")
+        .bench_function(BenchmarkId::new("vec", N), |b| {
+            b.iter(|| at.iter().map(|&i| black_box(&items)[i]).sum::<u64>())
+        });
+    c.benchmark_group("insert")
+        .bench_function(BenchmarkId::new("vec", N), |b| {
+            b.iter(|| {
+                let mut v = items.clone();
+                for &i in &at {
+                    v.insert(i, 0);
+                }
+                v
+            })
+        });
+    c.benchmark_group("append")
+        .bench_function(BenchmarkId::new("vec", N), |b| {
+            b.iter(|| {
+                let mut w = items.clone();
+                w.extend_from_slice(&items);
+                w
+            })
+        });
+    c.benchmark_group("slice")
+        .bench_function(BenchmarkId::new("vec", N), |b| {
+            b.iter(|| items[N / 3..2 * N / 3].to_vec())
+        });
+    c.benchmark_group("iter")
+        .bench_function(BenchmarkId::new("vec", N), |b| {
+            b.iter(|| black_box(&items).iter().sum::<u64>())
+        });
+}
+
+fn im(c: &mut Criterion) {
+    let v: im::Vector<u64> = (0..N as u64).collect();
+    let at = positions(N, 1000);
+    c.benchmark_group("push_back")
+        .bench_function(BenchmarkId::new("im", N), |b| {
+            b.iter(|| {
+                let mut v = im::Vector::new();
+                for i in 0..N as u64 {
+                    v.push_back(i);
+                }
+                v
+            })
+        });
+    c.benchmark_group("get")
+        .bench_function(BenchmarkId::new("im", N), |b| {
+            b.iter(|| at.iter().map(|&i| v[i]).sum::<u64>())
+        });
+    c.benchmark_group("insert")
+        .bench_function(BenchmarkId::new("im", N), |b| {
+            b.iter(|| {
+                let mut v = v.clone();
+                for &i in &at {
+                    v.insert(i, 0);
+                }
+                v
+            })
+        });
+    c.benchmark_group("append")
+        .bench_function(BenchmarkId::new("im", N), |b| {
+            b.iter(|| {
+                let mut w = v.clone();
+                w.append(v.clone());
+                w
+            })
+        });
+    c.benchmark_group("slice")
+        .bench_function(BenchmarkId::new("im", N), |b| {
+            b.iter(|| v.clone().slice(N / 3..2 * N / 3))
+        });
+    c.benchmark_group("iter")
+        .bench_function(BenchmarkId::new("im", N), |b| {
+            b.iter(|| black_box(&v).iter().sum::<u64>())
+        });
+}
+
+criterion_group!(benches, catvec::<8>, catvec::<32>, catvec::<128>, vec, im);
+criterion_main!(benches);
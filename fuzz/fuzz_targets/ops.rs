@@ -0,0 +1,5 @@
+//! The libFuzzer harness, for `cargo fuzz run ops`.
+
+#![no_main]
+
+libfuzzer_sys::fuzz_target!(|data: &[u8]| catvec_fuzz::run(data));
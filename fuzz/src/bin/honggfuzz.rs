@@ -0,0 +1,7 @@
+//! The honggfuzz harness, for `HFUZZ_BUILD_ARGS="--features honggfuzz" cargo hfuzz run honggfuzz`.
+
+fn main() {
+    loop {
+        honggfuzz::fuzz!(|data: &[u8]| { catvec_fuzz::run(data) });
+    }
+}
@@ -0,0 +1,144 @@
+//! The operation evaluator shared by the fuzz harnesses and the tests. Inputs are decoded into sequences of operations, which run against a [CatVec], a `Vec` and an [im::Vector] side by side, comparing every result and the full contents after every step. `Vec` is the plain model; `im::Vector` is a second persistent implementation, which catches mistakes in sharing that a model rebuilt from scratch never exercises.
+//!
+//! The tests in the main crate include this file as a module, so it only uses what they have as dependencies or dev-dependencies.
+
+use arbitrary::{Arbitrary, Unstructured};
+use catvec::CatVec;
+
+#[derive(Debug, Arbitrary, Clone)]
+pub enum Op {
+    /// Pushes a vector of the given length, counting up from the given value. Taking the contents from the input instead would let one literal swallow the rest of it.
+    Literal(u8, u8),
+    /// Pushes a copy of the top vector, so that later edits to either must leave the other alone.
+    Clone,
+    Append,
+    Insert(usize, u8),
+    /// Inserts within two positions of the end, on either side, to exercise the bounds check.
+    TryInsert(u8, u8),
+    Remove(usize),
+    Slice(usize, usize),
+    PushBack(u8),
+    PushFront(u8),
+    PopFront,
+    Get(usize),
+}
+
+type Vecs = (CatVec<u8, 4, 4>, Vec<u8>, im::Vector<u8>);
+
+fn check((cat, model, oracle): &Vecs) {
+    cat.check_invariants();
+    assert_eq!(cat.len(), model.len());
+    assert_eq!(oracle.len(), model.len());
+    assert!(cat.iter().eq(model.iter()));
+    assert!(oracle.iter().eq(model.iter()));
+}
+
+/// Runs a sequence of operations, panicking on the first difference between the three vectors.
+pub fn eval(ops: &[Op]) {
+    let mut stack: Vec<Vecs> = Vec::new();
+    for op in ops {
+        match op {
+            Op::Literal(len, start) => {
+                let v: Vec<u8> = (0..*len).map(|k| start.wrapping_add(k)).collect();
+                stack.push((v.as_slice().into(), v.clone(), v.into_iter().collect()))
+            }
+            Op::Clone => match stack.last() {
+                Some(top) => stack.push(top.clone()),
+                None => continue,
+            },
+            Op::Append => {
+                if stack.len() < 2 {
+                    continue;
+                }
+                let (cat, mut model, oracle) = stack.pop().unwrap();
+                let top = stack.last_mut().unwrap();
+                top.0.append(cat);
+                top.1.append(&mut model);
+                top.2.append(oracle);
+            }
+            op => {
+                let Some((cat, model, oracle)) = stack.last_mut() else {
+                    continue;
+                };
+                let len = model.len();
+                match *op {
+                    Op::Insert(i, x) => {
+                        let i = i % (len + 1);
+                        cat.insert(i, x);
+                        model.insert(i, x);
+                        oracle.insert(i, x);
+                    }
+                    Op::TryInsert(i, x) => {
+                        let i = (len + i as usize % 5).saturating_sub(2);
+                        match cat.try_insert(i, x) {
+                            Ok(()) => {
+                                model.insert(i, x);
+                                oracle.insert(i, x);
+                            }
+                            Err(_) => assert!(i > len),
+                        }
+                    }
+                    Op::Remove(i) => {
+                        if len == 0 {
+                            assert!(cat.try_remove(i).is_err());
+                            continue;
+                        }
+                        let i = i % len;
+                        let x = model.remove(i);
+                        assert_eq!(cat.remove(i), x);
+                        assert_eq!(oracle.remove(i), x);
+                    }
+                    Op::Slice(i, j) => {
+                        let i = i % (len + 1);
+                        let j = (j % (len + 1)).max(i);
+                        cat.slice_into(i..j);
+                        *model = model[i..j].to_vec();
+                        *oracle = oracle.take(j).skip(i);
+                    }
+                    Op::PushBack(x) => {
+                        cat.push_back(x);
+                        model.push(x);
+                        oracle.push_back(x);
+                    }
+                    Op::PushFront(x) => {
+                        cat.push_front(x);
+                        model.insert(0, x);
+                        oracle.push_front(x);
+                    }
+                    Op::PopFront => {
+                        let x = (!model.is_empty()).then(|| model.remove(0));
+                        assert_eq!(cat.pop_front(), x);
+                        assert_eq!(oracle.pop_front(), x);
+                    }
+                    Op::Get(i) => {
+                        let i = i % (len + 1);
+                        assert_eq!(cat.get(i), model.get(i));
+                        assert_eq!(oracle.get(i), model.get(i));
+                    }
+                    Op::Literal(..) | Op::Clone | Op::Append => unreachable!(),
+                }
+            }
+        }
+        check(stack.last().unwrap());
+    }
+    // copies that were edited after being cloned must still hold their own contents
+    stack.iter().for_each(check);
+}
+
+/// Decodes as many operations as the input holds.
+pub fn decode(data: &[u8]) -> Vec<Op> {
+    let mut u = Unstructured::new(data);
+    let mut ops = Vec::new();
+    while !u.is_empty() {
+        match u.arbitrary() {
+            Ok(op) => ops.push(op),
+            Err(_) => break,
+        }
+    }
+    ops
+}
+
+/// Decodes an input and runs it.
+pub fn run(data: &[u8]) {
+    eval(&decode(data));
+}
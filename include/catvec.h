@@ -0,0 +1,35 @@
+/* C declarations for the functions of catvec's `ffi` feature. See src/ffi.rs for the details of each. */
+
+#ifndef CATVEC_H
+#define CATVEC_H
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* An opaque handle to a persistent byte vector. */
+typedef struct CatVecBytes CatVecBytes;
+
+CatVecBytes *catvec_new(void);
+CatVecBytes *catvec_clone(const CatVecBytes *v);
+void catvec_free(CatVecBytes *v);
+
+size_t catvec_len(const CatVecBytes *v);
+bool catvec_get(const CatVecBytes *v, size_t idx, uint8_t *out);
+bool catvec_copy_to(const CatVecBytes *v, size_t start, uint8_t *out, size_t len);
+
+void catvec_push(CatVecBytes *v, uint8_t byte);
+void catvec_extend(CatVecBytes *v, const uint8_t *bytes, size_t len);
+void catvec_append(CatVecBytes *v, const CatVecBytes *other);
+/* Returns NULL if the range is inverted or out of bounds. */
+CatVecBytes *catvec_slice(const CatVecBytes *v, size_t start, size_t end);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
@@ -0,0 +1,69 @@
+use archery::SharedPointerKind;
+use arrow_array::{types::ArrowPrimitiveType, Array, ArrowNativeTypeOp, PrimitiveArray};
+
+use crate::{btree::Tree, focus::Focus, CatVec, Measure};
+
+impl<T: Clone, const ORD: usize, const LEAF: usize, P: SharedPointerKind, M: Measure<T>>
+    CatVec<T, ORD, LEAF, P, M>
+{
+    /// Builds a vector out of an Arrow primitive array, copying its values into leaves a leaf-sized chunk at a time. Returns None if the array has nulls, which a vector of plain values can't represent.
+    pub fn from_arrow<A: ArrowPrimitiveType<Native = T>>(array: &PrimitiveArray<A>) -> Option<Self>
+    where
+        T: ArrowNativeTypeOp,
+    {
+        if array.null_count() > 0 {
+            return None;
+        }
+        let leaves = array
+            .values()
+            .chunks(LEAF)
+            .map(|chunk| chunk.iter().copied().collect());
+        Some(Self {
+            inner: Tree::build_from_leaves(leaves).into(),
+            head: Default::default(),
+            tail: Default::default(),
+            focus: Focus::new(),
+        })
+    }
+}
+
+impl<A: ArrowPrimitiveType, const ORD: usize, const LEAF: usize, P: SharedPointerKind, M>
+    From<CatVec<A::Native, ORD, LEAF, P, M>> for PrimitiveArray<A>
+where
+    M: Measure<A::Native>,
+{
+    /// Copies the values into one Arrow buffer, a leaf at a time. The array has no nulls.
+    fn from(cv: CatVec<A::Native, ORD, LEAF, P, M>) -> Self {
+        let mut values = Vec::with_capacity(cv.len());
+        cv.for_each_chunk(|leaf| values.extend_from_slice(leaf));
+        PrimitiveArray::new(values.into(), None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use arrow_array::{types::Int64Type, Array, Int64Array};
+
+    use crate::CatVec;
+
+    #[test]
+    fn arrow_round_trip() {
+        let mut v: CatVec<i64, 4, 4> = (0..1000).collect::<Vec<_>>().into();
+        v.push_front(-1);
+        v.push_back(1000);
+        let array = Int64Array::from(v.clone());
+        assert_eq!(array.len(), 1002);
+        assert_eq!(array.null_count(), 0);
+        assert!(array.values().iter().eq(v.iter()));
+
+        let back: CatVec<i64, 4, 4> = CatVec::from_arrow(&array).unwrap();
+        back.check_invariants();
+        assert!(back.iter().eq(v.iter()));
+        let sliced = array.slice(10, 7);
+        let back: CatVec<i64, 4, 4> = CatVec::from_arrow::<Int64Type>(&sliced).unwrap();
+        assert_eq!(Vec::from(back), (9..16).collect::<Vec<_>>());
+
+        let with_nulls = Int64Array::from(vec![Some(1), None, Some(3)]);
+        assert!(CatVec::<i64, 4>::from_arrow(&with_nulls).is_none());
+    }
+}
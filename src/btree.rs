@@ -1,86 +1,259 @@
-use std::sync::Arc;
+use alloc::{vec, vec::Vec};
 
+use archery::{SharedPointer, SharedPointerKind};
 use arrayvec::ArrayVec;
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
 
-/// An implementation of a relative-indexed, immutable B+tree, const-generic over the fanout degree ORD.
+use crate::{
+    measure::{find_in_slice, measure_slice},
+    InvariantViolation, Measure, ViolationKind,
+};
+
+/// An implementation of a relative-indexed, immutable B+tree, const-generic over the fanout degree ORD of internal nodes and the capacity LEAF of leaves.
 /// https://github.com/jafingerhut/core.btree-vector/blob/master/doc/intro.md
-#[derive(Clone)]
-pub enum Tree<T: Clone, const ORD: usize> {
-    Internal(Internal<T, ORD>),
-    Array(ArrayVec<T, ORD>),
+pub enum Tree<T: Clone, const ORD: usize, const LEAF: usize, P: SharedPointerKind, M: Measure<T>> {
+    Internal(Internal<T, ORD, LEAF, P, M>),
+    Array(ArrayVec<T, LEAF>),
+}
+
+// Clone is implemented by hand throughout the crate, since deriving it would require the pointer kind to be Clone, which it needn't be.
+impl<T: Clone, const ORD: usize, const LEAF: usize, P: SharedPointerKind, M: Measure<T>> Clone
+    for Tree<T, ORD, LEAF, P, M>
+{
+    fn clone(&self) -> Self {
+        match self {
+            Tree::Internal(int) => Tree::Internal(int.clone()),
+            Tree::Array(arr) => Tree::Array(arr.clone()),
+        }
+    }
 }
 
-// impl<T: Clone + Debug, const ORD: usize> Tree<T, ORD> {
-//     pub fn eprint_graphviz(self: &Arc<Self>) -> u64 {
-//         // let my_id = Arc::as_ptr(self) as u64;
-//         let my_id = fastrand::u64(0..u64::MAX);
-//         match self.as_ref() {
-//             Tree::Array(vals) => {
-//                 eprintln!(
-//                     "{} [label = \"[{}, {:?}]\"  shape=box];",
-//                     my_id,
-//                     vals.len(),
-//                     vals
-//                 );
-//             }
-//             Tree::Internal(int) => {
-//                 for child in int.children.iter() {
-//                     let child_id = child.eprint_graphviz();
-//                     eprintln!("{} -> {};", my_id, child_id);
-//                 }
-//                 if int.root {
-//                     eprintln!("{} [label = \"ROOT[{}]\" shape=box];", my_id, int.length);
-//                 } else {
-//                     eprintln!("{} [label = \"[{}]\"  shape=box];", my_id, int.length);
-//                 }
-//             }
-//         }
-//         my_id
-//     }
-// }
-
-impl<T: Clone, const ORD: usize> Tree<T, ORD> {
-    pub fn eprint_graphviz(self: &Arc<Self>) -> u64 {
-        // let my_id = Arc::as_ptr(self) as u64;
-        let my_id = fastrand::u64(0..u64::MAX);
-        match self.as_ref() {
+impl<T: Clone, const ORD: usize, const LEAF: usize, P: SharedPointerKind, M: Measure<T>>
+    Tree<T, ORD, LEAF, P, M>
+{
+    /// Prints the edges and nodes of the tree to stderr in Graphviz syntax. Nodes are numbered in traversal order, so the same tree always prints the same way.
+    #[cfg(feature = "std")]
+    pub fn eprint_graphviz(&self) {
+        self.eprint_graphviz_from(&mut 0);
+    }
+
+    /// Prints this subtree, numbering its nodes starting from `next_id`, and returns the id of this node.
+    #[cfg(feature = "std")]
+    fn eprint_graphviz_from(&self, next_id: &mut u64) -> u64 {
+        let my_id = *next_id;
+        *next_id += 1;
+        match self {
             Tree::Array(vals) => {
                 eprintln!("{} [label = \"[{}, LEAF]\"  shape=box];", my_id, vals.len(),);
             }
             Tree::Internal(int) => {
                 for child in int.children.iter() {
-                    let child_id = child.eprint_graphviz();
+                    let child_id = child.eprint_graphviz_from(next_id);
                     eprintln!("{} -> {};", my_id, child_id);
                 }
                 if int.root {
-                    eprintln!("{} [label = \"ROOT[{}]\" shape=box];", my_id, int.length);
+                    eprintln!("{} [label = \"ROOT[{}]\" shape=box];", my_id, int.len());
                 } else {
-                    eprintln!("{} [label = \"[{}]\"  shape=box];", my_id, int.length);
+                    eprintln!("{} [label = \"[{}]\"  shape=box];", my_id, int.len());
                 }
             }
         }
         my_id
     }
 
+    /// Rejects, at compile time, orders and leaf sizes below 2, which leave nothing to split a full node into.
+    fn check_params() {
+        const {
+            assert!(
+                ORD >= 2 && LEAF >= 2,
+                "ORD and LEAF must both be at least 2"
+            )
+        }
+    }
+
+    /// Creates an empty tree, which is a lone leaf. Trees that fit in one leaf stay that way, without any internal nodes, until they outgrow it.
     pub fn new() -> Self {
-        Tree::Internal(Internal {
-            length: 0,
-            children: {
-                let mut v = ArrayVec::new();
-                v.push(Arc::new(Tree::Array(ArrayVec::new())));
-                v
-            },
-            root: true,
-        })
+        Self::check_params();
+        Tree::Array(ArrayVec::new())
+    }
+
+    /// Builds a tree in the most compact shape from a sequence of elements: leaves and internal nodes are packed full, so the height is minimal. Only the last two nodes of each level may be less full, to keep them at least half-full.
+    pub fn build(items: impl IntoIterator<Item = T>) -> Self {
+        let mut items = items.into_iter().peekable();
+        Self::build_from_leaves(core::iter::from_fn(|| {
+            items.peek()?;
+            Some(items.by_ref().take(LEAF).collect())
+        }))
+    }
+
+    /// Like [Tree::build], but from leaves that are already filled. All of them but the last should be full, or the tree won't be compact.
+    pub fn build_from_leaves(leaves: impl IntoIterator<Item = ArrayVec<T, LEAF>>) -> Self {
+        Self::check_params();
+        let mut level: Vec<Self> = leaves
+            .into_iter()
+            .filter(|leaf| !leaf.is_empty())
+            .map(Tree::Array)
+            .collect();
+        Self::balance_last_two(&mut level);
+        while level.len() > 1 {
+            let mut nodes = level.into_iter().peekable();
+            let mut next_level = Vec::new();
+            while nodes.peek().is_some() {
+                let children: ArrayVec<_, ORD> =
+                    nodes.by_ref().take(ORD).map(SharedPointer::new).collect();
+                next_level.push(Tree::Internal(Internal::new(children, false)));
+            }
+            Self::balance_last_two(&mut next_level);
+            level = next_level;
+        }
+        match level.pop() {
+            None => Tree::new(),
+            Some(Tree::Internal(mut int)) => {
+                int.root = true;
+                Tree::Internal(int)
+            }
+            Some(leaf) => leaf,
+        }
+    }
+
+    /// Evens out the last two nodes of a level if the last one is less than half full.
+    fn balance_last_two(level: &mut [Self]) {
+        if let [.., left, right] = level {
+            if right.children_count() < right.min_children() {
+                right.steal_children_from(left, true);
+            }
+        }
     }
 
     pub fn len(&self) -> usize {
         match self {
-            Tree::Internal(internal) => internal.length,
+            Tree::Internal(internal) => internal.len(),
             Tree::Array(inner) => inner.len(),
         }
     }
 
+    /// The measure of all the elements, which internal nodes have cached.
+    pub fn measure(&self) -> M::Summary {
+        match self {
+            Tree::Internal(internal) => internal.measure(),
+            Tree::Array(inner) => measure_slice::<T, M>(inner),
+        }
+    }
+
+    /// The hash of the tree, reusing the hashes cached in internal nodes and filling in the missing ones. A leaf is hashed as a node whose children are its elements.
+    #[cfg(feature = "merkle")]
+    pub(crate) fn hash(&self) -> [u8; 32]
+    where
+        T: core::hash::Hash,
+    {
+        match self {
+            Tree::Array(items) => crate::merkle::leaf_hash(items),
+            Tree::Internal(int) => *int.hash.get_or_init(|| {
+                crate::merkle::node_hash(int.children.iter().map(|c| (c.len(), c.hash())))
+            }),
+        }
+    }
+
+    /// The canonical chunking of the tree's elements, reusing the chunkings cached in internal nodes and filling in the missing ones, so that after an edit only the path that was copied is visited.
+    #[cfg(feature = "merkle")]
+    pub(crate) fn chunking(&self) -> alloc::borrow::Cow<'_, crate::merkle::Chunking>
+    where
+        T: core::hash::Hash,
+    {
+        match self {
+            Tree::Array(items) => {
+                alloc::borrow::Cow::Owned(crate::merkle::Chunking::elements(items))
+            }
+            Tree::Internal(int) => alloc::borrow::Cow::Borrowed(int.chunking.get_or_init(|| {
+                std::sync::Arc::new(
+                    int.children
+                        .iter()
+                        .fold(Default::default(), |acc: crate::merkle::Chunking, c| {
+                            acc.append(&c.chunking())
+                        }),
+                )
+            })),
+        }
+    }
+
+    /// Makes an internal node out of the given children, which must all have the same height.
+    #[cfg(feature = "merkle")]
+    pub(crate) fn from_children(
+        children: ArrayVec<SharedPointer<Self, P>, ORD>,
+        root: bool,
+    ) -> Self {
+        Tree::Internal(Internal::new(children, root))
+    }
+
+    /// Returns true if the two trees are known to be the same version, because they consist of the very same child nodes. Never looks further than the immediate children.
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        if core::ptr::eq(self, other) {
+            return true;
+        }
+        match (self, other) {
+            (Tree::Internal(this), Tree::Internal(other)) => {
+                this.len() == other.len()
+                    && this.children.len() == other.children.len()
+                    && this
+                        .children
+                        .iter()
+                        .zip(other.children.iter())
+                        .all(|(a, b)| SharedPointer::ptr_eq(a, b))
+            }
+            _ => false,
+        }
+    }
+
+    /// Moves all elements, in order, to the end of the given vector. Subtrees that are shared with other trees are cloned from instead.
+    pub fn move_into(self, out: &mut Vec<T>) {
+        match self {
+            Tree::Array(items) => out.extend(items),
+            Tree::Internal(int) => {
+                for child in int.children {
+                    match SharedPointer::try_unwrap(child) {
+                        Ok(child) => child.move_into(out),
+                        Err(shared) => shared.leaves().for_each(|leaf| out.extend_from_slice(leaf)),
+                    }
+                }
+            }
+        }
+    }
+
+    /// Iterates over the leaves of the tree, in order, as contiguous slices.
+    pub fn leaves(&self) -> Leaves<'_, T, ORD, LEAF, P, M> {
+        Leaves::new(self)
+    }
+
+    /// Collects the pointers to the non-empty leaves below this node, in order. A root that is itself a leaf has no pointer, so it collects nothing.
+    pub(crate) fn leaf_pointers<'a>(&'a self, out: &mut Vec<&'a SharedPointer<Self, P>>) {
+        if let Tree::Internal(int) = self {
+            for child in int.children.iter() {
+                match child.as_ref() {
+                    Tree::Array(arr) if arr.is_empty() => {}
+                    Tree::Array(_) => out.push(child),
+                    Tree::Internal(_) => child.leaf_pointers(out),
+                }
+            }
+        }
+    }
+
+    /// Collects the non-empty leaves below this node, in order, as mutable slices, copying every shared node on the way and clearing the cached hashes. The cached measures are left alone, so this is only for unmeasured trees.
+    #[cfg(any(feature = "rayon", feature = "rand"))]
+    pub(crate) fn leaves_mut<'a>(&'a mut self, out: &mut Vec<&'a mut [T]>) {
+        match self {
+            Tree::Internal(int) => {
+                int.forget_hash();
+                for child in int.children.iter_mut() {
+                    SharedPointer::make_mut(child).leaves_mut(out);
+                }
+            }
+            Tree::Array(arr) if arr.is_empty() => {}
+            Tree::Array(arr) => out.push(arr),
+        }
+    }
+
+    #[cfg(any(test, feature = "merkle"))]
     pub fn get(&self, idx: usize) -> Option<&T> {
         match self {
             Tree::Internal(internal) => internal.get(idx),
@@ -88,6 +261,139 @@ impl<T: Clone, const ORD: usize> Tree<T, ORD> {
         }
     }
 
+    /// The measure of the first `idx` elements, which takes one descent, combining the cached measures of the children to the left along the way.
+    pub(crate) fn measure_prefix(&self, mut idx: usize) -> M::Summary {
+        let mut node = self;
+        let mut acc = M::identity();
+        loop {
+            match node {
+                Tree::Array(items) => {
+                    return M::combine(&acc, &measure_slice::<T, M>(&items[..idx]))
+                }
+                Tree::Internal(int) => {
+                    let (child, offset) = int.key_to_idx_and_offset(idx);
+                    for m in &int.measures[..child] {
+                        acc = M::combine(&acc, m);
+                    }
+                    idx -= offset;
+                    node = &int.children[child];
+                }
+            }
+        }
+    }
+
+    /// The measure of the elements from `idx` to the end, the mirror image of [Tree::measure_prefix].
+    fn measure_suffix(&self, idx: usize) -> M::Summary {
+        match self {
+            Tree::Array(items) => measure_slice::<T, M>(&items[idx..]),
+            Tree::Internal(int) => {
+                let (child, offset) = int.key_to_idx_and_offset(idx);
+                int.measures[child + 1..].iter().fold(
+                    int.children[child].measure_suffix(idx - offset),
+                    |acc, m| M::combine(&acc, m),
+                )
+            }
+        }
+    }
+
+    /// The measure of the elements in `start..end`. Descends to the node where the two ends part ways, then combines a suffix of one child, the cached measures of the children in between and a prefix of the other, so it takes two descents at most.
+    pub(crate) fn measure_range(&self, start: usize, end: usize) -> M::Summary {
+        match self {
+            Tree::Array(items) => measure_slice::<T, M>(&items[start..end]),
+            Tree::Internal(int) => {
+                let (first, first_offset) = int.key_to_idx_and_offset(start);
+                let (last, last_offset) = int.key_to_idx_and_offset(end);
+                if first == last {
+                    return int.children[first]
+                        .measure_range(start - first_offset, end - first_offset);
+                }
+                let acc = int.measures[first + 1..last].iter().fold(
+                    int.children[first].measure_suffix(start - first_offset),
+                    |acc, m| M::combine(&acc, m),
+                );
+                M::combine(&acc, &int.children[last].measure_prefix(end - last_offset))
+            }
+        }
+    }
+
+    /// Finds the first position at which the measure of the elements up to and including it, combined onto `acc`, satisfies the predicate, which must stay satisfied once it is. Skips over every child whose measure doesn't get there, so it takes one descent. If no position does, returns the measure of everything combined onto `acc`.
+    pub(crate) fn find_by_measure(
+        &self,
+        mut acc: M::Summary,
+        pred: &mut impl FnMut(&M::Summary) -> bool,
+    ) -> Result<usize, M::Summary> {
+        match self {
+            Tree::Array(items) => find_in_slice::<T, M>(items, acc, pred),
+            Tree::Internal(int) => {
+                for (i, m) in int.measures.iter().enumerate() {
+                    let next = M::combine(&acc, m);
+                    if pred(&next) {
+                        return int.children[i]
+                            .find_by_measure(acc, pred)
+                            .map(|pos| pos + int.offset_of(i));
+                    }
+                    acc = next;
+                }
+                Err(acc)
+            }
+        }
+    }
+
+    /// Finds the leaf containing the given position, returning the position of its first element and its contents. The position equal to the length maps to the last leaf.
+    pub fn leaf_at(&self, mut idx: usize) -> (usize, &[T]) {
+        let mut node = self;
+        let mut start = 0;
+        loop {
+            match node {
+                Tree::Array(items) => return (start, items),
+                Tree::Internal(int) => {
+                    let (child, offset) = int.key_to_idx_and_offset(idx);
+                    start += offset;
+                    idx -= offset;
+                    node = &int.children[child];
+                }
+            }
+        }
+    }
+
+    /// For an internal node, finds the child containing the given position, returning its index and the position of its first element.
+    pub(crate) fn find_child(&self, idx: usize) -> Option<(usize, usize)> {
+        match self {
+            Tree::Internal(int) => Some(int.key_to_idx_and_offset(idx)),
+            Tree::Array(_) => None,
+        }
+    }
+
+    /// Binary searches a sorted tree with a comparator, descending only into the child that could contain the target. Children are compared by their last elements, which are reached by walking down their rightmost spines rather than looked up by position.
+    pub fn binary_search_by(
+        &self,
+        mut f: impl FnMut(&T) -> core::cmp::Ordering,
+    ) -> Result<usize, usize> {
+        let mut node = self;
+        let mut offset = 0;
+        loop {
+            match node {
+                Tree::Array(items) => {
+                    return items
+                        .binary_search_by(&mut f)
+                        .map(|i| i + offset)
+                        .map_err(|i| i + offset)
+                }
+                Tree::Internal(int) => {
+                    // the first child whose last element is not less than the target
+                    let idx = int
+                        .children
+                        .partition_point(|c| c.last().is_none_or(|x| f(x).is_lt()));
+                    if idx == int.children.len() {
+                        return Err(offset + int.len());
+                    }
+                    offset += int.offset_of(idx);
+                    node = &int.children[idx];
+                }
+            }
+        }
+    }
+
     pub fn get_mut(&mut self, idx: usize) -> Option<&mut T> {
         match self {
             Tree::Internal(internal) => internal.get_mut(idx),
@@ -95,19 +401,82 @@ impl<T: Clone, const ORD: usize> Tree<T, ORD> {
         }
     }
 
-    pub fn insert(&mut self, key: usize, value: T) -> Option<Self> {
+    /// Gets mutable references to the elements at two distinct positions, descending once along the spine they share and then down each branch separately.
+    pub fn get2_mut(&mut self, i: usize, j: usize) -> Option<(&mut T, &mut T)> {
         match self {
-            Tree::Internal(internal) => {
-                log::trace!("internal insert at key {}", key);
-                internal.insert(key, value)
+            Tree::Internal(internal) => internal.get2_mut(i, j),
+            Tree::Array(items) => {
+                let [x, y] = items.get_disjoint_mut([i, j]).ok()?;
+                Some((x, y))
             }
+        }
+    }
+
+    /// Calls `f` on each element in `start..end`, which must be in bounds, copying only the shared nodes on the paths to the leaves it overlaps.
+    pub fn apply(&mut self, start: usize, end: usize, f: &mut impl FnMut(&mut T)) {
+        match self {
+            Tree::Internal(internal) => internal.apply(start, end, f),
+            Tree::Array(items) => items[start..end].iter_mut().for_each(f),
+        }
+    }
+
+    /// Like [Tree::apply], but on all cores, with sibling subtrees overlapping the range updated as separate tasks.
+    #[cfg(feature = "rayon")]
+    pub(crate) fn par_apply(
+        &mut self,
+        start: usize,
+        end: usize,
+        f: &(impl Fn(&mut T) + Sync + Send),
+    ) where
+        T: Send + Sync,
+        P: Send + Sync,
+    {
+        match self {
+            Tree::Internal(internal) => internal.par_apply(start, end, f),
+            Tree::Array(items) => items[start..end].iter_mut().for_each(f),
+        }
+    }
+
+    /// Inserts at the given position of a root node, adding a level on top if it has to split.
+    pub fn insert(&mut self, key: usize, value: T) {
+        self.insert_at(&mut Position(key), value)
+    }
+
+    /// Inserts into a sorted root node, after all the elements that `cmp` doesn't order after the new one, and returns the position of the insertion. The position is found on the same descent that does the insertion.
+    pub fn insert_sorted_by(
+        &mut self,
+        cmp: impl FnMut(&T, &T) -> core::cmp::Ordering,
+        value: T,
+    ) -> usize {
+        let mut at = Sorted { cmp, offset: 0 };
+        self.insert_at(&mut at, value);
+        at.offset
+    }
+
+    fn insert_at(&mut self, at: &mut impl Locate<T>, value: T) {
+        if let Some(right) = self.insert_or_split(at, value) {
+            let left = core::mem::replace(self, Tree::new());
+            let children = IntoIterator::into_iter([left, right])
+                .map(SharedPointer::new)
+                .collect();
+            *self = Tree::Internal(Internal::new(children, true));
+        }
+    }
+
+    /// Inserts where `at` leads. If the node is full, it splits in two, and the right half is returned, for the caller to add as a sibling.
+    fn insert_or_split(&mut self, at: &mut impl Locate<T>, value: T) -> Option<Self> {
+        Self::check_params();
+        match self {
+            Tree::Internal(internal) => internal.insert(at, value),
             Tree::Array(values) => {
+                let key = at.leaf(values, &value);
+                trace!("leaf insert at key {}", key);
                 if !values.is_full() {
                     values.insert(key, value);
                     None
                 } else {
                     let split_point = values.len() / 2;
-                    let mut other_values: ArrayVec<_, ORD> = values.drain(split_point..).collect();
+                    let mut other_values: ArrayVec<_, LEAF> = values.drain(split_point..).collect();
                     if key >= split_point {
                         other_values.insert(key - split_point, value);
                     } else {
@@ -119,6 +488,17 @@ impl<T: Clone, const ORD: usize> Tree<T, ORD> {
         }
     }
 
+    /// The last element, found by walking down the rightmost nonempty children.
+    pub(crate) fn last(&self) -> Option<&T> {
+        let mut node = self;
+        loop {
+            match node {
+                Tree::Array(items) => return items.last(),
+                Tree::Internal(int) => node = int.children.iter().rev().find(|c| c.len() > 0)?,
+            }
+        }
+    }
+
     pub fn concat(&mut self, mut other: Self) {
         if self.len() == 0 {
             *self = other;
@@ -131,78 +511,62 @@ impl<T: Clone, const ORD: usize> Tree<T, ORD> {
         let other_height = other.height();
         // easy case: heights are the same
         if self_height == other_height {
-            match self {
-                Tree::Array(this) => {
-                    let mut other = match other {
-                        Tree::Array(other) => other,
-                        _ => unreachable!(),
-                    };
-                    if this.len() + other.len() <= ORD {
+            // take ownership of our root, so that nodes are moved rather than cloned
+            let this = core::mem::replace(self, Tree::Array(ArrayVec::new()));
+            *self = match (this, other) {
+                (Tree::Array(mut this), Tree::Array(mut other)) => {
+                    if this.len() + other.len() <= LEAF {
                         // well, that's pretty trivial
-                        this.extend(other.into_iter())
+                        this.extend(other);
+                        Tree::Array(this)
                     } else {
                         // okay, now we can apportion the nodes into two halves
-                        if this.len() < ORD / 2 {
-                            let to_move = ORD / 2 - this.len();
+                        if this.len() < LEAF / 2 {
+                            let to_move = LEAF / 2 - this.len();
                             this.extend(other.drain(0..to_move));
-                        } else if other.len() < ORD / 2 {
-                            let to_move = ORD / 2 - other.len();
+                        } else if other.len() < LEAF / 2 {
+                            let to_move = LEAF / 2 - other.len();
                             let start_idx = this.len() - to_move;
-                            let new_other =
-                                this.drain(start_idx..).chain(other.into_iter()).collect();
+                            let new_other = this.drain(start_idx..).chain(other).collect();
                             other = new_other
                         }
-                        let noviy = Internal {
-                            length: this.len() + other.len(),
-                            children: IntoIterator::into_iter([this.clone(), other])
-                                .map(|i| Arc::new(Tree::Array(i)))
+                        Tree::Internal(Internal::new(
+                            IntoIterator::into_iter([this, other])
+                                .map(|i| SharedPointer::new(Tree::Array(i)))
                                 .collect(),
-                            root: true,
-                        };
-                        *self = Tree::Internal(noviy)
+                            true,
+                        ))
                     }
                 }
-                Tree::Internal(this) => {
-                    let mut other = match other {
-                        Tree::Internal(other) => other,
-                        _ => unreachable!(),
-                    };
+                (Tree::Internal(mut this), Tree::Internal(mut other)) => {
                     if this.children.len() + other.children.len() <= ORD {
-                        this.length += other.length;
-                        this.children.extend(other.children.into_iter())
+                        this.children.extend(other.children);
+                        this.reindex();
+                        Tree::Internal(this)
                     } else {
                         if this.children.len() < ORD / 2 {
                             let to_move = ORD / 2 - this.children.len();
-                            for elem in other.children.drain(0..to_move) {
-                                other.length -= elem.len();
-                                this.length += elem.len();
-                                this.children.push(elem);
-                            }
+                            this.children.extend(other.children.drain(0..to_move));
                         } else if other.children.len() < ORD / 2 {
                             let to_move = ORD / 2 - other.children.len();
                             let start_idx = this.children.len() - to_move;
-                            let mut new_other = ArrayVec::new();
-                            for elem in this.children.drain(start_idx..) {
-                                other.length += elem.len();
-                                this.length -= elem.len();
-                                new_other.push(elem);
-                            }
+                            let mut new_other: ArrayVec<_, ORD> =
+                                this.children.drain(start_idx..).collect();
                             new_other.extend(other.children.drain(0..));
                             other.children = new_other;
                         }
                         this.root = false;
                         other.root = false;
-                        let this = Arc::new(Tree::Internal(this.clone()));
-                        let other = Arc::new(Tree::Internal(other));
-                        let noviy = Internal {
-                            length: this.len() + other.len(),
-                            children: IntoIterator::into_iter([this.clone(), other]).collect(),
-                            root: true,
-                        };
-                        *self = Tree::Internal(noviy)
+                        this.reindex();
+                        other.reindex();
+                        let children = IntoIterator::into_iter([this, other])
+                            .map(|i| SharedPointer::new(Tree::Internal(i)))
+                            .collect();
+                        Tree::Internal(Internal::new(children, true))
                     }
                 }
-            }
+                _ => unreachable!(),
+            };
             self.fixup(true);
             self.fixup(false);
         } else {
@@ -220,20 +584,51 @@ impl<T: Clone, const ORD: usize> Tree<T, ORD> {
         }
     }
 
+    /// Splits the tree in two at the given position, which must be in bounds, keeping the elements before it and returning the rest. Nodes on the path to the split are split in place if uniquely owned, and copied only if shared; the nodes to either side are moved over. The two halves end up shaped like the results of [Tree::take_head] and [Tree::drop_head], fringes fixed up the same way, and likewise may need [Tree::collapse_root].
+    pub fn split_off(&mut self, key: usize) -> Self {
+        match self {
+            Tree::Internal(internal) => {
+                let root = internal.root;
+                let mut right = Tree::Internal(internal.split_off(key));
+                if root {
+                    self.fixup(true);
+                    right.fixup(false);
+                }
+                right
+            }
+            Tree::Array(items) => Tree::Array(items.drain(key..).collect()),
+        }
+    }
+
+    /// Removes chains of single-child internal nodes at the top of the tree, so that the height reflects the actual size. A tree that fits in one leaf becomes just that leaf.
+    pub fn collapse_root(&mut self) {
+        while let Tree::Internal(int) = self {
+            if int.children.len() != 1 {
+                return;
+            }
+            let child = int.children.pop().unwrap();
+            let mut child =
+                SharedPointer::try_unwrap(child).unwrap_or_else(|child| (*child).clone());
+            if let Tree::Internal(child) = &mut child {
+                child.root = true;
+            }
+            *self = child;
+        }
+    }
+
     fn pad_once(&mut self) {
         if let Tree::Internal(int) = self {
             int.root = false;
         }
-        let len = self.len();
-        let noo = Internal {
-            root: true,
-            children: IntoIterator::into_iter([Arc::new(self.clone())]).collect(),
-            length: len,
-        };
+        let child = core::mem::replace(self, Tree::Array(ArrayVec::new()));
+        let noo = Internal::new(
+            IntoIterator::into_iter([SharedPointer::new(child)]).collect(),
+            true,
+        );
         *self = Tree::Internal(noo)
     }
 
-    fn height(&self) -> usize {
+    pub fn height(&self) -> usize {
         match self {
             Tree::Internal(i) => i.height(),
             _ => 0,
@@ -270,161 +665,167 @@ impl<T: Clone, const ORD: usize> Tree<T, ORD> {
 
     /// Checks invariants.
     pub fn check_invariants(&self) {
+        if let Err(violation) = self.validate(&mut Vec::new()) {
+            panic!("invariant violated: {}", violation)
+        }
+    }
+
+    /// Checks invariants, reporting the first violation found. `path` is the path to this node, and is used for reporting.
+    pub fn validate(&self, path: &mut Vec<usize>) -> Result<(), InvariantViolation> {
+        self.validate_node(path, true, true)
+    }
+
+    /// Checks the invariants of a node that may be the root, or the rightmost node of its level.
+    fn validate_node(
+        &self,
+        path: &mut Vec<usize>,
+        is_top: bool,
+        is_rightmost: bool,
+    ) -> Result<(), InvariantViolation> {
+        let violation = |kind| InvariantViolation {
+            path: path.clone(),
+            kind,
+        };
+        if let Tree::Internal(int) = self {
+            let Some(first) = int.children.first() else {
+                return Err(violation(ViolationKind::Childless));
+            };
+            if int.root != is_top {
+                return Err(violation(ViolationKind::RootFlag { root: int.root }));
+            }
+            if int.dense != int.is_dense() {
+                return Err(violation(ViolationKind::DenseFlag { dense: int.dense }));
+            }
+            let mut actual = 0;
+            for (i, child) in int.children.iter().enumerate() {
+                actual += child.len();
+                let cached = int.sizes.get(i).copied();
+                if cached != Some(actual) {
+                    return Err(violation(ViolationKind::LengthMismatch {
+                        cached: cached.unwrap_or(0),
+                        actual,
+                    }));
+                }
+            }
+            for (index, child) in int.children.iter().enumerate() {
+                if int.measures.get(index) != Some(&child.measure()) {
+                    return Err(violation(ViolationKind::MeasureMismatch { index }));
+                }
+            }
+            if int.sizes.len() != int.children.len() {
+                return Err(violation(ViolationKind::LengthMismatch {
+                    cached: int.len(),
+                    actual,
+                }));
+            }
+            let empty_allowed = is_top && int.len() == 0;
+            if let Some(index) = int.children.iter().position(|c| c.len() == 0) {
+                if !empty_allowed {
+                    return Err(violation(ViolationKind::EmptyChild { index }));
+                }
+            }
+            let expected = first.height();
+            if int.height != expected + 1 {
+                return Err(violation(ViolationKind::HeightMismatch {
+                    cached: int.height,
+                    actual: expected + 1,
+                }));
+            }
+            if let Some(child) = int.children.iter().find(|c| c.height() != expected) {
+                return Err(violation(ViolationKind::UnevenHeight {
+                    expected,
+                    actual: child.height(),
+                }));
+            }
+        }
+        // the root may be arbitrarily small, and so may the rightmost leaf, which is where pushes go
+        let min_exempt = is_top || (is_rightmost && matches!(self, Tree::Array(_)));
+        if !min_exempt && self.children_count() < self.min_children() {
+            return Err(violation(ViolationKind::Underfull {
+                children: self.children_count(),
+                min: self.min_children(),
+            }));
+        }
         if let Some(children) = self.children() {
-            for child in children {
-                child.check_invariants();
+            for (i, child) in children.iter().enumerate() {
+                path.push(i);
+                child.validate_node(path, false, is_rightmost && i + 1 == children.len())?;
+                path.pop();
             }
-            assert_eq!(children.len(), self.children_count());
-            assert_eq!(self.len(), children.iter().map(|c| c.len()).sum::<usize>());
         }
-        let is_root = if let Tree::Internal(int) = self {
-            int.root
+        Ok(())
+    }
+
+    /// Repairs the left or right fringe of the tree after slicing or concatenation, in a single bottom-up pass down the fringe. Everything off the fringe must already satisfy the invariants.
+    fn fixup(&mut self, is_right: bool) {
+        trace!("fixup(is_right = {})", is_right);
+        self.fix_fringe(is_right);
+        if self.len() == 0 {
+            *self = Tree::new();
+        }
+        self.collapse_root();
+    }
+
+    /// Fixes the fringe below this node, deepest level first, so that every node strictly below it satisfies the invariants. The only exception is a chain of single-child nodes hanging off this node, which has no neighbors to borrow from yet; it gets fixed by the first ancestor that has them.
+    fn fix_fringe(&mut self, is_right: bool) {
+        let int = match self {
+            Tree::Internal(int) => int,
+            Tree::Array(_) => return,
+        };
+        let fringe = if is_right {
+            int.children.last_mut()
         } else {
-            true
+            int.children.first_mut()
         };
-        if !is_root {
-            assert!(self.children_count() >= ORD / 2)
+        if let Some(fringe) = fringe {
+            SharedPointer::make_mut(fringe).fix_fringe(is_right);
         }
+        // slicing can leave empty children behind, and doesn't maintain lengths along the fringe
+        int.children.retain(|c| c.len() > 0);
+        int.reindex();
+        self.settle_fringe(is_right);
     }
 
-    /// Fixes stuff
-    ///
-    /// TODO: fix log^2(n) runtime
-    fn fixup(&mut self, is_right: bool) {
-        log::trace!("fixup(is_right = {})", is_right);
-        for depth in (0..self.height()).rev() {
-            log::trace!("at depth {}", depth);
-            let this = self.unwrap_internal();
-            let mut stack = Vec::new();
-            if is_right {
-                stack.extend(this.children.iter_mut().map(|e| (e, 0usize)));
-            } else {
-                stack.extend(this.children.iter_mut().rev().map(|e| (e, 0usize)));
-            }
-            defmac::defmac!(pushch children, level => if is_right {
-                stack.extend(children.iter_mut().map(|e| (e, level)));
+    /// If the fringe child of this node is underfull, merges it into its neighbor or steals children from it. Either way, the node that ends up at the fringe may have an underfull fringe child of its own, left over from a chain of single-child nodes, so this continues down the fringe until there's nothing left to fix.
+    fn settle_fringe(&mut self, is_right: bool) {
+        let int = match self {
+            Tree::Internal(int) => int,
+            Tree::Array(_) => return,
+        };
+        let count = int.children.len();
+        if count < 2 {
+            return;
+        }
+        let fringe_idx = if is_right { count - 1 } else { 0 };
+        // settling below a node that just stole children can merge two of them, so it may take another round
+        while int.children[fringe_idx].fringe_deficient() {
+            let (fringe, neighbor) = if is_right {
+                let (rest, fringe) = int.children.split_at_mut(fringe_idx);
+                (&mut fringe[0], &mut rest[fringe_idx - 1])
             } else {
-                stack.extend(children.iter_mut().rev().map(|e| (e, level)));
-            });
-            // we first go down all the way to the fringe
-            for _ in 0..depth {
-                let (elem, _) = stack.last().unwrap();
-                match elem.as_ref() {
-                    Tree::Internal(int) => {
-                        // if no children, BAIL!
-                        if int.children.is_empty() {
-                            break;
-                        }
-                        let (elem, current_level) = stack.pop().unwrap();
-                        let int = Arc::make_mut(elem).unwrap_internal();
-                        log::trace!("pushing at level {}", current_level);
-                        pushch!(&mut int.children, current_level + 1);
-                    }
-                    Tree::Array(_) => {
-                        // BAIL out!
-                        break;
-                    }
-                }
-            }
-            log::trace!("stack has {} elements", stack.len());
-            if stack.is_empty() {
-                break;
-            }
-            // At this point, the stack begins from the last level of the fringe.
-            let (fringe_tip, h) = stack.pop().unwrap();
-            assert!(h <= depth);
-            if h < depth {
-                break;
-            }
-            let fringe_tip = Arc::make_mut(fringe_tip);
-            // We attempt to pop a neighbor at the same level
-            let neighbor = loop {
-                if let Some((elem, elem_level)) = stack.pop() {
-                    log::trace!("finding neighbor at height {}", elem_level);
-                    assert!(elem_level <= depth);
-                    let top = Arc::make_mut(elem);
-                    if elem_level == depth {
-                        log::trace!("found the right thing");
-                        break Some(top);
-                    } else if let Some(children) = top.children_mut() {
-                        log::trace!("pushing {} children", children.len());
-                        pushch!(children, elem_level + 1);
-                    } else {
-                        log::trace!("skipping element with NO children");
-                    }
-                } else {
-                    break None;
-                }
+                let (fringe, rest) = int.children.split_at_mut(1);
+                (&mut fringe[0], &mut rest[0])
             };
-            log::trace!(
-                "at node with {} children, found neighbor with {:?} children",
-                fringe_tip.children_count(),
-                neighbor.as_ref().map(|n| n.children_count())
-            );
-            // Fixup for that node
-            let at_new_root = fringe_tip.fixup_inner(neighbor, is_right);
-            if at_new_root {
-                *self = fringe_tip.clone();
-                break;
-            }
-        }
-        log::trace!("final fixup!");
-        self.fixup_inner(None, is_right);
-    }
-
-    /// Given a fringe node and its left/right neighbor, fix the invariants of the fringe node. Returns true if and only if the fringe node should be spun up to the root.
-    fn fixup_inner(&mut self, neighbor: Option<&mut Self>, is_right: bool) -> bool {
-        // We remove any empty children. These are from previous runs.
-        if let Tree::Internal(fringe) = self {
-            fringe.children.retain(|c| c.len() > 0);
-            fringe.length = fringe.children.iter().map(|c| (c.len())).sum();
-            if fringe.root && fringe.children.is_empty() {
-                fringe.children.push(Arc::new(Tree::Array(ArrayVec::new())))
-            }
-        }
-
-        // go through the different cases now!
-        // case 1: no neighbor. This means that this node should be the root!
-        match neighbor {
-            None => {
-                log::trace!("case 1 hit");
-                if let Tree::Internal(int) = self {
-                    int.root = true;
-                    true
-                } else {
-                    false
-                }
-            }
-            Some(neighbor) => {
-                if let Tree::Internal(neighbor) = neighbor {
-                    neighbor.children.retain(|c| c.len() > 0);
-                    neighbor.length = neighbor.children.iter().map(|c| c.len()).sum();
-                }
-                // case 2: F doesn't actually violate invariants
-                if self.children_count() >= ORD / 2 {
-                    log::trace!("case 2 hit");
-                    return false;
-                }
-                // case 3: F violates the invariants by having too little children.
-                assert!(self.children_count() < ORD / 2);
-                // case 3a: self + neighbor have at most ORD children. we merge self into neighbor.
-                if self.children_count() + neighbor.children_count() <= ORD {
-                    log::trace!("case 3a hit");
-                    self.give_all_children_to(neighbor, is_right);
-                    false
-                } else {
-                    // case 3b: self+neighbor overflow in children. we steal children from our neighbor.
-                    log::trace!("case 3b hit");
-                    self.steal_children_from(neighbor, is_right);
-                    false
-                }
+            let fringe = SharedPointer::make_mut(fringe);
+            let neighbor = SharedPointer::make_mut(neighbor);
+            if fringe.children_count() + neighbor.children_count() <= fringe.capacity() {
+                trace!("merging fringe into neighbor");
+                fringe.give_all_children_to(neighbor, is_right);
+                neighbor.settle_fringe(is_right);
+                int.children.remove(fringe_idx);
+                int.reindex();
+                return;
             }
+            trace!("stealing from neighbor");
+            fringe.steal_children_from(neighbor, is_right);
+            fringe.settle_fringe(is_right);
+            int.reindex();
         }
     }
 
     /// Push children to the other node.
     fn give_all_children_to(&mut self, other: &mut Self, is_right: bool) {
-        log::trace!("giving all children");
+        trace!("giving all children");
         match other {
             Tree::Array(other) => {
                 let this = self.unwrap_arr();
@@ -432,33 +833,25 @@ impl<T: Clone, const ORD: usize> Tree<T, ORD> {
                     other.extend(this.drain(0..))
                 } else {
                     this.extend(other.drain(0..));
-                    std::mem::swap(this, other);
+                    core::mem::swap(this, other);
                 }
             }
             Tree::Internal(other) => {
                 let this = self.unwrap_internal();
-                other.length += this.length;
-                this.length = 0;
                 if is_right {
                     other.children.extend(this.children.drain(0..));
                 } else {
                     this.children.extend(other.children.drain(0..));
-                    std::mem::swap(&mut this.children, &mut other.children);
+                    core::mem::swap(&mut this.children, &mut other.children);
                 }
+                this.reindex();
+                other.reindex();
             }
         }
     }
 
     /// List of all children
-    fn children_mut(&mut self) -> Option<&mut ArrayVec<Arc<Self>, ORD>> {
-        match self {
-            Tree::Array(_) => None,
-            Tree::Internal(int) => Some(&mut int.children),
-        }
-    }
-
-    /// List of all children
-    fn children(&self) -> Option<&ArrayVec<Arc<Self>, ORD>> {
+    pub(crate) fn children(&self) -> Option<&ArrayVec<SharedPointer<Self, P>, ORD>> {
         match self {
             Tree::Array(_) => None,
             Tree::Internal(int) => Some(&int.children),
@@ -471,45 +864,40 @@ impl<T: Clone, const ORD: usize> Tree<T, ORD> {
             Tree::Array(other) => {
                 let this = self.unwrap_arr();
                 if is_right {
-                    while this.len() < ORD / 2 {
+                    while this.len() < LEAF / 2 {
                         this.insert(0, other.pop().expect("other children ran out"))
                     }
                 } else {
-                    log::trace!("{} STEALING {}", this.len(), other.len());
+                    trace!("{} STEALING {}", this.len(), other.len());
                     let before = this.len() + other.len();
-                    let to_move = ORD / 2 - this.len();
+                    let to_move = LEAF / 2 - this.len();
                     this.extend(other.drain(0..to_move));
                     let after = this.len() + other.len();
-                    log::trace!("{} BALANCED {}", this.len(), other.len());
+                    trace!("{} BALANCED {}", this.len(), other.len());
                     assert_eq!(before, after);
                 }
             }
             Tree::Internal(other) => {
                 let this = self.unwrap_internal();
+                // a lone child may still need siblings to borrow from, so take at least two
+                let min = (ORD / 2).max(2);
                 if is_right {
-                    while this.children.len() < ORD / 2 {
+                    while this.children.len() < min {
                         let child = other.children.pop().expect("other children ran out");
-                        other.length -= child.len();
-                        this.length += child.len();
                         this.children.insert(0, child);
                     }
                 } else {
-                    let before = this.length + other.length;
-                    let to_move = ORD / 2 - this.children.len();
-                    for child in other.children.drain(0..to_move) {
-                        other.length -= child.len();
-                        this.length += child.len();
-                        this.children.push(child);
-                    }
-                    let after = this.length + other.length;
-                    assert_eq!(before, after);
+                    let to_move = min - this.children.len();
+                    this.children.extend(other.children.drain(0..to_move));
                 }
+                this.reindex();
+                other.reindex();
             }
         }
     }
 
     /// Unwraps as array.
-    fn unwrap_arr(&mut self) -> &mut ArrayVec<T, ORD> {
+    fn unwrap_arr(&mut self) -> &mut ArrayVec<T, LEAF> {
         match self {
             Tree::Array(arr) => arr,
             _ => panic!("unwrap_arr called on a non-array node "),
@@ -517,7 +905,7 @@ impl<T: Clone, const ORD: usize> Tree<T, ORD> {
     }
 
     /// Unwraps as internal.
-    fn unwrap_internal(&mut self) -> &mut Internal<T, ORD> {
+    fn unwrap_internal(&mut self) -> &mut Internal<T, ORD, LEAF, P, M> {
         match self {
             Tree::Internal(int) => int,
             _ => panic!("unwrap_internal called on non-internal node"),
@@ -532,53 +920,316 @@ impl<T: Clone, const ORD: usize> Tree<T, ORD> {
         }
     }
 
-    // Returns the two first *immediate* children of this node.
-    fn first_two_children(&mut self) -> (Option<&mut Self>, Option<&mut Self>) {
+    /// Returns the most children this node can hold: LEAF elements for a leaf, ORD children for an internal node.
+    fn capacity(&self) -> usize {
         match self {
-            Tree::Array(_) => (None, None),
-            Tree::Internal(i) => {
-                if i.children.is_empty() {
-                    return (None, None);
-                }
-                let (first, rest) = i.children.split_first_mut().unwrap();
-                let rest_first = rest.split_first_mut().map(|p| p.0);
-                (
-                    Some(Arc::make_mut(first)),
-                    rest_first.map(|rf| Arc::make_mut(rf)),
-                )
-            }
+            Tree::Array(_) => LEAF,
+            Tree::Internal(_) => ORD,
         }
     }
 
-    // Returns the two last *immediate* children of this node.
-    fn last_two_children(&mut self) -> (Option<&mut Self>, Option<&mut Self>) {
+    /// Returns the fewest children a node other than the root may hold.
+    fn min_children(&self) -> usize {
+        self.capacity() / 2
+    }
+
+    /// Returns true if this node is underfull, or is a chain of single-child nodes ending in an underfull node. Such chains are allowed when ORD is so small that a single child is half full, but leaves may still need more than one element, and the chain has no siblings to fix them with.
+    fn fringe_deficient(&self) -> bool {
         match self {
-            Tree::Array(_) => (None, None),
-            Tree::Internal(i) => {
-                if i.children.is_empty() {
-                    return (None, None);
+            Tree::Array(arr) => arr.len() < LEAF / 2,
+            Tree::Internal(int) => {
+                int.children.len() < ORD / 2
+                    || (int.children.len() == 1 && int.children[0].fringe_deficient())
+            }
+        }
+    }
+}
+
+/// A pointer to a child node.
+/// Where an insertion goes, worked out one level at a time on the way down, so that finding the place and inserting there take a single descent.
+trait Locate<T> {
+    /// Picks the child of an internal node to insert into, and narrows down to it.
+    fn child<const ORD: usize, const LEAF: usize, P: SharedPointerKind, M: Measure<T>>(
+        &mut self,
+        node: &Internal<T, ORD, LEAF, P, M>,
+        value: &T,
+    ) -> usize
+    where
+        T: Clone;
+
+    /// Picks the position in a leaf to insert at.
+    fn leaf(&mut self, items: &[T], value: &T) -> usize;
+}
+
+/// Inserts at a position, relative to the node being descended into.
+struct Position(usize);
+
+impl<T> Locate<T> for Position {
+    fn child<const ORD: usize, const LEAF: usize, P: SharedPointerKind, M: Measure<T>>(
+        &mut self,
+        node: &Internal<T, ORD, LEAF, P, M>,
+        _: &T,
+    ) -> usize
+    where
+        T: Clone,
+    {
+        let (idx, offset) = node.key_to_idx_and_offset(self.0);
+        self.0 -= offset;
+        idx
+    }
+
+    fn leaf(&mut self, _: &[T], _: &T) -> usize {
+        self.0
+    }
+}
+
+/// Inserts into sorted elements after the last one not ordered after the new one, keeping track of the position of the insertion.
+struct Sorted<F> {
+    cmp: F,
+    offset: usize,
+}
+
+impl<T, F: FnMut(&T, &T) -> core::cmp::Ordering> Locate<T> for Sorted<F> {
+    fn child<const ORD: usize, const LEAF: usize, P: SharedPointerKind, M: Measure<T>>(
+        &mut self,
+        node: &Internal<T, ORD, LEAF, P, M>,
+        value: &T,
+    ) -> usize
+    where
+        T: Clone,
+    {
+        // the first child whose last element comes after the new one, or else the last child
+        let cmp = &mut self.cmp;
+        let idx = node
+            .children
+            .partition_point(|c| c.last().is_none_or(|x| cmp(x, value).is_le()))
+            .min(node.children.len() - 1);
+        self.offset += node.offset_of(idx);
+        idx
+    }
+
+    fn leaf(&mut self, items: &[T], value: &T) -> usize {
+        let idx = items.partition_point(|x| (self.cmp)(x, value).is_le());
+        self.offset += idx;
+        idx
+    }
+}
+
+pub(crate) type Child<T, const ORD: usize, const LEAF: usize, P, M> =
+    SharedPointer<Tree<T, ORD, LEAF, P, M>, P>;
+
+/// An iterator over the non-empty leaves of a tree, from left to right.
+pub struct Leaves<
+    'a,
+    T: Clone,
+    const ORD: usize,
+    const LEAF: usize,
+    P: SharedPointerKind,
+    M: Measure<T>,
+> {
+    stack: Vec<core::slice::Iter<'a, Child<T, ORD, LEAF, P, M>>>,
+    single: Option<&'a [T]>,
+    head: Option<&'a [T]>,
+    tail: Option<&'a [T]>,
+}
+
+impl<'a, T: Clone, const ORD: usize, const LEAF: usize, P: SharedPointerKind, M: Measure<T>> Clone
+    for Leaves<'a, T, ORD, LEAF, P, M>
+{
+    fn clone(&self) -> Self {
+        Self {
+            stack: self.stack.clone(),
+            single: self.single,
+            head: self.head,
+            tail: self.tail,
+        }
+    }
+}
+
+impl<'a, T: Clone, const ORD: usize, const LEAF: usize, P: SharedPointerKind, M: Measure<T>>
+    Leaves<'a, T, ORD, LEAF, P, M>
+{
+    fn new(tree: &'a Tree<T, ORD, LEAF, P, M>) -> Self {
+        match tree {
+            Tree::Internal(int) => Self {
+                stack: vec![int.children.iter()],
+                single: None,
+                head: None,
+                tail: None,
+            },
+            Tree::Array(arr) => Self {
+                stack: Vec::new(),
+                single: Some(arr.as_slice()).filter(|a| !a.is_empty()),
+                head: None,
+                tail: None,
+            },
+        }
+    }
+
+    /// Yields one more leaf before and one more after the ones in the tree, unless they are empty.
+    pub(crate) fn with_ends(mut self, head: &'a [T], tail: &'a [T]) -> Self {
+        self.head = Some(head).filter(|h| !h.is_empty());
+        self.tail = Some(tail).filter(|t| !t.is_empty());
+        self
+    }
+}
+
+impl<'a, T: Clone, const ORD: usize, const LEAF: usize, P: SharedPointerKind, M: Measure<T>>
+    Iterator for Leaves<'a, T, ORD, LEAF, P, M>
+{
+    type Item = &'a [T];
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(head) = self.head.take() {
+            return Some(head);
+        }
+        if let Some(single) = self.single.take() {
+            return Some(single);
+        }
+        loop {
+            let top = match self.stack.last_mut() {
+                Some(top) => top,
+                None => return self.tail.take(),
+            };
+            match top.next().map(|c| c.as_ref()) {
+                None => {
+                    self.stack.pop();
+                }
+                Some(Tree::Internal(int)) => self.stack.push(int.children.iter()),
+                Some(Tree::Array(arr)) => {
+                    if !arr.is_empty() {
+                        return Some(arr.as_slice());
+                    }
                 }
-                let (first, rest) = i.children.split_last_mut().unwrap();
-                let rest_first = rest.split_last_mut().map(|p| p.0);
-                (
-                    Some(Arc::make_mut(first)),
-                    rest_first.map(|rf| Arc::make_mut(rf)),
-                )
             }
         }
     }
 }
 
-#[derive(Clone)]
-pub struct Internal<T: Clone, const ORD: usize> {
-    length: usize,
-    children: ArrayVec<Arc<Tree<T, ORD>>, ORD>,
+pub struct Internal<
+    T: Clone,
+    const ORD: usize,
+    const LEAF: usize,
+    P: SharedPointerKind,
+    M: Measure<T>,
+> {
+    children: ArrayVec<SharedPointer<Tree<T, ORD, LEAF, P, M>, P>, ORD>,
+    /// Running totals of the children's lengths, so that `sizes[i]` counts the elements in children `0..=i`. Lets lookups binary search for the right child.
+    sizes: ArrayVec<usize, ORD>,
+    /// The measure of each child.
+    measures: ArrayVec<M::Summary, ORD>,
+    /// Number of levels from this node down to the leaves, which is 1 if the children are leaves.
+    height: usize,
+    /// Set if every child but the last is completely full, as is usual for vectors built by pushing or [Tree::build]. Lookups in such a node find the right child by division, like in a radix tree, and only search `sizes` in nodes relaxed by slicing, concatenation, or inserts in the middle.
+    dense: bool,
     root: bool,
+    /// The hash of the node, computed on first use and cleared whenever the node changes. Unchanged subtrees keep theirs across edits, so rehashing after an edit only visits the path that was copied.
+    #[cfg(feature = "merkle")]
+    hash: std::sync::OnceLock<[u8; 32]>,
+    /// The canonical chunking of the node's elements, cached and cleared along with the hash.
+    #[cfg(feature = "merkle")]
+    chunking: std::sync::OnceLock<std::sync::Arc<crate::merkle::Chunking>>,
+}
+
+impl<T: Clone, const ORD: usize, const LEAF: usize, P: SharedPointerKind, M: Measure<T>> Clone
+    for Internal<T, ORD, LEAF, P, M>
+{
+    fn clone(&self) -> Self {
+        Self {
+            children: self.children.clone(),
+            sizes: self.sizes.clone(),
+            measures: self.measures.clone(),
+            height: self.height,
+            dense: self.dense,
+            root: self.root,
+            #[cfg(feature = "merkle")]
+            hash: self.hash.clone(),
+            #[cfg(feature = "merkle")]
+            chunking: self.chunking.clone(),
+        }
+    }
 }
 
-impl<T: Clone, const ORD: usize> Internal<T, ORD> {
+impl<T: Clone, const ORD: usize, const LEAF: usize, P: SharedPointerKind, M: Measure<T>>
+    Internal<T, ORD, LEAF, P, M>
+{
+    fn new(
+        children: ArrayVec<SharedPointer<Tree<T, ORD, LEAF, P, M>, P>, ORD>,
+        root: bool,
+    ) -> Self {
+        let mut int = Internal {
+            height: children.first().map_or(1, |c| c.height() + 1),
+            children,
+            sizes: ArrayVec::new(),
+            measures: ArrayVec::new(),
+            dense: false,
+            root,
+            #[cfg(feature = "merkle")]
+            hash: Default::default(),
+            #[cfg(feature = "merkle")]
+            chunking: Default::default(),
+        };
+        int.reindex();
+        int
+    }
+
+    fn len(&self) -> usize {
+        self.sizes.last().copied().unwrap_or(0)
+    }
+
+    /// Recomputes the running lengths and the measures after children were added, removed, or changed.
+    fn reindex(&mut self) {
+        let mut total = 0;
+        self.sizes = self
+            .children
+            .iter()
+            .map(|c| {
+                total += c.len();
+                total
+            })
+            .collect();
+        self.measures = self.children.iter().map(|c| c.measure()).collect();
+        self.dense = self.is_dense();
+        self.forget_hash();
+    }
+
+    /// Clears the cached hash and chunking after a change that doesn't go through [Internal::reindex].
+    fn forget_hash(&mut self) {
+        #[cfg(feature = "merkle")]
+        {
+            self.hash.take();
+            self.chunking.take();
+        }
+    }
+
+    /// The measure of the whole node.
+    fn measure(&self) -> M::Summary {
+        self.measures
+            .iter()
+            .fold(M::identity(), |acc, m| M::combine(&acc, m))
+    }
+
+    /// The most elements a child of this node can hold, or None if that doesn't fit in a usize.
+    fn child_capacity(&self) -> Option<usize> {
+        ORD.checked_pow(self.height as u32 - 1)?.checked_mul(LEAF)
+    }
+
+    /// Checks whether every child but the last holds as many elements as it possibly can.
+    fn is_dense(&self) -> bool {
+        match self.child_capacity() {
+            Some(cap) => self.children.iter().rev().skip(1).all(|c| c.len() == cap),
+            None => false,
+        }
+    }
+
+    /// The position of the first element of the given child.
+    fn offset_of(&self, idx: usize) -> usize {
+        idx.checked_sub(1).map_or(0, |i| self.sizes[i])
+    }
+
+    #[cfg(any(test, feature = "merkle"))]
     fn get(&self, key: usize) -> Option<&T> {
-        if key >= self.length {
+        if key >= self.len() {
             return None;
         }
         let (idx, offset) = self.key_to_idx_and_offset(key);
@@ -586,115 +1237,213 @@ impl<T: Clone, const ORD: usize> Internal<T, ORD> {
     }
 
     fn get_mut(&mut self, key: usize) -> Option<&mut T> {
-        if key >= self.length {
+        if key >= self.len() {
             return None;
         }
         let (idx, offset) = self.key_to_idx_and_offset(key);
-        Arc::make_mut(&mut self.children[idx]).get_mut(key - offset)
+        self.forget_hash();
+        SharedPointer::make_mut(&mut self.children[idx]).get_mut(key - offset)
+    }
+
+    fn get2_mut(&mut self, i: usize, j: usize) -> Option<(&mut T, &mut T)> {
+        if i == j || i >= self.len() || j >= self.len() {
+            return None;
+        }
+        let (a, a_offset) = self.key_to_idx_and_offset(i);
+        let (b, b_offset) = self.key_to_idx_and_offset(j);
+        self.forget_hash();
+        if a == b {
+            return SharedPointer::make_mut(&mut self.children[a])
+                .get2_mut(i - a_offset, j - a_offset);
+        }
+        let [x, y] = self.children.get_disjoint_mut([a, b]).ok()?;
+        Some((
+            SharedPointer::make_mut(x).get_mut(i - a_offset)?,
+            SharedPointer::make_mut(y).get_mut(j - b_offset)?,
+        ))
+    }
+
+    fn apply(&mut self, start: usize, end: usize, f: &mut impl FnMut(&mut T)) {
+        if start >= end {
+            return;
+        }
+        let (first, _) = self.key_to_idx_and_offset(start);
+        let (last, _) = self.key_to_idx_and_offset(end - 1);
+        for idx in first..=last {
+            let offset = self.offset_of(idx);
+            let child_end = self.sizes[idx];
+            let child = SharedPointer::make_mut(&mut self.children[idx]);
+            child.apply(start.max(offset) - offset, end.min(child_end) - offset, f);
+            // the sizes stay the same, but the elements, and so their measures, may have changed
+            self.measures[idx] = child.measure();
+        }
+        self.forget_hash();
     }
 
-    fn insert(&mut self, key: usize, value: T) -> Option<Tree<T, ORD>> {
+    #[cfg(feature = "rayon")]
+    fn par_apply(&mut self, start: usize, end: usize, f: &(impl Fn(&mut T) + Sync + Send))
+    where
+        T: Send + Sync,
+        P: Send + Sync,
+    {
+        if start >= end {
+            return;
+        }
+        let (first, _) = self.key_to_idx_and_offset(start);
+        let (last, _) = self.key_to_idx_and_offset(end - 1);
+        let sizes = &self.sizes;
+        self.children[first..=last]
+            .par_iter_mut()
+            .zip(first..last + 1)
+            .for_each(|(child, idx)| {
+                let offset = idx.checked_sub(1).map_or(0, |i| sizes[i]);
+                SharedPointer::make_mut(child).par_apply(
+                    start.max(offset) - offset,
+                    end.min(sizes[idx]) - offset,
+                    f,
+                );
+            });
+        for idx in first..=last {
+            self.measures[idx] = self.children[idx].measure();
+        }
+        self.forget_hash();
+    }
+
+    fn insert(&mut self, at: &mut impl Locate<T>, value: T) -> Option<Tree<T, ORD, LEAF, P, M>> {
         if !self.children.is_full() {
-            log::trace!("non-full case");
+            trace!("non-full case");
             // we have room to stuff some more, this is the easy case
-            let (idx, offset) = self.key_to_idx_and_offset(key);
-            let correct_child = Arc::make_mut(&mut self.children[idx]);
-            // try inserting into that child
-            let other = correct_child.insert(key - offset, value);
-            // if the other side is Some, this means that we need to insert an extra child.
-            if let Some(other) = other {
-                self.children.insert(idx + 1, Arc::new(other));
-                log::trace!("non-full case, but adding another child")
-            }
-            self.length += 1;
+            let idx = at.child(self, &value);
+            self.insert_into_child(idx, at, value);
             // no need to twiddle with our parents at all
             None
         } else if self.root {
-            log::trace!("full root, adding another level");
-            // just make another level, stupid
-            let mut self_copy = self.clone();
-            self_copy.root = false;
-            self.children.clear();
-            self.children.push(Arc::new(Tree::Internal(self_copy)));
-            self.insert(key, value)
+            trace!("full root, adding another level");
+            // just make another level, stupid. the old root is moved down rather than cloned, so that its children aren't shared for nothing
+            let mut old_root = core::mem::replace(self, Internal::new(ArrayVec::new(), true));
+            old_root.root = false;
+            self.height = old_root.height + 1;
+            self.children
+                .push(SharedPointer::new(Tree::Internal(old_root)));
+            self.reindex();
+            self.insert(at, value)
         } else {
-            log::trace!("complicated case");
-            // the more complicated case. we split off like half of the nodes
+            trace!("complicated case");
+            // the more complicated case. we pick the child first, then split off like half of the nodes
+            let idx = at.child(self, &value);
             let split_point = self.children.len() / 2;
             let other_children: ArrayVec<_, ORD> = self.children.drain(split_point..).collect();
             assert_eq!(self.children.len() + other_children.len(), ORD);
-            let mut other = Tree::Internal(Internal {
-                length: other_children.iter().map(|f| f.len()).sum(),
-                children: other_children,
-                root: false,
-            });
-            let split_point = self.length - other.len();
-            self.length -= other.len();
-            // insert into the other side. this CANNOT cause an overflow no matter what!
-            if key >= split_point {
-                assert!(other.insert(key - split_point, value).is_none());
+            let mut other = Internal::new(other_children, false);
+            self.reindex();
+            // insert into the half holding the child. this CANNOT cause an overflow no matter what!
+            if idx >= split_point {
+                other.insert_into_child(idx - split_point, at, value);
             } else {
-                assert!(self.insert(key, value).is_none());
+                self.insert_into_child(idx, at, value);
             }
-            Some(other)
+            Some(Tree::Internal(other))
+        }
+    }
+
+    /// Inserts into the given child, which `at` has already been narrowed down to, adding a sibling after it if it splits. The node must have room for one more child.
+    fn insert_into_child(&mut self, idx: usize, at: &mut impl Locate<T>, value: T) {
+        let correct_child = SharedPointer::make_mut(&mut self.children[idx]);
+        // try inserting into that child
+        let other = correct_child.insert_or_split(at, value);
+        // if the other side is Some, this means that we need to insert an extra child.
+        if let Some(other) = other {
+            self.children.insert(idx + 1, SharedPointer::new(other));
+            trace!("adding another child");
+            self.reindex();
+        } else {
+            self.sizes[idx..].iter_mut().for_each(|s| *s += 1);
+            self.measures[idx] = self.children[idx].measure();
+            self.dense = self.is_dense();
+            self.forget_hash();
         }
     }
 
+    /// Finds the child containing the given position, along with the position of its first element. Positions past the end map to the last child.
     fn key_to_idx_and_offset(&self, key: usize) -> (usize, usize) {
-        let mut offset = 0;
-        for (idx, child) in self.children.iter().enumerate() {
-            if key - offset < child.len() || idx + 1 == self.children.len() {
-                return (idx, offset);
-            }
-            offset += child.len()
+        if self.dense {
+            let cap = self.child_capacity().unwrap();
+            let idx = (key / cap).min(self.children.len() - 1);
+            return (idx, idx * cap);
         }
-        unreachable!()
+        let idx = self
+            .sizes
+            .partition_point(|&s| s <= key)
+            .min(self.children.len() - 1);
+        (idx, self.offset_of(idx))
     }
 
     fn drop_head(&mut self, key: usize) {
         if key == 0 {
             return;
         }
-        assert!(key <= self.length);
-        self.length -= key;
+        assert!(key <= self.len());
         let (idx, offset) = self.key_to_idx_and_offset(key);
         self.children.drain(0..idx);
         if !self.children.is_empty() {
-            Arc::make_mut(&mut self.children[0]).drop_head(key - offset);
+            SharedPointer::make_mut(&mut self.children[0]).drop_head(key - offset);
         }
+        self.reindex();
+    }
+
+    fn split_off(&mut self, key: usize) -> Self {
+        assert!(key <= self.len());
+        let (idx, offset) = self.key_to_idx_and_offset(key);
+        let mut right: ArrayVec<_, ORD> = self.children.drain(idx + 1..).collect();
+        let child = SharedPointer::make_mut(&mut self.children[idx]);
+        right.insert(0, SharedPointer::new(child.split_off(key - offset)));
+        self.reindex();
+        Internal::new(right, self.root)
     }
 
     fn take_head(&mut self, key: usize) {
-        assert!(key <= self.length);
-        if key == self.length {
+        assert!(key <= self.len());
+        if key == self.len() {
             return;
         }
         let (idx, offset) = self.key_to_idx_and_offset(key);
         self.children.drain(idx + 1..);
         if let Some(last) = self.children.last_mut() {
-            Arc::make_mut(last).take_head(key - offset);
+            SharedPointer::make_mut(last).take_head(key - offset);
         }
+        self.reindex();
     }
 
     fn height(&self) -> usize {
-        let mut height = 1;
-        let mut ptr = self;
-        loop {
-            if let Some(Tree::Internal(n)) = ptr.children.get(0).map(|f| f.as_ref()) {
-                ptr = n;
-                height += 1;
-            } else {
-                return height;
-            }
+        self.height
+    }
+
+    /// Makes a node of the same shape as this one, with the given children in place of its own. The new children must have the same lengths and heights as the old ones, but may be measured differently.
+    #[cfg(feature = "rayon")]
+    pub(crate) fn with_children<U: Clone, N: Measure<U>>(
+        &self,
+        children: ArrayVec<SharedPointer<Tree<U, ORD, LEAF, P, N>, P>, ORD>,
+    ) -> Internal<U, ORD, LEAF, P, N> {
+        Internal {
+            measures: children.iter().map(|c| c.measure()).collect(),
+            children,
+            sizes: self.sizes.clone(),
+            height: self.height,
+            dense: self.dense,
+            root: self.root,
+            #[cfg(feature = "merkle")]
+            hash: Default::default(),
+            #[cfg(feature = "merkle")]
+            chunking: Default::default(),
         }
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::Tree;
+    use crate::{Tree, ViolationKind};
 
-    use std::sync::Arc;
+    use archery::{ArcK, SharedPointer};
 
     use log::LevelFilter;
 
@@ -705,7 +1454,7 @@ mod tests {
             .try_init();
     }
 
-    fn testvec(n: usize) -> Tree<usize, 5> {
+    fn testvec(n: usize) -> Tree<usize, 5, 5, ArcK, ()> {
         let mut tree = Tree::new();
         for i in 0..n {
             let idx = tree.len();
@@ -716,7 +1465,7 @@ mod tests {
 
     #[test]
     fn basic_insertion() {
-        let mut tree: Tree<usize, 5> = Tree::new();
+        let mut tree: Tree<usize, 5, 5, ArcK, ()> = Tree::new();
         let mut vec = Vec::new();
         for i in 0..20 {
             let idx = tree.len() / 2;
@@ -725,14 +1474,64 @@ mod tests {
             vec.insert(idx, i)
         }
         tree.take_head(5);
-        Arc::new(tree).eprint_graphviz();
+        tree.eprint_graphviz();
+    }
+
+    #[test]
+    fn collapse_root() {
+        let mut tree: Tree<usize, 5, 5, ArcK, ()> = testvec(30);
+        let height = tree.height();
+        tree.pad_once();
+        tree.pad_once();
+        assert_eq!(tree.height(), height + 2);
+        tree.collapse_root();
+        tree.check_invariants();
+        assert_eq!(tree.height(), height);
+        assert_eq!(tree.get(29), Some(&29));
+    }
+
+    #[test]
+    fn validate_reports_path() {
+        let mut tree: Tree<usize, 5, 5, ArcK, ()> = testvec(100);
+        assert!(tree.validate(&mut Vec::new()).is_ok());
+        let root = tree.unwrap_internal();
+        root.sizes[1..].iter_mut().for_each(|s| *s += 1);
+        let child = SharedPointer::make_mut(&mut root.children[1]).unwrap_internal();
+        let last = child.sizes.len() - 1;
+        child.sizes[last] += 1;
+        let violation = tree.validate(&mut Vec::new()).unwrap_err();
+        assert_eq!(violation.path, vec![1]);
+        assert!(matches!(
+            violation.kind,
+            ViolationKind::LengthMismatch { .. }
+        ));
+
+        let root = tree.unwrap_internal();
+        root.children.clear();
+        root.sizes.clear();
+        root.measures.clear();
+        let violation = tree.validate(&mut Vec::new()).unwrap_err();
+        assert_eq!(violation.path, Vec::<usize>::new());
+        assert_eq!(violation.kind, ViolationKind::Childless);
+    }
+
+    #[test]
+    fn dense_lookup() {
+        let mut tree: Tree<usize, 5, 5, ArcK, ()> = Tree::build(0..125);
+        assert!(tree.unwrap_internal().dense);
+        tree.insert(3, 1000);
+        tree.check_invariants();
+        assert!(!tree.unwrap_internal().dense);
+        assert_eq!(tree.get(3), Some(&1000));
+        assert_eq!(tree.get(124), Some(&123));
+        assert_eq!(tree.get(125), Some(&124));
     }
 
     #[test]
     fn concat() {
         init_logs();
-        let mut tree: Tree<usize, 5> = testvec(125);
+        let mut tree: Tree<usize, 5, 5, ArcK, ()> = testvec(125);
         tree.concat(testvec(1));
-        Arc::new(tree).eprint_graphviz();
+        tree.eprint_graphviz();
     }
 }
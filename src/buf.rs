@@ -0,0 +1,146 @@
+use alloc::vec;
+use core::ops::RangeBounds;
+
+use ::bytes::{buf::UninitSlice, Buf, BufMut, Bytes};
+use archery::{ArcK, SharedPointer, SharedPointerKind};
+
+use crate::{range_bounds, ByteCursor, CatVec, Measure};
+
+impl<const ORD: usize, const LEAF: usize> CatVec<u8, ORD, LEAF, ArcK> {
+    /// Copies a range of the vector into [Bytes]. A range within a single leaf isn't copied at all: the [Bytes] shares the leaf and keeps it alive. Panics if the range is out of bounds.
+    ///
+    /// Going the other way, [Bytes] converts into a vector through the usual `From` impl for byte slices, which copies, since leaves keep their elements inline.
+    pub fn to_bytes(&self, range: impl RangeBounds<usize>) -> Bytes {
+        let (start, end) = range_bounds(range, self.len());
+        assert!(
+            start <= end && end <= self.len(),
+            "range {}..{} out of bounds for a vector of length {}",
+            start,
+            end,
+            self.len()
+        );
+        if start == end {
+            return Bytes::new();
+        }
+        let (leaf_start, leaf) = self.shared_leaf_at(start).unwrap();
+        if end - leaf_start <= leaf.len() {
+            return Bytes::from_owner(leaf.slice(start - leaf_start..end - leaf_start));
+        }
+        let mut out = vec![0; end - start];
+        self.copy_to_slice(start..end, &mut out);
+        out.into()
+    }
+}
+
+/// Reads a leaf at a time: each [Buf::chunk] is the rest of the current leaf.
+impl<const ORD: usize, const LEAF: usize, P: SharedPointerKind, M: Measure<u8>> Buf
+    for ByteCursor<ORD, LEAF, P, M>
+{
+    fn remaining(&self) -> usize {
+        (self.get_ref().len() as u64).saturating_sub(self.position()) as usize
+    }
+
+    fn chunk(&self) -> &[u8] {
+        self.remaining_in_leaf()
+    }
+
+    fn advance(&mut self, cnt: usize) {
+        assert!(
+            cnt <= self.remaining(),
+            "cannot advance past the end of the buffer"
+        );
+        self.set_position(self.position() + cnt as u64);
+    }
+}
+
+/// Appends to the end of the vector. Each [BufMut::chunk_mut] is the spare room in the tail buffer, which is moved into the tree whenever it fills up.
+unsafe impl<const ORD: usize, const LEAF: usize, P: SharedPointerKind, M: Measure<u8>> BufMut
+    for CatVec<u8, ORD, LEAF, P, M>
+{
+    fn remaining_mut(&self) -> usize {
+        isize::MAX as usize - self.len()
+    }
+
+    unsafe fn advance_mut(&mut self, cnt: usize) {
+        // chunk_mut has already made the tail unique, so this never copies it and loses the newly written bytes
+        let tail = SharedPointer::make_mut(&mut self.tail);
+        assert!(
+            cnt <= tail.remaining_capacity(),
+            "cannot advance past the end of the chunk"
+        );
+        tail.set_len(tail.len() + cnt);
+    }
+
+    fn chunk_mut(&mut self) -> &mut UninitSlice {
+        if self.tail.is_full() {
+            self.flush_tail();
+        }
+        let tail = SharedPointer::make_mut(&mut self.tail);
+        let len = tail.len();
+        // SAFETY: the tail buffer has room for LEAF bytes, of which the first len are in use
+        unsafe { UninitSlice::from_raw_parts_mut(tail.as_mut_ptr().add(len), LEAF - len) }
+    }
+
+    fn put_slice(&mut self, src: &[u8]) {
+        self.extend_from_slice(src)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ::bytes::{Buf, BufMut, Bytes};
+
+    use crate::{ByteCursor, CatBytes};
+
+    #[test]
+    fn buf_traits() {
+        let mut v: CatBytes<4, 8> = CatBytes::new();
+        let mut shadow = Vec::new();
+        for i in 0..100u32 {
+            v.put_u32(i);
+            v.put_u8(i as u8);
+            v.put_slice(&[1, 2, 3]);
+            shadow.extend_from_slice(&i.to_be_bytes());
+            shadow.push(i as u8);
+            shadow.extend_from_slice(&[1, 2, 3]);
+        }
+        let w = v.clone();
+        v.put_bytes(7, 20);
+        shadow.extend_from_slice(&[7; 20]);
+        v.check_invariants();
+        assert_eq!(Vec::from(v.clone()), shadow);
+        assert_eq!(w.len(), 800);
+        let mut cursor = ByteCursor::new(v);
+        assert_eq!(cursor.remaining(), shadow.len());
+        assert!(cursor.chunk().len() <= 8);
+        for i in 0..100u32 {
+            assert_eq!(cursor.get_u32(), i);
+            assert_eq!(cursor.get_u8(), i as u8);
+            cursor.advance(3);
+        }
+        assert_eq!(cursor.copy_to_bytes(20).as_ref(), &[7; 20][..]);
+        assert!(!cursor.has_remaining());
+    }
+
+    #[test]
+    fn to_bytes() {
+        let frames: Vec<Bytes> = (0..50u8)
+            .map(|i| Bytes::from(vec![i; i as usize % 13]))
+            .collect();
+        let mut v: CatBytes<4, 8> = CatBytes::new();
+        for frame in &frames {
+            v.append(frame.clone().into());
+        }
+        v.check_invariants();
+        let shadow = frames.concat();
+        assert_eq!(Vec::from(v.clone()), shadow);
+        for (start, end) in [(0, 0), (0, 3), (10, 11), (5, 200), (0, shadow.len())] {
+            assert_eq!(v.to_bytes(start..end), shadow[start..end]);
+        }
+        let (leaf_start, leaf) = v.shared_leaf_at(100).unwrap();
+        let bytes = v.to_bytes(leaf_start..leaf_start + leaf.len());
+        assert_eq!(bytes.as_ptr(), leaf.as_ptr());
+        drop(v);
+        assert_eq!(bytes, &shadow[leaf_start..leaf_start + leaf.len()]);
+    }
+}
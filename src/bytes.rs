@@ -0,0 +1,215 @@
+use alloc::{vec, vec::Vec};
+use core::ops::RangeBounds;
+
+use archery::{ArcK, SharedPointerKind};
+
+use crate::{btree::Leaves, range_bounds, CatVec, Measure};
+
+/// A [CatVec] of bytes, for assembling buffers out of pieces. Byte vectors get bulk operations that copy whole leaves at a time with `memcpy`, such as [CatVec::copy_to_slice], and can be searched for byte strings with [CatVec::find]. Leaves default to 256 bytes, which suits byte-sized elements better than the usual 32.
+pub type CatBytes<const ORD: usize = 32, const LEAF: usize = 256, P = ArcK, M = ()> =
+    CatVec<u8, ORD, LEAF, P, M>;
+
+impl<const ORD: usize, const LEAF: usize, P: SharedPointerKind, M: Measure<u8>>
+    CatVec<u8, ORD, LEAF, P, M>
+{
+    /// Copies the bytes in a range into a slice of the same length, a leaf at a time. Panics if the range is out of bounds or its length differs from the slice's.
+    pub fn copy_to_slice(&self, range: impl RangeBounds<usize>, out: &mut [u8]) {
+        let (start, end) = range_bounds(range, self.len());
+        assert!(
+            start <= end && end <= self.len(),
+            "range {}..{} out of bounds for a vector of length {}",
+            start,
+            end,
+            self.len()
+        );
+        assert_eq!(
+            end - start,
+            out.len(),
+            "range and destination slice have different lengths"
+        );
+        let mut pos = start;
+        let mut written = 0;
+        while pos < end {
+            let (leaf_start, leaf) = self.leaf_at(pos);
+            let leaf = &leaf[pos - leaf_start..];
+            let n = leaf.len().min(end - pos);
+            out[written..written + n].copy_from_slice(&leaf[..n]);
+            pos += n;
+            written += n;
+        }
+    }
+
+    /// Returns the position of the first occurrence of `needle`, which may span any number of leaves. An empty needle is found at position 0.
+    pub fn find(&self, needle: &[u8]) -> Option<usize> {
+        self.find_iter(needle).next()
+    }
+
+    /// Returns an iterator over the positions of the non-overlapping occurrences of `needle`, from left to right, as with [str::match_indices]. The search runs in a single pass over the leaves, in time linear in the length of the vector plus that of the needle.
+    pub fn find_iter<'a>(&'a self, needle: &'a [u8]) -> FindIter<'a, ORD, LEAF, P, M> {
+        FindIter {
+            leaves: self.leaves(),
+            leaf: &[],
+            offset: 0,
+            len: self.len(),
+            needle,
+            fail: failure_table(needle),
+            matched: 0,
+        }
+    }
+}
+
+/// An iterator over the positions where a byte string occurs in a [CatBytes], returned by [CatVec::find_iter].
+pub struct FindIter<
+    'a,
+    const ORD: usize,
+    const LEAF: usize = 256,
+    P: SharedPointerKind = ArcK,
+    M: Measure<u8> = (),
+> {
+    leaves: Leaves<'a, u8, ORD, LEAF, P, M>,
+    /// The unsearched rest of the current leaf.
+    leaf: &'a [u8],
+    /// The position in the vector of the start of `leaf`.
+    offset: usize,
+    len: usize,
+    needle: &'a [u8],
+    /// For each prefix of the needle, the length of the longest proper prefix of it that is also a suffix of it: the Knuth-Morris-Pratt failure function.
+    fail: Vec<usize>,
+    /// How many bytes of the needle the bytes before `leaf` end with.
+    matched: usize,
+}
+
+impl<'a, const ORD: usize, const LEAF: usize, P: SharedPointerKind, M: Measure<u8>>
+    FindIter<'a, ORD, LEAF, P, M>
+{
+    fn advance(&mut self, n: usize) {
+        self.leaf = &self.leaf[n..];
+        self.offset += n;
+    }
+}
+
+impl<'a, const ORD: usize, const LEAF: usize, P: SharedPointerKind, M: Measure<u8>> Iterator
+    for FindIter<'a, ORD, LEAF, P, M>
+{
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        if self.needle.is_empty() {
+            // The empty string occurs at every position, including the end.
+            let pos = self.offset;
+            if pos > self.len {
+                return None;
+            }
+            self.offset += 1;
+            return Some(pos);
+        }
+        loop {
+            if self.leaf.is_empty() {
+                self.leaf = self.leaves.next()?;
+            }
+            if self.matched == 0 {
+                // Nothing is partially matched, so skip straight to the next byte that can start a match.
+                match self.leaf.iter().position(|&b| b == self.needle[0]) {
+                    Some(i) => self.advance(i),
+                    None => {
+                        self.advance(self.leaf.len());
+                        continue;
+                    }
+                }
+            }
+            let b = self.leaf[0];
+            self.advance(1);
+            while self.matched > 0 && self.needle[self.matched] != b {
+                self.matched = self.fail[self.matched - 1];
+            }
+            if self.needle[self.matched] == b {
+                self.matched += 1;
+            }
+            if self.matched == self.needle.len() {
+                self.matched = 0;
+                return Some(self.offset - self.needle.len());
+            }
+        }
+    }
+}
+
+/// Computes the Knuth-Morris-Pratt failure function of a needle.
+fn failure_table(needle: &[u8]) -> Vec<usize> {
+    let mut fail = vec![0; needle.len()];
+    let mut k = 0;
+    for i in 1..needle.len() {
+        while k > 0 && needle[i] != needle[k] {
+            k = fail[k - 1];
+        }
+        if needle[i] == needle[k] {
+            k += 1;
+        }
+        fail[i] = k;
+    }
+    fail
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CatBytes;
+
+    #[test]
+    fn bulk_bytes() {
+        let mut shadow = Vec::new();
+        let mut v: CatBytes<4, 16> = CatBytes::new();
+        for i in 0..200usize {
+            let piece: Vec<u8> = (0..(i * 7) % 53).map(|j| (i + j) as u8).collect();
+            v.extend_from_slice(&piece);
+            shadow.extend_from_slice(&piece);
+            if i % 17 == 0 {
+                v.push_front(i as u8);
+                shadow.insert(0, i as u8);
+            }
+        }
+        v.check_invariants();
+        assert_eq!(Vec::from(v.clone()), shadow);
+        for (start, end) in [(0, 0), (0, 10), (5, 300), (100, shadow.len())] {
+            let mut out = vec![0; end - start];
+            v.copy_to_slice(start..end, &mut out);
+            assert_eq!(out, &shadow[start..end]);
+        }
+    }
+
+    #[test]
+    fn find() {
+        // A small alphabet, so that partial matches and matches across leaves are common.
+        let shadow: Vec<u8> = (0..5000u32)
+            .map(|i| b"ab"[(i * i / 7 % 3 % 2) as usize])
+            .collect();
+        let mut v: CatBytes<4, 16> = shadow[5..].into();
+        for &b in shadow[..5].iter().rev() {
+            v.push_front(b);
+        }
+        for needle in [
+            &b"a"[..],
+            b"ab",
+            b"aab",
+            b"abab",
+            b"bbabbab",
+            b"aaaaaaaaaaaaaaaaaaaaa",
+            b"c",
+        ] {
+            let mut expected = Vec::new();
+            let mut i = 0;
+            while i + needle.len() <= shadow.len() {
+                if &shadow[i..i + needle.len()] == needle {
+                    expected.push(i);
+                    i += needle.len();
+                } else {
+                    i += 1;
+                }
+            }
+            assert_eq!(v.find_iter(needle).collect::<Vec<_>>(), expected);
+            assert_eq!(v.find(needle), expected.first().copied());
+        }
+        let short: CatBytes = b"xyz"[..].into();
+        assert_eq!(short.find_iter(b"").collect::<Vec<_>>(), [0, 1, 2, 3]);
+        assert_eq!(short.find(b""), Some(0));
+        assert_eq!(CatBytes::<4>::new().find(b"x"), None);
+    }
+}
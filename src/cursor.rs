@@ -0,0 +1,345 @@
+use alloc::vec::Vec;
+use core::ops::Range;
+
+use archery::{ArcK, SharedPointerKind};
+
+use crate::{btree::Tree, CatVec, Measure};
+
+/// A read-only cursor into a [crate::CatVec] that remembers the leaf it is in, so that moving to nearby positions doesn't descend from the root every time.
+///
+/// The cursor can sit at any position from `0` up to and including the length of the vector; the position equal to the length is "past the end" and has no current element.
+pub struct Cursor<
+    'a,
+    T: Clone,
+    const ORD: usize,
+    const LEAF: usize = 32,
+    P: SharedPointerKind = ArcK,
+    M: Measure<T> = (),
+> {
+    root: &'a Tree<T, ORD, LEAF, P, M>,
+    /// The vector's head and tail buffers, which come before and after everything in the tree.
+    head: &'a [T],
+    tail: &'a [T],
+    /// Path from the root down to the current leaf.
+    path: Vec<PathEntry<'a, T, ORD, LEAF, P, M>>,
+    leaf: &'a [T],
+    leaf_start: usize,
+    pos: usize,
+}
+
+impl<'a, T: Clone, const ORD: usize, const LEAF: usize, P: SharedPointerKind, M: Measure<T>> Clone
+    for Cursor<'a, T, ORD, LEAF, P, M>
+{
+    fn clone(&self) -> Self {
+        Self {
+            root: self.root,
+            head: self.head,
+            tail: self.tail,
+            path: self.path.clone(),
+            leaf: self.leaf,
+            leaf_start: self.leaf_start,
+            pos: self.pos,
+        }
+    }
+}
+
+/// One internal node along a cursor's path.
+struct PathEntry<
+    'a,
+    T: Clone,
+    const ORD: usize,
+    const LEAF: usize,
+    P: SharedPointerKind,
+    M: Measure<T>,
+> {
+    node: &'a Tree<T, ORD, LEAF, P, M>,
+    /// Index of the child the path goes into.
+    idx: usize,
+    /// Position of the node's first element.
+    start: usize,
+    len: usize,
+}
+
+impl<'a, T: Clone, const ORD: usize, const LEAF: usize, P: SharedPointerKind, M: Measure<T>> Clone
+    for PathEntry<'a, T, ORD, LEAF, P, M>
+{
+    fn clone(&self) -> Self {
+        Self {
+            node: self.node,
+            idx: self.idx,
+            start: self.start,
+            len: self.len,
+        }
+    }
+}
+
+impl<'a, T: Clone, const ORD: usize, const LEAF: usize, P: SharedPointerKind, M: Measure<T>>
+    PathEntry<'a, T, ORD, LEAF, P, M>
+{
+    fn new(node: &'a Tree<T, ORD, LEAF, P, M>, start: usize) -> Self {
+        Self {
+            node,
+            idx: 0,
+            start,
+            len: node.len(),
+        }
+    }
+}
+
+impl<'a, T: Clone, const ORD: usize, const LEAF: usize, P: SharedPointerKind, M: Measure<T>>
+    Cursor<'a, T, ORD, LEAF, P, M>
+{
+    pub(crate) fn new(
+        root: &'a Tree<T, ORD, LEAF, P, M>,
+        head: &'a [T],
+        tail: &'a [T],
+        pos: usize,
+    ) -> Self {
+        let mut cursor = Self {
+            root,
+            head,
+            tail,
+            path: Vec::new(),
+            leaf: &[],
+            leaf_start: 0,
+            pos: 0,
+        };
+        cursor.seek(pos);
+        cursor
+    }
+
+    /// The position of the cursor.
+    pub fn position(&self) -> usize {
+        self.pos
+    }
+
+    /// The element under the cursor, or None if the cursor is past the end.
+    pub fn current(&self) -> Option<&'a T> {
+        self.pos
+            .checked_sub(self.leaf_start)
+            .and_then(|i| self.leaf.get(i))
+    }
+
+    /// Moves the cursor forward by one, returning the element now under it.
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> Option<&'a T> {
+        self.seek(self.pos + 1)
+    }
+
+    /// Moves the cursor back by one, returning the element now under it. Does nothing at position zero.
+    pub fn prev(&mut self) -> Option<&'a T> {
+        self.seek(self.pos.saturating_sub(1))
+    }
+
+    /// Moves the cursor to the given position, clamped to the length of the vector, returning the element now under it. Cost is proportional to how far up the tree the old and new positions diverge.
+    pub fn seek(&mut self, pos: usize) -> Option<&'a T> {
+        let tree_end = self.head.len() + self.root.len();
+        self.pos = pos.min(tree_end + self.tail.len());
+        if self.pos >= self.leaf_start && self.pos < self.leaf_start + self.leaf.len() {
+            return self.current();
+        }
+        if self.pos < self.head.len() {
+            self.leaf = self.head;
+            self.leaf_start = 0;
+            return self.current();
+        }
+        if self.pos >= tree_end {
+            self.leaf = self.tail;
+            self.leaf_start = tree_end;
+            return self.current();
+        }
+        // climb until we're in a node that covers the position
+        while let Some(top) = self.path.last() {
+            if self.pos >= top.start && self.pos < top.start + top.len {
+                break;
+            }
+            self.path.pop();
+        }
+        if self.path.is_empty() {
+            match self.root {
+                Tree::Internal(_) => self.path.push(PathEntry::new(self.root, self.head.len())),
+                Tree::Array(arr) => {
+                    self.leaf = arr;
+                    self.leaf_start = self.head.len();
+                    return self.current();
+                }
+            }
+        }
+        // descend down to the leaf
+        loop {
+            let top = self.path.last_mut().unwrap();
+            let (idx, offset) = top.node.find_child(self.pos - top.start).unwrap();
+            top.idx = idx;
+            let offset = top.start + offset;
+            let child: &'a Tree<T, ORD, LEAF, P, M> = &top.node.children().unwrap()[idx];
+            match child {
+                Tree::Internal(_) => self.path.push(PathEntry::new(child, offset)),
+                Tree::Array(arr) => {
+                    self.leaf = arr;
+                    self.leaf_start = offset;
+                    return self.current();
+                }
+            }
+        }
+    }
+}
+
+/// A cursor into a [crate::CatVec] that supports editing at its position.
+///
+/// Edits are made to a detached copy of the leaf around the cursor, so a burst of edits in one place doesn't touch the tree at all. The edited region is spliced back into the tree, rebalancing once, when the cursor moves out of it, an edit happens somewhere else, or the cursor is dropped.
+pub struct CursorMut<
+    'a,
+    T: Clone,
+    const ORD: usize,
+    const LEAF: usize = 32,
+    P: SharedPointerKind = ArcK,
+    M: Measure<T> = (),
+> {
+    vec: &'a mut CatVec<T, ORD, LEAF, P, M>,
+    /// The range of the tree that `buffer` replaces, if a region is loaded.
+    region: Option<Range<usize>>,
+    buffer: Vec<T>,
+    pos: usize,
+}
+
+impl<'a, T: Clone, const ORD: usize, const LEAF: usize, P: SharedPointerKind, M: Measure<T>>
+    CursorMut<'a, T, ORD, LEAF, P, M>
+{
+    pub(crate) fn new(vec: &'a mut CatVec<T, ORD, LEAF, P, M>, pos: usize) -> Self {
+        let pos = pos.min(vec.len());
+        Self {
+            vec,
+            region: None,
+            buffer: Vec::new(),
+            pos,
+        }
+    }
+
+    /// The position of the cursor.
+    pub fn position(&self) -> usize {
+        self.pos
+    }
+
+    /// The current length of the vector, including pending edits.
+    pub fn len(&self) -> usize {
+        match &self.region {
+            Some(region) => self.vec.len() - region.len() + self.buffer.len(),
+            None => self.vec.len(),
+        }
+    }
+
+    /// Returns true if the vector, including pending edits, is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The element under the cursor, or None if the cursor is past the end.
+    pub fn current(&self) -> Option<&T> {
+        self.get(self.pos)
+    }
+
+    /// Moves the cursor forward by one, returning the element now under it.
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> Option<&T> {
+        self.seek(self.pos + 1)
+    }
+
+    /// Moves the cursor back by one, returning the element now under it. Does nothing at position zero.
+    pub fn prev(&mut self) -> Option<&T> {
+        self.seek(self.pos.saturating_sub(1))
+    }
+
+    /// Moves the cursor to the given position, clamped to the length of the vector, returning the element now under it. Leaving the edited region flushes it.
+    pub fn seek(&mut self, pos: usize) -> Option<&T> {
+        self.pos = pos.min(self.len());
+        if let Some(region) = &self.region {
+            if self.pos < region.start || self.pos > region.start + self.buffer.len() {
+                self.flush();
+            }
+        }
+        self.current()
+    }
+
+    /// Inserts an element before the cursor. The cursor stays on the same element.
+    pub fn insert_before(&mut self, val: T) {
+        let i = self.load(false);
+        self.buffer.insert(i, val);
+        self.pos += 1;
+    }
+
+    /// Inserts an element after the cursor. The cursor stays on the same element; if it is past the end, it ends up on the new element.
+    pub fn insert_after(&mut self, val: T) {
+        // The new element goes after the one under the cursor, so that element has to be loaded, unless there is none.
+        let i = self.load(self.pos < self.len());
+        let i = (i + 1).min(self.buffer.len());
+        self.buffer.insert(i, val);
+    }
+
+    /// Removes and returns the element under the cursor. The cursor ends up on the element after it.
+    pub fn remove_current(&mut self) -> Option<T> {
+        if self.pos == self.len() {
+            return None;
+        }
+        let i = self.load(true);
+        Some(self.buffer.remove(i))
+    }
+
+    /// Replaces the element under the cursor, returning the old one. Returns the given value back if the cursor is past the end.
+    pub fn replace(&mut self, val: T) -> Result<T, T> {
+        if self.pos == self.len() {
+            return Err(val);
+        }
+        let i = self.load(true);
+        Ok(core::mem::replace(&mut self.buffer[i], val))
+    }
+
+    /// Splices any pending edits back into the tree.
+    pub fn flush(&mut self) {
+        if let Some(region) = self.region.take() {
+            let right = self.vec.split_off(region.end);
+            self.vec.slice_into(..region.start);
+            self.vec
+                .append(CatVec::from(core::mem::take(&mut self.buffer)));
+            self.vec.append(right);
+        }
+    }
+
+    fn get(&self, pos: usize) -> Option<&T> {
+        match &self.region {
+            Some(region) if pos >= region.start => {
+                let i = pos - region.start;
+                if i < self.buffer.len() {
+                    self.buffer.get(i)
+                } else {
+                    self.vec.get(i - self.buffer.len() + region.end)
+                }
+            }
+            _ => self.vec.get(pos),
+        }
+    }
+
+    /// Makes sure the cursor position lies within the loaded region, flushing and loading a new one if needed, and returns the cursor's index into the buffer. Unless `need_element` is set, the position just past the end of the region counts as within it.
+    fn load(&mut self, need_element: bool) -> usize {
+        if let Some(region) = &self.region {
+            let end = region.start + self.buffer.len();
+            let in_region =
+                self.pos >= region.start && (self.pos < end || (!need_element && self.pos == end));
+            if in_region && self.buffer.len() <= LEAF * 4 {
+                return self.pos - region.start;
+            }
+            self.flush();
+        }
+        let (start, leaf) = self.vec.leaf_at(self.pos);
+        self.buffer = leaf.to_vec();
+        self.region = Some(start..start + leaf.len());
+        self.pos - start
+    }
+}
+
+impl<'a, T: Clone, const ORD: usize, const LEAF: usize, P: SharedPointerKind, M: Measure<T>> Drop
+    for CursorMut<'a, T, ORD, LEAF, P, M>
+{
+    fn drop(&mut self) {
+        self.flush()
+    }
+}
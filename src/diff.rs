@@ -0,0 +1,611 @@
+use alloc::{vec, vec::Vec};
+use core::ops::Range;
+
+use archery::SharedPointerKind;
+
+use crate::{btree::Tree, CatVec, Measure};
+
+/// The edit script turning one vector into another, as produced by [CatVec::diff] and replayed by [CatVec::apply].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Patch<T> {
+    /// Length of the vector the patch was made against.
+    pub base_len: usize,
+    pub edits: Vec<Edit<T>>,
+}
+
+impl<T> Patch<T> {
+    /// Returns true if the patch makes no changes.
+    pub fn is_empty(&self) -> bool {
+        self.edits.is_empty()
+    }
+}
+
+/// An error applying a [Patch].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PatchError {
+    /// The vector doesn't have the length the patch was made against.
+    BaseMismatch { expected: usize, actual: usize },
+    /// An edit refers to positions past the end of the vector.
+    OutOfBounds { index: usize, len: usize },
+}
+
+impl core::fmt::Display for PatchError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            PatchError::BaseMismatch { expected, actual } => write!(
+                f,
+                "patch was made against a vector of length {}, but got length {}",
+                expected, actual
+            ),
+            PatchError::OutOfBounds { index, len } => write!(
+                f,
+                "patch edit at index {} out of bounds for length {}",
+                index, len
+            ),
+        }
+    }
+}
+
+impl core::error::Error for PatchError {}
+
+/// A single step of an edit script. Indices refer to the vector as it is after all previous edits of the script have been applied.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Edit<T> {
+    /// Insert the given values at an index.
+    Insert { index: usize, values: Vec<T> },
+    /// Delete `len` elements starting from an index.
+    Delete { index: usize, len: usize },
+    /// Overwrite elements starting from an index with the given values.
+    Replace { index: usize, values: Vec<T> },
+}
+
+/// A change to a range of a base vector: the elements in `base` are replaced by `values`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Hunk<T> {
+    pub base: Range<usize>,
+    pub values: Vec<T>,
+}
+
+/// Two changes made to overlapping parts of the base vector in a [CatVec::merge3].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Conflict<T> {
+    pub ours: Hunk<T>,
+    pub theirs: Hunk<T>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Keep,
+    Delete,
+    Insert,
+}
+
+impl<
+        T: Clone + PartialEq,
+        const ORD: usize,
+        const LEAF: usize,
+        P: SharedPointerKind,
+        M: Measure<T>,
+    > CatVec<T, ORD, LEAF, P, M>
+{
+    /// Computes an edit script that turns this vector into the other one.
+    ///
+    /// Subtrees and leaves the two vectors share are matched up by pointer and skipped whole, wherever they are, so diffing two versions of a big vector costs roughly in proportion to what actually changed. Whatever isn't shared is diffed element-wise with Myers' algorithm, in linear space.
+    pub fn diff(&self, other: &Self) -> Patch<T> {
+        let mut edits = Vec::new();
+        diff_pieces(self.pieces(), other.pieces(), 0, &mut edits);
+        Patch {
+            base_len: self.len(),
+            edits,
+        }
+    }
+
+    /// Applies a patch made by [CatVec::diff], returning the patched vector. Untouched parts of the vector are shared with the result.
+    pub fn apply(&self, patch: &Patch<T>) -> Result<Self, PatchError> {
+        if patch.base_len != self.len() {
+            return Err(PatchError::BaseMismatch {
+                expected: patch.base_len,
+                actual: self.len(),
+            });
+        }
+        let mut result = self.clone();
+        for edit in patch.edits.iter() {
+            let (index, len, values) = match edit {
+                Edit::Insert { index, values } => (*index, 0, values.as_slice()),
+                Edit::Delete { index, len } => (*index, *len, &[][..]),
+                Edit::Replace { index, values } => (*index, values.len(), values.as_slice()),
+            };
+            let end = match index.checked_add(len).filter(|&end| end <= result.len()) {
+                Some(end) => end,
+                None => {
+                    return Err(PatchError::OutOfBounds {
+                        index: index.saturating_add(len),
+                        len: result.len(),
+                    })
+                }
+            };
+            let mut right = result.clone();
+            right.slice_into(end..);
+            result.slice_into(..index);
+            result.append(values.into());
+            result.append(right);
+        }
+        Ok(result)
+    }
+
+    /// Combines two versions derived from a common base version. Changes made by only one side are taken as they are, and the result shares all untouched parts with `base`. If the two sides changed overlapping parts of the base differently, all such conflicts are returned instead.
+    pub fn merge3(base: &Self, ours: &Self, theirs: &Self) -> Result<Self, Vec<Conflict<T>>> {
+        let ours = to_hunks(base.diff(ours).edits);
+        let theirs = to_hunks(base.diff(theirs).edits);
+        let mut merged = Vec::new();
+        let mut conflicts = Vec::new();
+        let (mut i, mut j) = (0, 0);
+        while i < ours.len() && j < theirs.len() {
+            let (o, t) = (&ours[i], &theirs[j]);
+            if o == t {
+                merged.push(o.clone());
+                i += 1;
+                j += 1;
+            } else if conflicting(o, t) {
+                conflicts.push(Conflict {
+                    ours: o.clone(),
+                    theirs: t.clone(),
+                });
+                if o.base.end <= t.base.end {
+                    i += 1;
+                }
+                if t.base.end <= o.base.end {
+                    j += 1;
+                }
+            } else if (o.base.start, o.base.end) < (t.base.start, t.base.end) {
+                merged.push(o.clone());
+                i += 1;
+            } else {
+                merged.push(t.clone());
+                j += 1;
+            }
+        }
+        if !conflicts.is_empty() {
+            return Err(conflicts);
+        }
+        merged.extend_from_slice(&ours[i..]);
+        merged.extend_from_slice(&theirs[j..]);
+        let mut result = Self::new();
+        let mut done = 0;
+        for hunk in merged {
+            let mut untouched = base.clone();
+            untouched.slice_into(done..hunk.base.start);
+            result.append(untouched);
+            result.append(hunk.values.into());
+            done = hunk.base.end;
+        }
+        let mut rest = base.clone();
+        rest.slice_into(done..);
+        result.append(rest);
+        Ok(result)
+    }
+
+    /// Splits the vector into the head, the tree and the tail, leaving out empty leaves.
+    fn pieces(&self) -> Vec<Piece<'_, T, ORD, LEAF, P, M>> {
+        let root = match self.inner.as_ref() {
+            Tree::Array(arr) => Piece::Leaf(arr),
+            tree => Piece::Node(tree),
+        };
+        let mut pieces = vec![Piece::Leaf(&self.head), root, Piece::Leaf(&self.tail)];
+        pieces.retain(|piece| piece.len() > 0);
+        pieces
+    }
+}
+
+/// A run of elements the diff treats as a unit: either a whole subtree or a single leaf. Two pieces are equal only if they are the very same nodes.
+enum Piece<'a, T: Clone, const ORD: usize, const LEAF: usize, P: SharedPointerKind, M: Measure<T>> {
+    Node(&'a Tree<T, ORD, LEAF, P, M>),
+    Leaf(&'a [T]),
+}
+
+impl<'a, T: Clone, const ORD: usize, const LEAF: usize, P: SharedPointerKind, M: Measure<T>>
+    Piece<'a, T, ORD, LEAF, P, M>
+{
+    fn len(&self) -> usize {
+        match self {
+            Piece::Node(tree) => tree.len(),
+            Piece::Leaf(leaf) => leaf.len(),
+        }
+    }
+
+    fn height(&self) -> usize {
+        match self {
+            Piece::Node(tree) => tree.height(),
+            Piece::Leaf(_) => 0,
+        }
+    }
+
+    /// Pushes the children of the piece if it is a node of the given height, or else the piece itself.
+    fn expand_into(self, height: usize, out: &mut Vec<Self>) {
+        match self {
+            Piece::Node(tree) if tree.height() == height => {
+                for child in tree.children().expect("node has no children") {
+                    match child.as_ref() {
+                        Tree::Array(arr) if arr.is_empty() => {}
+                        Tree::Array(arr) => out.push(Piece::Leaf(arr)),
+                        child => out.push(Piece::Node(child)),
+                    }
+                }
+            }
+            piece => out.push(piece),
+        }
+    }
+
+    fn elements(&self, out: &mut Vec<&'a T>) {
+        match self {
+            Piece::Node(tree) => tree.leaves().for_each(|leaf| out.extend(leaf)),
+            Piece::Leaf(leaf) => out.extend(leaf.iter()),
+        }
+    }
+}
+
+impl<'a, T: Clone, const ORD: usize, const LEAF: usize, P: SharedPointerKind, M: Measure<T>>
+    PartialEq for Piece<'a, T, ORD, LEAF, P, M>
+{
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Piece::Node(a), Piece::Node(b)) => a.ptr_eq(b),
+            (Piece::Leaf(a), Piece::Leaf(b)) => same_leaf(a, b),
+            _ => false,
+        }
+    }
+}
+
+/// Appends the edits turning one run of pieces into another, given the position in the edited vector where they start.
+///
+/// The pieces are matched up with Myers' algorithm, by identity. Each stretch between matches is diffed again with its tallest nodes split into their children, so a subtree shared at different depths in the two vectors still gets matched once the nodes above it have been split. Once only leaves are left, the elements are diffed.
+fn diff_pieces<
+    'a,
+    T: Clone + PartialEq,
+    const ORD: usize,
+    const LEAF: usize,
+    P: SharedPointerKind,
+    M: Measure<T>,
+>(
+    a: Vec<Piece<'a, T, ORD, LEAF, P, M>>,
+    b: Vec<Piece<'a, T, ORD, LEAF, P, M>>,
+    start: usize,
+    edits: &mut Vec<Edit<T>>,
+) {
+    let total =
+        |pieces: &[Piece<'a, T, ORD, LEAF, P, M>]| pieces.iter().map(|p| p.len()).sum::<usize>();
+    let elements = |pieces: &[Piece<'a, T, ORD, LEAF, P, M>]| {
+        let mut out = Vec::new();
+        pieces.iter().for_each(|piece| piece.elements(&mut out));
+        out
+    };
+    let height = a
+        .iter()
+        .chain(b.iter())
+        .map(|p| p.height())
+        .max()
+        .unwrap_or(0);
+    if a.is_empty() || b.is_empty() || height == 0 {
+        let (a, b) = (elements(&a), elements(&b));
+        edits.extend(to_edits(&myers(&a, &b), &b, start));
+        return;
+    }
+    let ops = myers(&a, &b);
+    let (mut a, mut b) = (a.into_iter(), b.into_iter());
+    let mut pos = start;
+    let mut i = 0;
+    while i < ops.len() {
+        if ops[i] == Op::Keep {
+            a.next();
+            pos += b.next().expect("kept piece is missing").len();
+            i += 1;
+            continue;
+        }
+        let (mut gap_a, mut gap_b) = (Vec::new(), Vec::new());
+        while i < ops.len() && ops[i] != Op::Keep {
+            match ops[i] {
+                Op::Delete => a
+                    .next()
+                    .expect("deleted piece is missing")
+                    .expand_into(height, &mut gap_a),
+                _ => b
+                    .next()
+                    .expect("inserted piece is missing")
+                    .expand_into(height, &mut gap_b),
+            }
+            i += 1;
+        }
+        let len = total(&gap_b);
+        diff_pieces(gap_a, gap_b, pos, edits);
+        pos += len;
+    }
+}
+
+/// Converts an edit script into hunks against the original vector.
+fn to_hunks<T>(edits: Vec<Edit<T>>) -> Vec<Hunk<T>> {
+    // how much longer the edited vector is than the original, so far
+    let mut delta = 0isize;
+    let mut hunks: Vec<Hunk<T>> = Vec::new();
+    for edit in edits {
+        let (index, len, values) = match edit {
+            Edit::Insert { index, values } => (index, 0, values),
+            Edit::Delete { index, len } => (index, len, Vec::new()),
+            Edit::Replace { index, values } => (index, values.len(), values),
+        };
+        let start = (index as isize - delta) as usize;
+        delta += values.len() as isize - len as isize;
+        // a deletion directly followed by an insertion at the same spot is one change
+        match hunks.last_mut() {
+            Some(last) if last.base.end == start && len == 0 => last.values.extend(values),
+            _ => hunks.push(Hunk {
+                base: start..start + len,
+                values,
+            }),
+        }
+    }
+    hunks
+}
+
+/// Returns true if two hunks against the same base can't both be applied: either their ranges overlap, or both insert at the same point, or one inserts inside the other's range.
+fn conflicting<T>(a: &Hunk<T>, b: &Hunk<T>) -> bool {
+    let overlap = a.base.start.max(b.base.start) < a.base.end.min(b.base.end);
+    let inside = |x: &Hunk<T>, y: &Hunk<T>| {
+        x.base.is_empty() && y.base.start < x.base.start && x.base.start < y.base.end
+    };
+    let same_point = a.base.is_empty() && b.base.is_empty() && a.base.start == b.base.start;
+    overlap || same_point || inside(a, b) || inside(b, a)
+}
+
+/// Returns true if the two leaves are the very same leaf node.
+fn same_leaf<T>(a: &[T], b: &[T]) -> bool {
+    core::ptr::eq(a, b)
+}
+
+/// Myers' O(ND) diff, returning the sequence of operations turning `a` into `b`. Uses the linear space refinement, bisecting the problem at the middle snake of an optimal path, so memory stays proportional to the input however far apart the two sides are.
+fn myers<T: PartialEq>(a: &[T], b: &[T]) -> Vec<Op> {
+    let mut ops = Vec::with_capacity(a.len().max(b.len()));
+    let size = a.len() + b.len() + 3;
+    let (mut forward, mut backward) = (vec![0; size], vec![0; size]);
+    bisect(a, b, &mut forward, &mut backward, &mut ops);
+    // Bisecting can leave insertions before deletions in a run of changes, so put the deletions first, the way the edits expect.
+    for run in ops.split_mut(|op| *op == Op::Keep) {
+        let deleted = run.iter().filter(|op| **op == Op::Delete).count();
+        let (deletions, insertions) = run.split_at_mut(deleted);
+        deletions.fill(Op::Delete);
+        insertions.fill(Op::Insert);
+    }
+    ops
+}
+
+/// Pushes the operations turning `a` into `b`, using the two buffers for the furthest reaching paths.
+fn bisect<T: PartialEq>(
+    a: &[T],
+    b: &[T],
+    forward: &mut [usize],
+    backward: &mut [usize],
+    ops: &mut Vec<Op>,
+) {
+    let prefix = a.iter().zip(b).take_while(|(x, y)| x == y).count();
+    let (a, b) = (&a[prefix..], &b[prefix..]);
+    let suffix = a
+        .iter()
+        .rev()
+        .zip(b.iter().rev())
+        .take_while(|(x, y)| x == y)
+        .count();
+    let (a, b) = (&a[..a.len() - suffix], &b[..b.len() - suffix]);
+    ops.extend(core::iter::repeat_n(Op::Keep, prefix));
+    if a.is_empty() || b.is_empty() {
+        ops.extend(core::iter::repeat_n(Op::Delete, a.len()));
+        ops.extend(core::iter::repeat_n(Op::Insert, b.len()));
+    } else {
+        // Both ends differ, so at least two edits are needed, and each half takes fewer than the whole.
+        let (x, y) = middle_snake(a, b, forward, backward);
+        bisect(&a[..x], &b[..y], forward, backward, ops);
+        bisect(&a[x..], &b[y..], forward, backward, ops);
+    }
+    ops.extend(core::iter::repeat_n(Op::Keep, suffix));
+}
+
+/// Finds a point an optimal path from the start to the end of the edit graph passes through, with about half of its edits on either side, by searching from both ends at once until the searches meet. The backward search works on the reversed sequences, so its diagonal `k` is the forward diagonal `delta - k`.
+fn middle_snake<T: PartialEq>(
+    a: &[T],
+    b: &[T],
+    forward: &mut [usize],
+    backward: &mut [usize],
+) -> (usize, usize) {
+    let (n, m) = (a.len() as isize, b.len() as isize);
+    let delta = n - m;
+    let odd = delta % 2 != 0;
+    let max = (n + m + 1) / 2;
+    // Index of diagonal k in the buffers.
+    let at = |k: isize| (k + max + 1) as usize;
+    forward[at(1)] = 0;
+    backward[at(1)] = 0;
+    // Follows the diagonal k of the edit graph as far as possible on its d-th step, given the furthest points reached on the neighbouring diagonals the step before.
+    let furthest = |v: &[usize], d: isize, k: isize| {
+        if k == -d || (k != d && v[at(k - 1)] < v[at(k + 1)]) {
+            v[at(k + 1)] as isize
+        } else {
+            v[at(k - 1)] as isize + 1
+        }
+    };
+    for d in 0..=max {
+        for k in (-d..=d).step_by(2) {
+            let mut x = furthest(forward, d, k);
+            let mut y = x - k;
+            while x < n && y < m && a[x as usize] == b[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            forward[at(k)] = x as usize;
+            if odd && (delta - k).abs() < d && x + backward[at(delta - k)] as isize >= n {
+                return (x as usize, y as usize);
+            }
+        }
+        for k in (-d..=d).step_by(2) {
+            let mut x = furthest(backward, d, k);
+            let mut y = x - k;
+            while x < n && y < m && a[(n - 1 - x) as usize] == b[(m - 1 - y) as usize] {
+                x += 1;
+                y += 1;
+            }
+            backward[at(k)] = x as usize;
+            if !odd && (delta - k).abs() <= d && x + forward[at(delta - k)] as isize >= n {
+                return ((n - x) as usize, (m - y) as usize);
+            }
+        }
+    }
+    unreachable!("the searches always meet")
+}
+
+/// Groups runs of operations into edits, pairing up deletions and insertions of equal length into replacements.
+fn to_edits<T: Clone>(ops: &[Op], b: &[&T], start: usize) -> Vec<Edit<T>> {
+    let mut edits = Vec::new();
+    let mut index = start;
+    let mut b_idx = 0;
+    let mut i = 0;
+    while i < ops.len() {
+        if ops[i] == Op::Keep {
+            index += 1;
+            b_idx += 1;
+            i += 1;
+            continue;
+        }
+        let deleted = ops[i..].iter().take_while(|op| **op == Op::Delete).count();
+        i += deleted;
+        let inserted = ops[i..].iter().take_while(|op| **op == Op::Insert).count();
+        i += inserted;
+        let values: Vec<T> = b[b_idx..b_idx + inserted]
+            .iter()
+            .map(|x| (*x).clone())
+            .collect();
+        b_idx += inserted;
+        if deleted == inserted {
+            edits.push(Edit::Replace { index, values });
+        } else {
+            if deleted > 0 {
+                edits.push(Edit::Delete {
+                    index,
+                    len: deleted,
+                });
+            }
+            if inserted > 0 {
+                edits.push(Edit::Insert { index, values });
+            }
+        }
+        index += inserted;
+    }
+    edits
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Edit, Patch, PatchError};
+    use crate::CatVec;
+
+    /// Replays an edit script on a plain vector.
+    fn replay(mut v: Vec<usize>, edits: &[Edit<usize>]) -> Vec<usize> {
+        for edit in edits {
+            match edit {
+                Edit::Insert { index, values } => {
+                    v.splice(index..index, values.iter().copied());
+                }
+                Edit::Delete { index, len } => {
+                    v.drain(*index..*index + *len);
+                }
+                Edit::Replace { index, values } => {
+                    v.splice(*index..*index + values.len(), values.iter().copied());
+                }
+            }
+        }
+        v
+    }
+
+    #[test]
+    fn diff_roundtrip() {
+        let base: Vec<usize> = (0..1000).collect();
+        let a: CatVec<usize, 5> = base.clone().into();
+        let mut b = a.clone();
+        b.insert(500, 9999);
+        *b.get_mut(20).unwrap() = 8888;
+        b.slice_into(..900);
+        let patch = a.diff(&b);
+        assert_eq!(patch.edits.len(), 3);
+        assert_eq!(replay(base.clone(), &patch.edits), Vec::from(b.clone()));
+        assert!(a.apply(&patch).unwrap() == b);
+        assert!(a.diff(&a.clone()).is_empty());
+        let c: CatVec<usize, 5> = (500..1200).collect::<Vec<_>>().into();
+        assert_eq!(replay(base, &a.diff(&c).edits), Vec::from(c.clone()));
+        assert_eq!(
+            c.apply(&patch).unwrap_err(),
+            PatchError::BaseMismatch {
+                expected: 1000,
+                actual: 700
+            }
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn patch_serde_roundtrip() {
+        let a: CatVec<usize, 5> = (0..300).collect::<Vec<_>>().into();
+        let mut b = a.clone();
+        b.insert(10, 1000);
+        b.slice_into(..250);
+        *b.get_mut(100).unwrap() = 2000;
+        let patch = a.diff(&b);
+        let json = serde_json::to_string(&patch).unwrap();
+        let decoded: super::Patch<usize> = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded, patch);
+        assert!(a.apply(&decoded).unwrap() == b);
+    }
+
+    #[test]
+    fn merge3() {
+        let base: CatVec<usize, 5> = (0..500).collect::<Vec<_>>().into();
+        let mut ours = base.clone();
+        ours.insert(100, 1000);
+        let mut theirs = base.clone();
+        *theirs.get_mut(300).unwrap() = 2000;
+        theirs.slice_into(..400);
+        let merged = CatVec::merge3(&base, &ours, &theirs).unwrap();
+        merged.check_invariants();
+        let mut expected: Vec<usize> = (0..400).collect();
+        expected.insert(100, 1000);
+        expected[301] = 2000;
+        assert_eq!(Vec::from(merged), expected);
+
+        *ours.get_mut(301).unwrap() = 3000;
+        let conflicts = CatVec::merge3(&base, &ours, &theirs).unwrap_err();
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].ours.values, vec![3000]);
+    }
+
+    #[test]
+    fn patch_out_of_bounds() {
+        let a: CatVec<usize, 5> = (0..10).collect::<Vec<_>>().into();
+        for edits in [
+            vec![Edit::Delete {
+                index: usize::MAX,
+                len: 1,
+            }],
+            vec![Edit::Replace {
+                index: usize::MAX,
+                values: vec![1, 2],
+            }],
+            vec![Edit::Delete { index: 8, len: 3 }],
+        ] {
+            let patch = Patch {
+                base_len: 10,
+                edits,
+            };
+            assert!(matches!(
+                a.apply(&patch),
+                Err(PatchError::OutOfBounds { len: 10, .. })
+            ));
+        }
+    }
+}
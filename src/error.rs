@@ -0,0 +1,62 @@
+/// An error from one of the fallible operations on a [crate::CatVec], such as [crate::CatVec::try_insert], which report bad positions instead of panicking on them.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CatVecError {
+    /// A position is past the end of the vector, or, for operations that take an existing element, at the end.
+    OutOfBounds { idx: usize, len: usize },
+    /// A range starts after it ends, or ends past the end of the vector.
+    InvalidRange {
+        start: usize,
+        end: usize,
+        len: usize,
+    },
+}
+
+impl core::fmt::Display for CatVecError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            CatVecError::OutOfBounds { idx, len } => write!(
+                f,
+                "index {} out of bounds for a vector of length {}",
+                idx, len
+            ),
+            CatVecError::InvalidRange { start, end, len } => SliceError {
+                start: *start,
+                end: *end,
+                len: *len,
+            }
+            .fmt(f),
+        }
+    }
+}
+
+impl core::error::Error for CatVecError {}
+
+/// An error from [crate::CatVec::try_slice_into]: the range starts after it ends, or ends past the end of the vector.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SliceError {
+    pub start: usize,
+    pub end: usize,
+    pub len: usize,
+}
+
+impl core::fmt::Display for SliceError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        if self.start > self.end {
+            write!(f, "range starts at {} but ends at {}", self.start, self.end)
+        } else {
+            write!(
+                f,
+                "range {}..{} out of bounds for a vector of length {}",
+                self.start, self.end, self.len
+            )
+        }
+    }
+}
+
+impl core::error::Error for SliceError {}
+
+impl From<SliceError> for CatVecError {
+    fn from(SliceError { start, end, len }: SliceError) -> Self {
+        CatVecError::InvalidRange { start, end, len }
+    }
+}
@@ -0,0 +1,190 @@
+//! C ABI functions over a byte vector, for embedding in a C host. The vector is handed out as an opaque, heap-allocated [CatVecBytes] handle; every handle from [catvec_new], [catvec_clone] or [catvec_slice] must eventually be passed to [catvec_free]. Handles are independent persistent vectors, so cloning and slicing are cheap and never affect other handles. Functions never unwind into C: they report bad positions through their return values instead.
+//!
+//! The functions are exported unmangled, so a static or dynamic library built from a crate depending on this one with the `ffi` feature exposes them. `include/catvec.h` declares them for C.
+
+use alloc::boxed::Box;
+use core::slice;
+
+use crate::CatVec;
+
+/// An opaque handle to a byte vector, for C code. It holds a `CatVec<u8, 64>`.
+pub struct CatVecBytes {
+    inner: CatVec<u8, 64>,
+}
+
+fn handle(inner: CatVec<u8, 64>) -> *mut CatVecBytes {
+    Box::into_raw(Box::new(CatVecBytes { inner }))
+}
+
+/// Creates an empty vector.
+#[no_mangle]
+pub extern "C" fn catvec_new() -> *mut CatVecBytes {
+    handle(CatVec::new())
+}
+
+/// Creates a new handle to a copy of the vector, in O(1) time.
+///
+/// # Safety
+///
+/// `v` must be a live handle.
+#[no_mangle]
+pub unsafe extern "C" fn catvec_clone(v: *const CatVecBytes) -> *mut CatVecBytes {
+    handle((*v).inner.clone())
+}
+
+/// Frees a handle. Null is allowed and does nothing.
+///
+/// # Safety
+///
+/// `v` must be null or a live handle, which must not be used again.
+#[no_mangle]
+pub unsafe extern "C" fn catvec_free(v: *mut CatVecBytes) {
+    if !v.is_null() {
+        drop(Box::from_raw(v));
+    }
+}
+
+/// The number of bytes in the vector.
+///
+/// # Safety
+///
+/// `v` must be a live handle.
+#[no_mangle]
+pub unsafe extern "C" fn catvec_len(v: *const CatVecBytes) -> usize {
+    (*v).inner.len()
+}
+
+/// Reads the byte at a position into `out`. Returns false, leaving `out` alone, if the position is out of bounds.
+///
+/// # Safety
+///
+/// `v` must be a live handle and `out` must be valid for writing a byte.
+#[no_mangle]
+pub unsafe extern "C" fn catvec_get(v: *const CatVecBytes, idx: usize, out: *mut u8) -> bool {
+    match (*v).inner.get(idx) {
+        Some(&byte) => {
+            *out = byte;
+            true
+        }
+        None => false,
+    }
+}
+
+/// Copies `len` bytes starting at `start` into `out`, a leaf at a time. Returns false, copying nothing, if the range is out of bounds.
+///
+/// # Safety
+///
+/// `v` must be a live handle and `out` must be valid for writing `len` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn catvec_copy_to(
+    v: *const CatVecBytes,
+    start: usize,
+    out: *mut u8,
+    len: usize,
+) -> bool {
+    let v = &(*v).inner;
+    match start.checked_add(len) {
+        Some(end) if end <= v.len() => {
+            if len > 0 {
+                v.copy_to_slice(start..end, slice::from_raw_parts_mut(out, len));
+            }
+            true
+        }
+        _ => false,
+    }
+}
+
+/// Appends a byte.
+///
+/// # Safety
+///
+/// `v` must be a live handle.
+#[no_mangle]
+pub unsafe extern "C" fn catvec_push(v: *mut CatVecBytes, byte: u8) {
+    (*v).inner.push_back(byte)
+}
+
+/// Appends `len` bytes read from `bytes`.
+///
+/// # Safety
+///
+/// `v` must be a live handle and `bytes` must be valid for reading `len` bytes. `bytes` may be null if `len` is zero.
+#[no_mangle]
+pub unsafe extern "C" fn catvec_extend(v: *mut CatVecBytes, bytes: *const u8, len: usize) {
+    if len > 0 {
+        (*v).inner
+            .extend_from_slice(slice::from_raw_parts(bytes, len))
+    }
+}
+
+/// Appends the contents of `other`, which is left as it was. Structure is shared rather than copied, so this takes O(log n) time. `v` and `other` may be the same handle.
+///
+/// # Safety
+///
+/// `v` and `other` must be live handles.
+#[no_mangle]
+pub unsafe extern "C" fn catvec_append(v: *mut CatVecBytes, other: *const CatVecBytes) {
+    let other = (*other).inner.clone();
+    (*v).inner.append(other)
+}
+
+/// Creates a new handle to the bytes from `start` up to but not including `end`, sharing structure with the vector. Returns null if the range is inverted or out of bounds.
+///
+/// # Safety
+///
+/// `v` must be a live handle.
+#[no_mangle]
+pub unsafe extern "C" fn catvec_slice(
+    v: *const CatVecBytes,
+    start: usize,
+    end: usize,
+) -> *mut CatVecBytes {
+    match (*v).inner.try_slice(start..end) {
+        Ok(sliced) => handle(sliced),
+        Err(_) => core::ptr::null_mut(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        catvec_append, catvec_clone, catvec_copy_to, catvec_extend, catvec_free, catvec_get,
+        catvec_len, catvec_new, catvec_push, catvec_slice,
+    };
+
+    #[test]
+    fn c_abi() {
+        unsafe {
+            let v = catvec_new();
+            for i in 0..200u8 {
+                catvec_push(v, i);
+            }
+            catvec_extend(v, b"hello".as_ptr(), 5);
+            catvec_extend(v, core::ptr::null(), 0);
+            assert_eq!(catvec_len(v), 205);
+            let snapshot = catvec_clone(v);
+            catvec_append(v, v);
+            assert_eq!(catvec_len(v), 410);
+            assert_eq!(catvec_len(snapshot), 205);
+
+            let mut byte = 0;
+            assert!(catvec_get(v, 300, &mut byte));
+            assert_eq!(byte, 95);
+            assert!(!catvec_get(v, 410, &mut byte));
+
+            let s = catvec_slice(v, 200, 205);
+            assert!(!s.is_null());
+            let mut out = [0u8; 5];
+            assert!(catvec_copy_to(s, 0, out.as_mut_ptr(), 5));
+            assert_eq!(&out, b"hello");
+            assert!(!catvec_copy_to(s, 1, out.as_mut_ptr(), 5));
+            assert!(catvec_slice(v, 5, 411).is_null());
+            assert!(catvec_slice(v, 6, 5).is_null());
+
+            catvec_free(s);
+            catvec_free(snapshot);
+            catvec_free(v);
+            catvec_free(core::ptr::null_mut());
+        }
+    }
+}
@@ -0,0 +1,304 @@
+use alloc::vec::Vec;
+use core::{
+    convert::{TryFrom, TryInto},
+    hash::Hash,
+    marker::PhantomData,
+    mem::size_of,
+};
+use std::{
+    collections::HashMap,
+    io::{self, Read, Write},
+};
+
+use archery::SharedPointerKind;
+
+use crate::{CatVec, LoadError, Measure, NodeStore, StoredNode};
+
+/// Element types that are plain bytes, which can be written to a file and read back by copying their bytes: integers and arrays of them. Their values can't hold pointers, and every pattern of `size_of::<Self>()` bytes must be a valid value.
+///
+/// # Safety
+///
+/// Implementors must have no padding bytes and no invalid bit patterns. Elements are stored in native byte order, which a [NodeFile] records, so it can only be read on a machine with the same endianness as the one that wrote it.
+pub unsafe trait Pod: Copy + 'static {}
+
+macro_rules! impl_pod {
+    ($($t:ty),*) => {
+        $(unsafe impl Pod for $t {})*
+    };
+}
+
+impl_pod!(u8, u16, u32, u64, u128, i8, i16, i32, i64, i128, f32, f64);
+
+unsafe impl<T: Pod, const N: usize> Pod for [T; N] {}
+
+const MAGIC: &[u8; 8] = b"CATVEC\0\0";
+const VERSION: u32 = 1;
+const HEADER_LEN: usize = 72;
+const INDEX_ENTRY_LEN: usize = 40;
+
+/// A file of vector nodes, as written by [CatVec::write_snapshot], held in a byte buffer. Opening one only reads its header; nodes are found by binary search in an index at the end of the file, and decoded only when a [StoredCatVec](crate::StoredCatVec) or [CatVec::load] asks for them.
+///
+/// Decoding a leaf copies its elements out of the buffer, since the tree's leaves own their elements and the elements in the file needn't be aligned. So each leaf a [StoredCatVec](crate::StoredCatVec) read touches costs a copy of up to LEAF elements, and [CatVec::load] copies every element.
+///
+/// All integers are little-endian. The file starts with a 72-byte header: the magic bytes `CATVEC\0\0`, a u32 format version, the u32 size of an element, a byte for the byte order of the elements (0 for little-endian and 1 for big-endian) followed by seven zero bytes, the storage hash of the vector, the u64 number of nodes and the u64 offset of the index. Then come the nodes, each a kind byte (0 for a leaf and 1 for an internal node), a u64 count, and either that many elements or that many u64 lengths each followed by a 32-byte hash. The index lists a 32-byte hash and a u64 offset for every node, sorted by hash. Each node is written once, however many times it is shared.
+///
+/// The version changes whenever the layout does, and readers reject versions they don't know rather than misreading them, so snapshots written by this version stay readable by later ones.
+pub struct NodeFile<T, B> {
+    bytes: B,
+    root: [u8; 32],
+    count: usize,
+    index: usize,
+    _elements: PhantomData<fn() -> T>,
+}
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+/// The byte order elements are written in, as recorded in the header.
+fn byte_order() -> u8 {
+    if cfg!(target_endian = "big") {
+        1
+    } else {
+        0
+    }
+}
+
+fn read_u64(bytes: &[u8], at: usize) -> io::Result<u64> {
+    bytes
+        .get(at..at + 8)
+        .map(|b| u64::from_le_bytes(b.try_into().unwrap()))
+        .ok_or_else(|| invalid("node file is truncated"))
+}
+
+fn read_usize(bytes: &[u8], at: usize) -> io::Result<usize> {
+    usize::try_from(read_u64(bytes, at)?).map_err(|_| invalid("node file offset overflows"))
+}
+
+impl<T: Pod, B: AsRef<[u8]>> NodeFile<T, B> {
+    /// Reads the header of a node file held in a buffer, checking that it is a version this crate understands and that it holds elements of this size, in this machine's byte order.
+    pub fn from_bytes(bytes: B) -> io::Result<Self> {
+        let header = bytes
+            .as_ref()
+            .get(..HEADER_LEN)
+            .ok_or_else(|| invalid("node file is truncated"))?;
+        if &header[..8] != MAGIC {
+            return Err(invalid("not a node file"));
+        }
+        let version = u32::from_le_bytes(header[8..12].try_into().unwrap());
+        if version != VERSION {
+            return Err(invalid("unsupported node file version"));
+        }
+        let size = u32::from_le_bytes(header[12..16].try_into().unwrap());
+        if size as usize != size_of::<T>() {
+            return Err(invalid("node file holds elements of a different size"));
+        }
+        if header[16] != byte_order() {
+            return Err(invalid("node file was written with a different byte order"));
+        }
+        let root = header[24..56].try_into().unwrap();
+        let count = read_usize(header, 56)?;
+        let index = read_usize(header, 64)?;
+        let index_end = count
+            .checked_mul(INDEX_ENTRY_LEN)
+            .and_then(|len| len.checked_add(index))
+            .ok_or_else(|| invalid("node file index overflows"))?;
+        if index_end > bytes.as_ref().len() {
+            return Err(invalid("node file is truncated"));
+        }
+        Ok(Self {
+            bytes,
+            root,
+            count,
+            index,
+            _elements: PhantomData,
+        })
+    }
+
+    /// The [CatVec::storage_hash] of the vector the file was written from.
+    pub fn storage_hash(&self) -> [u8; 32] {
+        self.root
+    }
+
+    /// Finds the offset of the node with a given hash in the index.
+    fn find(&self, hash: &[u8; 32]) -> Option<usize> {
+        let bytes = self.bytes.as_ref();
+        let entry = |i: usize| &bytes[self.index + i * INDEX_ENTRY_LEN..][..INDEX_ENTRY_LEN];
+        let (mut lo, mut hi) = (0, self.count);
+        while lo < hi {
+            let mid = lo + (hi - lo) / 2;
+            match entry(mid)[..32].cmp(&hash[..]) {
+                core::cmp::Ordering::Less => lo = mid + 1,
+                core::cmp::Ordering::Greater => hi = mid,
+                core::cmp::Ordering::Equal => return read_usize(entry(mid), 32).ok(),
+            }
+        }
+        None
+    }
+
+    /// Decodes the node at an offset. A leaf's elements are copied out of the buffer one by one, since they may sit at any alignment in it.
+    fn node_at(&self, at: usize) -> io::Result<StoredNode<T>> {
+        let bytes = self.bytes.as_ref();
+        let kind = *bytes
+            .get(at)
+            .ok_or_else(|| invalid("node file is truncated"))?;
+        let count = read_usize(bytes, at + 1)?;
+        let body = at + 9;
+        let width = match kind {
+            0 => size_of::<T>(),
+            1 => 40,
+            _ => return Err(invalid("unknown node kind")),
+        };
+        let body = count
+            .checked_mul(width)
+            .and_then(|len| bytes.get(body..body.checked_add(len)?))
+            .ok_or_else(|| invalid("node file is truncated"))?;
+        Ok(match kind {
+            0 => StoredNode::Leaf(
+                body.chunks_exact(width.max(1))
+                    .take(count)
+                    // SAFETY: any bytes make a valid Pod value, and read_unaligned doesn't care where they sit
+                    .map(|b| unsafe { core::ptr::read_unaligned(b.as_ptr() as *const T) })
+                    .collect(),
+            ),
+            _ => StoredNode::Internal(
+                body.chunks_exact(40)
+                    .map(|c| Ok((read_usize(c, 0)?, c[8..].try_into().unwrap())))
+                    .collect::<io::Result<_>>()?,
+            ),
+        })
+    }
+}
+
+impl<T: Pod + Hash, const ORD: usize, const LEAF: usize, P: SharedPointerKind, M: Measure<T>>
+    CatVec<T, ORD, LEAF, P, M>
+{
+    /// Writes a snapshot of the vector, in the format described at [NodeFile], and returns its storage hash. Nodes shared within the vector are written once. The nodes are collected and sorted first, so the whole snapshot is built in memory.
+    pub fn write_snapshot(&self, mut out: impl Write) -> io::Result<[u8; 32]> {
+        let mut nodes: HashMap<[u8; 32], StoredNode<T>> = HashMap::new();
+        let root = match self.persist(&mut nodes) {
+            Ok(root) => root,
+            Err(never) => match never {},
+        };
+        let mut nodes: Vec<_> = nodes.into_iter().collect();
+        nodes.sort_unstable_by_key(|a| a.0);
+        let mut body = Vec::new();
+        let mut index = Vec::with_capacity(nodes.len() * INDEX_ENTRY_LEN);
+        for (hash, node) in &nodes {
+            index.extend_from_slice(hash);
+            index.extend_from_slice(&((HEADER_LEN + body.len()) as u64).to_le_bytes());
+            match node {
+                StoredNode::Leaf(items) => {
+                    body.push(0);
+                    body.extend_from_slice(&(items.len() as u64).to_le_bytes());
+                    for x in items {
+                        // SAFETY: Pod values have no padding, so all their bytes are initialized
+                        body.extend_from_slice(unsafe {
+                            core::slice::from_raw_parts(x as *const T as *const u8, size_of::<T>())
+                        });
+                    }
+                }
+                StoredNode::Internal(children) => {
+                    body.push(1);
+                    body.extend_from_slice(&(children.len() as u64).to_le_bytes());
+                    for (len, hash) in children {
+                        body.extend_from_slice(&(*len as u64).to_le_bytes());
+                        body.extend_from_slice(hash);
+                    }
+                }
+            }
+        }
+        let mut header = Vec::with_capacity(HEADER_LEN);
+        header.extend_from_slice(MAGIC);
+        header.extend_from_slice(&VERSION.to_le_bytes());
+        header.extend_from_slice(&(size_of::<T>() as u32).to_le_bytes());
+        header.extend_from_slice(&[byte_order(), 0, 0, 0, 0, 0, 0, 0]);
+        header.extend_from_slice(&root);
+        header.extend_from_slice(&(nodes.len() as u64).to_le_bytes());
+        header.extend_from_slice(&((HEADER_LEN + body.len()) as u64).to_le_bytes());
+        out.write_all(&header)?;
+        out.write_all(&body)?;
+        out.write_all(&index)?;
+        Ok(root)
+    }
+
+    /// Reads a snapshot written by [CatVec::write_snapshot], checking every node against its hash. To read only part of a big snapshot, open it as a [NodeFile] instead.
+    pub fn read_snapshot(mut input: impl Read) -> Result<Self, LoadError<io::Error>> {
+        let mut bytes = Vec::new();
+        input.read_to_end(&mut bytes).map_err(LoadError::Store)?;
+        let file = NodeFile::<T, _>::from_bytes(bytes).map_err(LoadError::Store)?;
+        Self::load(&file.storage_hash(), &file)
+    }
+}
+
+/// A node file is a read-only store.
+impl<T: Pod, B: AsRef<[u8]>> NodeStore<T> for NodeFile<T, B> {
+    type Error = io::Error;
+
+    fn get(&self, hash: &[u8; 32]) -> io::Result<Option<StoredNode<T>>> {
+        self.find(hash).map(|at| self.node_at(at)).transpose()
+    }
+
+    fn put(&mut self, _: [u8; 32], _: StoredNode<T>) -> io::Result<()> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "node files are read-only",
+        ))
+    }
+
+    fn contains(&self, hash: &[u8; 32]) -> io::Result<bool> {
+        Ok(self.find(hash).is_some())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::NodeFile;
+    use crate::{CatVec, StoredCatVec};
+
+    #[test]
+    fn node_file() {
+        let mut v: CatVec<[u16; 3], 4, 4> = (0..2000u16)
+            .map(|i| [i, i * 2, 7])
+            .collect::<Vec<_>>()
+            .into();
+        v.push_front([1, 2, 3]);
+        let mut half = v.clone();
+        half.slice_into(..1000);
+        v.append(half);
+        let mut bytes = Vec::new();
+        let root = v.write_snapshot(&mut bytes).unwrap();
+        let file: NodeFile<[u16; 3], _> = NodeFile::from_bytes(bytes.as_slice()).unwrap();
+        assert_eq!(file.storage_hash(), root);
+        let loaded: CatVec<[u16; 3], 4, 4> = CatVec::load(&root, &file).unwrap();
+        assert_eq!(loaded, v);
+        let stored: StoredCatVec<[u16; 3], _, 4, 4> = StoredCatVec::open(&root, file).unwrap();
+        assert_eq!(stored.get(1500).unwrap(), v.get(1500).copied());
+        assert!(NodeFile::<u32, _>::from_bytes(bytes.as_slice()).is_err());
+        assert!(NodeFile::<[u16; 3], _>::from_bytes(&bytes[..40]).is_err());
+    }
+
+    #[test]
+    fn snapshots() {
+        let mut v: CatVec<u32, 4, 4> = (0..5000).collect::<Vec<_>>().into();
+        v.push_back(9);
+        let mut bytes = Vec::new();
+        v.write_snapshot(&mut bytes).unwrap();
+        let restored: CatVec<u32, 4, 4> = CatVec::read_snapshot(bytes.as_slice()).unwrap();
+        restored.check_invariants();
+        assert_eq!(restored, v);
+
+        let mut newer = bytes.clone();
+        newer[8] += 1;
+        assert!(CatVec::<u32, 4, 4>::read_snapshot(newer.as_slice()).is_err());
+        let mut swapped = bytes.clone();
+        swapped[16] ^= 1;
+        let err = NodeFile::<u32, _>::from_bytes(swapped.as_slice())
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("byte order"));
+        let last = bytes.len() - 41;
+        bytes[last] ^= 1;
+        assert!(CatVec::<u32, 4, 4>::read_snapshot(bytes.as_slice()).is_err());
+    }
+}
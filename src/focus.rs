@@ -0,0 +1,78 @@
+use core::sync::atomic::{fence, AtomicPtr, AtomicUsize, Ordering};
+
+/// A memo of the most recently looked-up leaf, so that lookups near the previous one skip the descent from the root.
+///
+/// The memo is a seqlock over plain atomics, so that reads through `&self` can update it without giving up `Sync`. A reader that races with a writer simply misses. It holds a raw pointer into a leaf of the owning tree; the owner must [Focus::clear] it before every mutation of the tree, which keeps that pointer valid whenever it is read.
+///
+/// Every miss writes the memo, starting with a compare-and-swap on `version`, so threads looking up scattered positions in one shared vector keep taking the same cache line from each other. Clones start with a memo of their own, so such threads are better off each reading through a clone, which costs O(1).
+pub(crate) struct Focus<T> {
+    version: AtomicUsize,
+    start: AtomicUsize,
+    len: AtomicUsize,
+    leaf: AtomicPtr<T>,
+}
+
+impl<T> Focus<T> {
+    pub fn new() -> Self {
+        Self {
+            version: AtomicUsize::new(0),
+            start: AtomicUsize::new(0),
+            len: AtomicUsize::new(0),
+            leaf: AtomicPtr::new(core::ptr::null_mut()),
+        }
+    }
+
+    /// Looks up a position in the memoized leaf.
+    ///
+    /// # Safety
+    ///
+    /// The leaf passed to the last [Focus::set] must still be alive and unmodified for as long as the returned reference is.
+    pub unsafe fn get(&self, idx: usize) -> Option<&T> {
+        let before = self.version.load(Ordering::Acquire);
+        if before % 2 == 1 {
+            return None;
+        }
+        let start = self.start.load(Ordering::Relaxed);
+        let len = self.len.load(Ordering::Relaxed);
+        let leaf = self.leaf.load(Ordering::Relaxed);
+        fence(Ordering::Acquire);
+        if self.version.load(Ordering::Relaxed) != before || leaf.is_null() {
+            return None;
+        }
+        if idx < start || idx - start >= len {
+            return None;
+        }
+        Some(&*leaf.add(idx - start))
+    }
+
+    /// Memoizes a leaf and the position of its first element. Gives up silently if another thread is writing at the same time.
+    pub fn set(&self, start: usize, leaf: &[T]) {
+        let version = self.version.load(Ordering::Relaxed);
+        if version % 2 == 1
+            || self
+                .version
+                .compare_exchange(version, version + 1, Ordering::Relaxed, Ordering::Relaxed)
+                .is_err()
+        {
+            return;
+        }
+        fence(Ordering::Release);
+        self.start.store(start, Ordering::Relaxed);
+        self.len.store(leaf.len(), Ordering::Relaxed);
+        self.leaf.store(leaf.as_ptr() as *mut T, Ordering::Relaxed);
+        self.version.store(version + 2, Ordering::Release);
+    }
+
+    /// Forgets the memoized leaf.
+    pub fn clear(&mut self) {
+        *self.leaf.get_mut() = core::ptr::null_mut();
+        *self.len.get_mut() = 0;
+    }
+}
+
+impl<T> Clone for Focus<T> {
+    /// A clone starts out empty, since the memoized leaf might belong to the original's root node.
+    fn clone(&self) -> Self {
+        Self::new()
+    }
+}
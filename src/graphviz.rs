@@ -0,0 +1,129 @@
+use std::{collections::HashMap, fmt::Debug, io};
+
+use archery::{SharedPointer, SharedPointerKind};
+
+use crate::{btree::Tree, CatVec, Measure};
+
+/// How many elements of each leaf are shown in its label.
+const PREVIEW_LEN: usize = 8;
+
+impl<
+        T: Clone + Debug,
+        const ORD: usize,
+        const LEAF: usize,
+        P: SharedPointerKind,
+        M: Measure<T>,
+    > CatVec<T, ORD, LEAF, P, M>
+{
+    /// Renders the tree as a Graphviz DOT document. Nodes are numbered in traversal order, so the same tree always renders the same way. Leaves are labeled with a preview of their elements, and a subtree that appears several times in the tree is drawn once, with an edge from each parent.
+    pub fn to_graphviz(&self) -> String {
+        Self::to_graphviz_all(&[self])
+    }
+
+    /// Writes the tree as a Graphviz DOT document. See [CatVec::to_graphviz].
+    pub fn write_graphviz(&self, w: impl io::Write) -> io::Result<()> {
+        Self::write_graphviz_all(&[self], w)
+    }
+
+    /// Renders several vectors into one DOT document, drawing each node they share only once. This shows how much structure versions of a vector have in common.
+    pub fn to_graphviz_all(vecs: &[&Self]) -> String {
+        let mut out = Vec::new();
+        Self::write_graphviz_all(vecs, &mut out).expect("writing to a Vec cannot fail");
+        String::from_utf8(out).expect("DOT output is always UTF-8")
+    }
+
+    /// Writes several vectors into one DOT document. See [CatVec::to_graphviz_all].
+    pub fn write_graphviz_all(vecs: &[&Self], mut w: impl io::Write) -> io::Result<()> {
+        writeln!(w, "digraph catvec {{")?;
+        writeln!(w, "    node [shape=box];")?;
+        let mut writer = DotWriter {
+            w: &mut w,
+            next_id: 0,
+            seen: HashMap::new(),
+        };
+        for vec in vecs {
+            writer.write_node(&vec.inner, true)?;
+        }
+        writeln!(w, "}}")
+    }
+}
+
+struct DotWriter<
+    'w,
+    W,
+    T: Clone,
+    const ORD: usize,
+    const LEAF: usize,
+    P: SharedPointerKind,
+    M: Measure<T>,
+> {
+    w: &'w mut W,
+    next_id: usize,
+    /// Ids of the shared nodes written so far, by address.
+    seen: HashMap<*const Tree<T, ORD, LEAF, P, M>, usize>,
+}
+
+impl<
+        'w,
+        W: io::Write,
+        T: Clone + Debug,
+        const ORD: usize,
+        const LEAF: usize,
+        P: SharedPointerKind,
+        M: Measure<T>,
+    > DotWriter<'w, W, T, ORD, LEAF, P, M>
+{
+    /// Writes out a node and everything below it that hasn't been written yet, returning the node's id.
+    fn write_node(&mut self, node: &Tree<T, ORD, LEAF, P, M>, is_root: bool) -> io::Result<usize> {
+        let id = self.next_id;
+        self.next_id += 1;
+        let label = match node {
+            Tree::Array(items) => {
+                let mut preview = format!("{:?}", &items[..items.len().min(PREVIEW_LEN)]);
+                if items.len() > PREVIEW_LEN {
+                    preview.insert_str(preview.len() - 1, ", ...");
+                }
+                format!("[{}] {}", items.len(), preview)
+            }
+            Tree::Internal(_) if is_root => format!("ROOT[{}]", node.len()),
+            Tree::Internal(_) => format!("[{}]", node.len()),
+        };
+        writeln!(self.w, "    n{} [label=\"{}\"];", id, escape(&label))?;
+        for child in node.children().into_iter().flatten() {
+            let child_id = match self.seen.get(&SharedPointer::as_ptr(child)) {
+                Some(child_id) => *child_id,
+                None => {
+                    let child_id = self.write_node(child, false)?;
+                    self.seen.insert(SharedPointer::as_ptr(child), child_id);
+                    child_id
+                }
+            };
+            writeln!(self.w, "    n{} -> n{};", id, child_id)?;
+        }
+        Ok(id)
+    }
+}
+
+fn escape(label: &str) -> String {
+    label.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::CatVec;
+
+    #[test]
+    fn graphviz() {
+        let v: CatVec<&str, 4, 4> = vec!["a\"b"; 10].into();
+        let dot = v.to_graphviz();
+        assert!(dot.starts_with("digraph catvec {"));
+        assert!(dot.contains("n0 [label=\"ROOT[10]\"];"));
+        assert!(dot.contains(r#"[4] [\"a\\\"b\", "#));
+        assert_eq!(dot, v.clone().to_graphviz());
+        let mut w = v.clone();
+        w.push_back("c");
+        let both = CatVec::to_graphviz_all(&[&v, &w]);
+        let nodes = both.lines().filter(|l| l.contains("label")).count();
+        assert!(nodes < 2 * dot.lines().filter(|l| l.contains("label")).count());
+    }
+}
@@ -0,0 +1,127 @@
+use alloc::{vec, vec::Vec};
+
+use archery::{ArcK, SharedPointerKind};
+
+use crate::{CatVec, Measure};
+
+/// A [CatVec] together with a linear history of committed snapshots, supporting undo and redo. Snapshots are cheap persistent clones, so they share all unchanged structure with each other.
+pub struct History<
+    T: Clone,
+    const ORD: usize,
+    const LEAF: usize = 32,
+    P: SharedPointerKind = ArcK,
+    M: Measure<T> = (),
+> {
+    working: CatVec<T, ORD, LEAF, P, M>,
+    snapshots: Vec<CatVec<T, ORD, LEAF, P, M>>,
+    position: usize,
+}
+
+impl<T: Clone, const ORD: usize, const LEAF: usize, P: SharedPointerKind, M: Measure<T>> Clone
+    for History<T, ORD, LEAF, P, M>
+{
+    fn clone(&self) -> Self {
+        Self {
+            working: self.working.clone(),
+            snapshots: self.snapshots.clone(),
+            position: self.position,
+        }
+    }
+}
+
+impl<T: Clone, const ORD: usize, const LEAF: usize, P: SharedPointerKind, M: Measure<T>>
+    History<T, ORD, LEAF, P, M>
+{
+    /// Starts a history whose first snapshot is the given vector.
+    pub fn new(initial: CatVec<T, ORD, LEAF, P, M>) -> Self {
+        Self {
+            working: initial.clone(),
+            snapshots: vec![initial],
+            position: 0,
+        }
+    }
+
+    /// The working copy.
+    pub fn current(&self) -> &CatVec<T, ORD, LEAF, P, M> {
+        &self.working
+    }
+
+    /// Mutable access to the working copy. Changes become part of the history only when committed.
+    pub fn current_mut(&mut self) -> &mut CatVec<T, ORD, LEAF, P, M> {
+        &mut self.working
+    }
+
+    /// Records the working copy as a new snapshot after the one it is based on, discarding any snapshots that could have been redone. Returns the number of the new snapshot.
+    pub fn commit(&mut self) -> usize {
+        self.snapshots.truncate(self.position + 1);
+        self.snapshots.push(self.working.clone());
+        self.position = self.snapshots.len() - 1;
+        self.position
+    }
+
+    /// Replaces the working copy with the given snapshot, discarding uncommitted changes. Returns false if there is no such snapshot.
+    pub fn checkout(&mut self, n: usize) -> bool {
+        match self.snapshots.get(n) {
+            Some(snapshot) => {
+                self.working = snapshot.clone();
+                self.position = n;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Goes back to the previous snapshot, discarding uncommitted changes. Returns false if already at the first snapshot.
+    pub fn undo(&mut self) -> bool {
+        self.position > 0 && self.checkout(self.position - 1)
+    }
+
+    /// Goes forward to the next snapshot, if one was undone. Returns false if there is nothing to redo.
+    pub fn redo(&mut self) -> bool {
+        self.checkout(self.position + 1)
+    }
+
+    /// The number of the snapshot the working copy is based on.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// Gets a snapshot by number.
+    pub fn snapshot(&self, n: usize) -> Option<&CatVec<T, ORD, LEAF, P, M>> {
+        self.snapshots.get(n)
+    }
+
+    /// The number of snapshots.
+    pub fn len(&self) -> usize {
+        self.snapshots.len()
+    }
+
+    /// Always false, since a history has at least its initial snapshot.
+    pub fn is_empty(&self) -> bool {
+        self.snapshots.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::History;
+    use crate::CatVec;
+
+    #[test]
+    fn history() {
+        let mut history: History<usize, 4> = History::new(CatVec::new());
+        for i in 0..10 {
+            history.current_mut().push_back(i);
+            history.commit();
+        }
+        assert!(history.undo() && history.undo());
+        assert_eq!(history.current().len(), 8);
+        assert!(history.redo());
+        assert_eq!(history.current().len(), 9);
+        history.current_mut().push_back(100);
+        assert_eq!(history.commit(), 10);
+        assert!(!history.redo());
+        assert!(history.checkout(3));
+        assert_eq!(Vec::from(history.current().clone()), vec![0, 1, 2]);
+    }
+}
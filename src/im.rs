@@ -0,0 +1,51 @@
+use archery::SharedPointerKind;
+
+use crate::{btree::Tree, focus::Focus, CatVec, Measure};
+
+impl<T: Clone, const ORD: usize, const LEAF: usize, P: SharedPointerKind, M: Measure<T>>
+    CatVec<T, ORD, LEAF, P, M>
+{
+    /// Builds a vector out of an [im::Vector], reading it a chunk at a time rather than looking up each element. This isn't a `From` impl, since that would overlap the one for everything that is `AsRef<[T]>`.
+    pub fn from_im_vector(v: &::im::Vector<T>) -> Self {
+        Self {
+            inner: Tree::build(v.leaves().flat_map(|chunk| chunk.iter().cloned())).into(),
+            head: Default::default(),
+            tail: Default::default(),
+            focus: Focus::new(),
+        }
+    }
+}
+
+/// The conversion the other way is [CatVec::from_im_vector], a constructor rather than a `From<im::Vector<T>>` impl, since that impl would overlap the one for `AsRef<[T]>` types.
+impl<T: Clone, const ORD: usize, const LEAF: usize, P: SharedPointerKind, M: Measure<T>>
+    From<CatVec<T, ORD, LEAF, P, M>> for ::im::Vector<T>
+{
+    /// Copies the elements over a leaf at a time.
+    fn from(cv: CatVec<T, ORD, LEAF, P, M>) -> Self {
+        let mut result = ::im::Vector::new();
+        cv.for_each_chunk(|leaf| result.extend(leaf.iter().cloned()));
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::CatVec;
+
+    #[test]
+    fn im_round_trip() {
+        let mut original: ::im::Vector<usize> = (0..5000).collect();
+        original.insert(100, 7);
+        original.append((0..300).collect());
+        let v: CatVec<usize, 4, 4> = CatVec::from_im_vector(&original);
+        v.check_invariants();
+        assert!(v.iter().eq(original.iter()));
+        let mut v = v;
+        v.push_front(1);
+        v.push_back(2);
+        let back = ::im::Vector::from(v.clone());
+        assert!(back.iter().eq(v.iter()));
+        assert_eq!(back.len(), 5303);
+        assert!(CatVec::<usize, 4>::from_im_vector(&::im::Vector::new()).is_empty());
+    }
+}
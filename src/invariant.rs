@@ -0,0 +1,86 @@
+use alloc::vec::Vec;
+
+/// A broken structural invariant of a [crate::CatVec], as reported by [crate::CatVec::validate].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InvariantViolation {
+    /// Child indices leading from the root to the offending node.
+    pub path: Vec<usize>,
+    pub kind: ViolationKind,
+}
+
+/// Which invariant was broken.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ViolationKind {
+    /// One of an internal node's cached running lengths differs from the sum of its children's lengths up to that point.
+    LengthMismatch { cached: usize, actual: usize },
+    /// A node other than the root has fewer children than half the fanout. For leaves, the children are the elements, and the rightmost leaf is exempt.
+    Underfull { children: usize, min: usize },
+    /// An internal node's root flag is set when it isn't the root, or the other way around.
+    RootFlag { root: bool },
+    /// An internal node's cached flag for whether its children are all full, except maybe the last, is wrong.
+    DenseFlag { dense: bool },
+    /// An internal node has an empty child, which is only allowed for the root of an empty vector.
+    EmptyChild { index: usize },
+    /// An internal node has no children at all.
+    Childless,
+    /// An internal node's cached height isn't one more than the height of its children.
+    HeightMismatch { cached: usize, actual: usize },
+    /// The children of an internal node aren't all of the same height.
+    UnevenHeight { expected: usize, actual: usize },
+    /// An internal node's cached measure of a child differs from the child's actual measure.
+    MeasureMismatch { index: usize },
+}
+
+impl core::fmt::Display for InvariantViolation {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match &self.kind {
+            ViolationKind::LengthMismatch { cached, actual } => write!(
+                f,
+                "node at {:?} has cached length {}, but its children add up to {}",
+                self.path, cached, actual
+            ),
+            ViolationKind::Underfull { children, min } => write!(
+                f,
+                "node at {:?} has {} children, but needs at least {}",
+                self.path, children, min
+            ),
+            ViolationKind::RootFlag { root } => {
+                write!(
+                    f,
+                    "node at {:?} has its root flag wrongly set to {}",
+                    self.path, root
+                )
+            }
+            ViolationKind::DenseFlag { dense } => {
+                write!(
+                    f,
+                    "node at {:?} has its dense flag wrongly set to {}",
+                    self.path, dense
+                )
+            }
+            ViolationKind::EmptyChild { index } => {
+                write!(f, "node at {:?} has an empty child at {}", self.path, index)
+            }
+            ViolationKind::Childless => {
+                write!(f, "node at {:?} is internal but has no children", self.path)
+            }
+            ViolationKind::HeightMismatch { cached, actual } => write!(
+                f,
+                "node at {:?} has cached height {}, but is really {} high",
+                self.path, cached, actual
+            ),
+            ViolationKind::UnevenHeight { expected, actual } => write!(
+                f,
+                "node at {:?} has children of height {} and {}",
+                self.path, expected, actual
+            ),
+            ViolationKind::MeasureMismatch { index } => write!(
+                f,
+                "node at {:?} has a stale measure for its child at {}",
+                self.path, index
+            ),
+        }
+    }
+}
+
+impl core::error::Error for InvariantViolation {}
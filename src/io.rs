@@ -0,0 +1,194 @@
+use std::{
+    convert::TryFrom,
+    io::{self, BufRead, Read, Seek, SeekFrom, Write},
+};
+
+use archery::{ArcK, SharedPointerKind};
+
+use crate::{CatVec, Measure};
+
+/// A reader over a byte vector, implementing [Read], [BufRead] and [Seek] like [std::io::Cursor] does for contiguous buffers. Reads are served a leaf at a time, so a vector assembled from many pieces can be handed to a parser or decompressor without flattening it first.
+///
+/// The cursor owns its vector, which is cheap to clone off another one, so it can be passed around freely.
+pub struct ByteCursor<
+    const ORD: usize,
+    const LEAF: usize = 256,
+    P: SharedPointerKind = ArcK,
+    M: Measure<u8> = (),
+> {
+    vec: CatVec<u8, ORD, LEAF, P, M>,
+    pos: u64,
+}
+
+impl<const ORD: usize, const LEAF: usize, P: SharedPointerKind, M: Measure<u8>> Clone
+    for ByteCursor<ORD, LEAF, P, M>
+{
+    fn clone(&self) -> Self {
+        Self {
+            vec: self.vec.clone(),
+            pos: self.pos,
+        }
+    }
+}
+
+impl<const ORD: usize, const LEAF: usize, P: SharedPointerKind, M: Measure<u8>>
+    ByteCursor<ORD, LEAF, P, M>
+{
+    /// Creates a cursor at the start of the given vector.
+    pub fn new(vec: CatVec<u8, ORD, LEAF, P, M>) -> Self {
+        Self { vec, pos: 0 }
+    }
+
+    /// The vector being read.
+    pub fn get_ref(&self) -> &CatVec<u8, ORD, LEAF, P, M> {
+        &self.vec
+    }
+
+    /// Consumes the cursor, returning the vector.
+    pub fn into_inner(self) -> CatVec<u8, ORD, LEAF, P, M> {
+        self.vec
+    }
+
+    /// The current position. It may be past the end, after seeking there.
+    pub fn position(&self) -> u64 {
+        self.pos
+    }
+
+    /// Sets the current position, which may be past the end.
+    pub fn set_position(&mut self, pos: u64) {
+        self.pos = pos;
+    }
+
+    /// The bytes from the current position to the end of its leaf, which are empty at or past the end.
+    pub(crate) fn remaining_in_leaf(&self) -> &[u8] {
+        match usize::try_from(self.pos) {
+            Ok(pos) if pos < self.vec.len() => {
+                let (start, leaf) = self.vec.leaf_at(pos);
+                &leaf[pos - start..]
+            }
+            _ => &[],
+        }
+    }
+}
+
+impl<const ORD: usize, const LEAF: usize, P: SharedPointerKind, M: Measure<u8>>
+    From<CatVec<u8, ORD, LEAF, P, M>> for ByteCursor<ORD, LEAF, P, M>
+{
+    fn from(vec: CatVec<u8, ORD, LEAF, P, M>) -> Self {
+        Self::new(vec)
+    }
+}
+
+impl<const ORD: usize, const LEAF: usize, P: SharedPointerKind, M: Measure<u8>> Read
+    for ByteCursor<ORD, LEAF, P, M>
+{
+    /// Fills as much of the buffer as it can, across as many leaves as that takes.
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut written = 0;
+        while written < buf.len() {
+            let chunk = self.remaining_in_leaf();
+            if chunk.is_empty() {
+                break;
+            }
+            let n = chunk.len().min(buf.len() - written);
+            buf[written..written + n].copy_from_slice(&chunk[..n]);
+            written += n;
+            self.pos += n as u64;
+        }
+        Ok(written)
+    }
+}
+
+impl<const ORD: usize, const LEAF: usize, P: SharedPointerKind, M: Measure<u8>> BufRead
+    for ByteCursor<ORD, LEAF, P, M>
+{
+    /// Returns the rest of the current leaf.
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        Ok(self.remaining_in_leaf())
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.pos += amt as u64;
+    }
+}
+
+impl<const ORD: usize, const LEAF: usize, P: SharedPointerKind, M: Measure<u8>> Seek
+    for ByteCursor<ORD, LEAF, P, M>
+{
+    fn seek(&mut self, style: SeekFrom) -> io::Result<u64> {
+        let (base, offset) = match style {
+            SeekFrom::Start(n) => {
+                self.pos = n;
+                return Ok(n);
+            }
+            SeekFrom::End(n) => (self.vec.len() as u64, n),
+            SeekFrom::Current(n) => (self.pos, n),
+        };
+        match base.checked_add_signed(offset) {
+            Some(n) => {
+                self.pos = n;
+                Ok(n)
+            }
+            None => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "invalid seek to a negative or overflowing position",
+            )),
+        }
+    }
+}
+
+/// Appends written bytes to the end of the vector with [CatVec::extend_from_slice], so encoders and serializers can write straight into it. Writes never fail or come up short.
+impl<const ORD: usize, const LEAF: usize, P: SharedPointerKind, M: Measure<u8>> Write
+    for CatVec<u8, ORD, LEAF, P, M>
+{
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
+        self.extend_from_slice(buf);
+        Ok(())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{BufRead, Read, Seek, SeekFrom, Write};
+
+    use crate::CatBytes;
+
+    use super::ByteCursor;
+
+    #[test]
+    fn byte_cursor() {
+        let mut v: CatBytes<4, 8> = CatBytes::new();
+        for line in 0..50 {
+            writeln!(v, "line {}", line).unwrap();
+        }
+        v.check_invariants();
+        let text = String::from_utf8(Vec::from(v.clone())).unwrap();
+        let mut cursor = ByteCursor::new(v);
+        let lines: Vec<String> = cursor.by_ref().lines().map(Result::unwrap).collect();
+        assert_eq!(lines.len(), 50);
+        assert_eq!(lines[42], "line 42");
+        assert_eq!(cursor.seek(SeekFrom::Start(7)).unwrap(), 7);
+        let mut rest = String::new();
+        cursor.read_to_string(&mut rest).unwrap();
+        assert_eq!(rest, text[7..]);
+        assert_eq!(
+            cursor.seek(SeekFrom::End(-8)).unwrap(),
+            text.len() as u64 - 8
+        );
+        let mut buf = [0; 20];
+        assert_eq!(cursor.read(&mut buf).unwrap(), 8);
+        assert_eq!(&buf[..8], b"line 49\n");
+        assert!(cursor.seek(SeekFrom::Current(-1000)).is_err());
+        cursor.seek(SeekFrom::Current(100)).unwrap();
+        assert_eq!(cursor.read(&mut buf).unwrap(), 0);
+    }
+}
@@ -0,0 +1,495 @@
+use alloc::vec::Vec;
+use core::ops::ControlFlow;
+
+use archery::{ArcK, SharedPointerKind};
+
+use crate::{btree::Leaves, CatVec, Measure};
+
+/// An iterator over references to the elements of a [crate::CatVec], walking the leaves in order.
+pub struct Iter<
+    'a,
+    T: Clone,
+    const ORD: usize,
+    const LEAF: usize = 32,
+    P: SharedPointerKind = ArcK,
+    M: Measure<T> = (),
+> {
+    leaves: Leaves<'a, T, ORD, LEAF, P, M>,
+    current: core::slice::Iter<'a, T>,
+    remaining: usize,
+}
+
+impl<'a, T: Clone, const ORD: usize, const LEAF: usize, P: SharedPointerKind, M: Measure<T>> Clone
+    for Iter<'a, T, ORD, LEAF, P, M>
+{
+    fn clone(&self) -> Self {
+        Self {
+            leaves: self.leaves.clone(),
+            current: self.current.clone(),
+            remaining: self.remaining,
+        }
+    }
+}
+
+impl<'a, T: Clone, const ORD: usize, const LEAF: usize, P: SharedPointerKind, M: Measure<T>>
+    Iter<'a, T, ORD, LEAF, P, M>
+{
+    pub(crate) fn new(leaves: Leaves<'a, T, ORD, LEAF, P, M>, len: usize) -> Self {
+        Self {
+            leaves,
+            current: [].iter(),
+            remaining: len,
+        }
+    }
+
+    /// Skips the next `n` elements, jumping over whole leaves where possible.
+    fn advance(&mut self, mut n: usize) {
+        n = n.min(self.remaining);
+        self.remaining -= n;
+        loop {
+            let here = self.current.len();
+            if n < here {
+                if n > 0 {
+                    self.current.nth(n - 1);
+                }
+                return;
+            }
+            n -= here;
+            match self.leaves.next() {
+                Some(leaf) => self.current = leaf.iter(),
+                None => {
+                    self.current = [].iter();
+                    return;
+                }
+            }
+        }
+    }
+
+    /// Runs `f` on the iterator over each remaining leaf in turn, until it breaks. `f` may consume as much of the leaf as it likes, and the iterator can be resumed from wherever it stopped.
+    fn try_leaves<B>(
+        &mut self,
+        mut f: impl FnMut(&mut core::slice::Iter<'a, T>) -> ControlFlow<B>,
+    ) -> ControlFlow<B> {
+        loop {
+            let before = self.current.len();
+            let flow = f(&mut self.current);
+            self.remaining -= before - self.current.len();
+            flow?;
+            match self.leaves.next() {
+                Some(leaf) => self.current = leaf.iter(),
+                None => return ControlFlow::Continue(()),
+            }
+        }
+    }
+}
+
+impl<'a, T: Clone, const ORD: usize, const LEAF: usize, P: SharedPointerKind, M: Measure<T>>
+    Iterator for Iter<'a, T, ORD, LEAF, P, M>
+{
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(x) = self.current.next() {
+                self.remaining -= 1;
+                return Some(x);
+            }
+            self.current = self.leaves.next()?.iter();
+        }
+    }
+
+    fn nth(&mut self, n: usize) -> Option<Self::Item> {
+        self.advance(n);
+        self.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+
+    fn count(self) -> usize {
+        self.remaining
+    }
+
+    // The rest run the slice iterator's own loop over each leaf. `try_fold` cannot be overridden on stable, so the short-circuiting methods built on it are overridden one by one instead.
+    fn fold<B, F: FnMut(B, Self::Item) -> B>(self, init: B, mut f: F) -> B {
+        let acc = self.current.fold(init, &mut f);
+        self.leaves
+            .fold(acc, |acc, leaf| leaf.iter().fold(acc, &mut f))
+    }
+
+    fn find<F: FnMut(&Self::Item) -> bool>(&mut self, mut pred: F) -> Option<Self::Item> {
+        match self.try_leaves(|leaf| match leaf.find(&mut pred) {
+            Some(x) => ControlFlow::Break(x),
+            None => ControlFlow::Continue(()),
+        }) {
+            ControlFlow::Break(x) => Some(x),
+            ControlFlow::Continue(()) => None,
+        }
+    }
+
+    fn any<F: FnMut(Self::Item) -> bool>(&mut self, mut pred: F) -> bool {
+        self.try_leaves(|leaf| {
+            if leaf.any(&mut pred) {
+                ControlFlow::Break(())
+            } else {
+                ControlFlow::Continue(())
+            }
+        })
+        .is_break()
+    }
+
+    fn all<F: FnMut(Self::Item) -> bool>(&mut self, mut pred: F) -> bool {
+        !self.any(|x| !pred(x))
+    }
+
+    fn position<F: FnMut(Self::Item) -> bool>(&mut self, pred: F) -> Option<usize> {
+        let start = self.remaining;
+        // `any` stops just past the match, so the match is the last element consumed.
+        self.any(pred).then(|| start - self.remaining - 1)
+    }
+}
+
+impl<'a, T: Clone, const ORD: usize, const LEAF: usize, P: SharedPointerKind, M: Measure<T>>
+    ExactSizeIterator for Iter<'a, T, ORD, LEAF, P, M>
+{
+}
+
+/// Non-overlapping chunks of a [crate::CatVec], returned by [crate::CatVec::chunks]. Each chunk is lent out as a slice of references to at most `size` elements; the last one may be shorter. The references are gathered into a buffer that is reused from chunk to chunk, so a chunk borrows the [Chunks] and it is stepped through with [Chunks::next_chunk] rather than as an [Iterator].
+pub struct Chunks<
+    'a,
+    T: Clone,
+    const ORD: usize,
+    const LEAF: usize = 32,
+    P: SharedPointerKind = ArcK,
+    M: Measure<T> = (),
+> {
+    iter: Iter<'a, T, ORD, LEAF, P, M>,
+    size: usize,
+    buf: Vec<&'a T>,
+}
+
+impl<'a, T: Clone, const ORD: usize, const LEAF: usize, P: SharedPointerKind, M: Measure<T>> Clone
+    for Chunks<'a, T, ORD, LEAF, P, M>
+{
+    fn clone(&self) -> Self {
+        Self {
+            iter: self.iter.clone(),
+            size: self.size,
+            buf: self.buf.clone(),
+        }
+    }
+}
+
+impl<'a, T: Clone, const ORD: usize, const LEAF: usize, P: SharedPointerKind, M: Measure<T>>
+    Chunks<'a, T, ORD, LEAF, P, M>
+{
+    pub(crate) fn new(iter: Iter<'a, T, ORD, LEAF, P, M>, size: usize) -> Self {
+        assert!(size != 0, "chunk size must be non-zero");
+        Self {
+            iter,
+            size,
+            buf: Vec::new(),
+        }
+    }
+
+    /// Moves on to the next chunk and returns it, or None once the elements run out.
+    pub fn next_chunk(&mut self) -> Option<&[&'a T]> {
+        if self.iter.len() == 0 {
+            return None;
+        }
+        self.buf.clear();
+        self.buf.extend(self.iter.by_ref().take(self.size));
+        Some(&self.buf)
+    }
+
+    /// The number of chunks left.
+    pub fn remaining(&self) -> usize {
+        self.iter.len().div_ceil(self.size)
+    }
+}
+
+/// Overlapping windows of a [crate::CatVec], returned by [crate::CatVec::windows]. Each window is lent out as a slice of references to exactly `size` elements. The references sit in a ring buffer that slides forward one element per window, so each element is read from its leaf once, and nothing is allocated after the first window. A window borrows the [Windows], so it is stepped through with [Windows::next_window] rather than as an [Iterator].
+pub struct Windows<
+    'a,
+    T: Clone,
+    const ORD: usize,
+    const LEAF: usize = 32,
+    P: SharedPointerKind = ArcK,
+    M: Measure<T> = (),
+> {
+    iter: Iter<'a, T, ORD, LEAF, P, M>,
+    size: usize,
+    /// The current window is the last `size` references. The buffer holds up to twice that, and when it fills up the window is moved back to the front, which happens once every `size` windows.
+    buf: Vec<&'a T>,
+}
+
+impl<'a, T: Clone, const ORD: usize, const LEAF: usize, P: SharedPointerKind, M: Measure<T>> Clone
+    for Windows<'a, T, ORD, LEAF, P, M>
+{
+    fn clone(&self) -> Self {
+        Self {
+            iter: self.iter.clone(),
+            size: self.size,
+            buf: self.buf.clone(),
+        }
+    }
+}
+
+impl<'a, T: Clone, const ORD: usize, const LEAF: usize, P: SharedPointerKind, M: Measure<T>>
+    Windows<'a, T, ORD, LEAF, P, M>
+{
+    pub(crate) fn new(iter: Iter<'a, T, ORD, LEAF, P, M>, size: usize) -> Self {
+        assert!(size != 0, "window size must be non-zero");
+        Self {
+            iter,
+            size,
+            buf: Vec::new(),
+        }
+    }
+
+    /// Slides on to the next window and returns it, or None once the elements run out.
+    pub fn next_window(&mut self) -> Option<&[&'a T]> {
+        if self.buf.is_empty() {
+            if self.iter.len() < self.size {
+                return None;
+            }
+            self.buf.reserve_exact(2 * self.size);
+            self.buf.extend(self.iter.by_ref().take(self.size));
+        } else {
+            let next = self.iter.next()?;
+            if self.buf.len() == 2 * self.size {
+                self.buf.drain(..self.size);
+            }
+            self.buf.push(next);
+        }
+        Some(&self.buf[self.buf.len() - self.size..])
+    }
+
+    /// The number of windows left.
+    pub fn remaining(&self) -> usize {
+        if self.buf.is_empty() {
+            (self.iter.len() + 1).saturating_sub(self.size)
+        } else {
+            self.iter.len()
+        }
+    }
+}
+
+/// An iterator over maximal runs of a [crate::CatVec] in which every pair of consecutive elements satisfies a predicate, returned by [crate::CatVec::chunk_by].
+pub struct ChunkBy<
+    'a,
+    T: Clone,
+    const ORD: usize,
+    const LEAF: usize,
+    P: SharedPointerKind,
+    M: Measure<T>,
+    F,
+> {
+    iter: Iter<'a, T, ORD, LEAF, P, M>,
+    pred: F,
+}
+
+impl<
+        'a,
+        T: Clone,
+        const ORD: usize,
+        const LEAF: usize,
+        P: SharedPointerKind,
+        M: Measure<T>,
+        F: Clone,
+    > Clone for ChunkBy<'a, T, ORD, LEAF, P, M, F>
+{
+    fn clone(&self) -> Self {
+        Self {
+            iter: self.iter.clone(),
+            pred: self.pred.clone(),
+        }
+    }
+}
+
+impl<
+        'a,
+        T: Clone,
+        const ORD: usize,
+        const LEAF: usize,
+        P: SharedPointerKind,
+        M: Measure<T>,
+        F: FnMut(&T, &T) -> bool,
+    > ChunkBy<'a, T, ORD, LEAF, P, M, F>
+{
+    pub(crate) fn new(iter: Iter<'a, T, ORD, LEAF, P, M>, pred: F) -> Self {
+        Self { iter, pred }
+    }
+}
+
+impl<
+        'a,
+        T: Clone,
+        const ORD: usize,
+        const LEAF: usize,
+        P: SharedPointerKind,
+        M: Measure<T>,
+        F: FnMut(&T, &T) -> bool,
+    > Iterator for ChunkBy<'a, T, ORD, LEAF, P, M, F>
+{
+    type Item = core::iter::Take<Iter<'a, T, ORD, LEAF, P, M>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut scan = self.iter.clone();
+        let mut prev = scan.next()?;
+        let mut len = 1;
+        for x in scan {
+            if !(self.pred)(prev, x) {
+                break;
+            }
+            prev = x;
+            len += 1;
+        }
+        let chunk = self.iter.clone().take(len);
+        self.iter.advance(len);
+        Some(chunk)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let n = self.iter.len();
+        (n.min(1), Some(n))
+    }
+}
+
+/// An iterator over the pieces of a [crate::CatVec] between elements matching a predicate, each a structurally shared sub-vector, returned by [crate::CatVec::split].
+pub struct Split<
+    'a,
+    T: Clone,
+    const ORD: usize,
+    const LEAF: usize,
+    P: SharedPointerKind,
+    M: Measure<T>,
+    F,
+> {
+    vec: &'a CatVec<T, ORD, LEAF, P, M>,
+    /// The elements after the last separator found, starting at `start`.
+    iter: Iter<'a, T, ORD, LEAF, P, M>,
+    start: usize,
+    finished: bool,
+    pred: F,
+}
+
+impl<
+        'a,
+        T: Clone,
+        const ORD: usize,
+        const LEAF: usize,
+        P: SharedPointerKind,
+        M: Measure<T>,
+        F: FnMut(&T) -> bool,
+    > Split<'a, T, ORD, LEAF, P, M, F>
+{
+    pub(crate) fn new(vec: &'a CatVec<T, ORD, LEAF, P, M>, pred: F) -> Self {
+        Self {
+            vec,
+            iter: vec.iter(),
+            start: 0,
+            finished: false,
+            pred,
+        }
+    }
+}
+
+impl<
+        'a,
+        T: Clone,
+        const ORD: usize,
+        const LEAF: usize,
+        P: SharedPointerKind,
+        M: Measure<T>,
+        F: FnMut(&T) -> bool,
+    > Iterator for Split<'a, T, ORD, LEAF, P, M, F>
+{
+    type Item = CatVec<T, ORD, LEAF, P, M>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+        let start = self.start;
+        match self.iter.position(&mut self.pred) {
+            Some(len) => {
+                self.start += len + 1;
+                Some(self.vec.subvec(start..start + len))
+            }
+            None => {
+                self.finished = true;
+                Some(self.vec.subvec(start..))
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        if self.finished {
+            (0, Some(0))
+        } else {
+            (1, Some(self.iter.len() + 1))
+        }
+    }
+}
+
+/// An iterator over the maximal runs of equal consecutive elements of a [crate::CatVec], as pairs of a length and the repeated element, returned by [crate::CatVec::runs].
+pub struct Runs<
+    'a,
+    T: Clone,
+    const ORD: usize,
+    const LEAF: usize,
+    P: SharedPointerKind,
+    M: Measure<T>,
+> {
+    leaves: Leaves<'a, T, ORD, LEAF, P, M>,
+    /// The unscanned rest of the current leaf.
+    leaf: &'a [T],
+}
+
+impl<'a, T: Clone, const ORD: usize, const LEAF: usize, P: SharedPointerKind, M: Measure<T>>
+    Runs<'a, T, ORD, LEAF, P, M>
+{
+    pub(crate) fn new(leaves: Leaves<'a, T, ORD, LEAF, P, M>) -> Self {
+        Self { leaves, leaf: &[] }
+    }
+}
+
+impl<
+        'a,
+        T: Clone + PartialEq,
+        const ORD: usize,
+        const LEAF: usize,
+        P: SharedPointerKind,
+        M: Measure<T>,
+    > Iterator for Runs<'a, T, ORD, LEAF, P, M>
+{
+    type Item = (usize, &'a T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.leaf.is_empty() {
+            self.leaf = self.leaves.next()?;
+        }
+        let value = &self.leaf[0];
+        let mut count = 0;
+        // A run can continue through any number of leaves, so scan each one for the end of it.
+        loop {
+            let n = self
+                .leaf
+                .iter()
+                .position(|x| x != value)
+                .unwrap_or(self.leaf.len());
+            count += n;
+            self.leaf = &self.leaf[n..];
+            if !self.leaf.is_empty() {
+                break;
+            }
+            match self.leaves.next() {
+                Some(leaf) => self.leaf = leaf,
+                None => break,
+            }
+        }
+        Some((count, value))
+    }
+}
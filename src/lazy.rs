@@ -0,0 +1,187 @@
+use alloc::vec::Vec;
+use core::iter::FromIterator;
+
+use archery::{ArcK, SharedPointerKind};
+
+use crate::{CatVec, Measure};
+
+/// A concatenation of [CatVec]s that hasn't been carried out yet. Appending a vector is O(1), and elements can be read from the pieces as they are; the pieces are only joined into one tree by [LazyCatVec::force] or by converting into a [CatVec].
+///
+/// Joining merges neighboring pieces pairwise, level by level, so that most of the concatenations, and the rebalancing they involve, happen between small trees. Appending thousands of fragments and joining them once is much cheaper than appending them to a [CatVec] one at a time.
+pub struct LazyCatVec<
+    T: Clone,
+    const ORD: usize,
+    const LEAF: usize = 32,
+    P: SharedPointerKind = ArcK,
+    M: Measure<T> = (),
+> {
+    parts: Vec<CatVec<T, ORD, LEAF, P, M>>,
+    /// Running totals of the parts' lengths, so that `ends[i]` counts the elements in parts `0..=i`.
+    ends: Vec<usize>,
+}
+
+impl<T: Clone, const ORD: usize, const LEAF: usize, P: SharedPointerKind, M: Measure<T>> Clone
+    for LazyCatVec<T, ORD, LEAF, P, M>
+{
+    fn clone(&self) -> Self {
+        Self {
+            parts: self.parts.clone(),
+            ends: self.ends.clone(),
+        }
+    }
+}
+
+impl<T: Clone, const ORD: usize, const LEAF: usize, P: SharedPointerKind, M: Measure<T>>
+    LazyCatVec<T, ORD, LEAF, P, M>
+{
+    /// Creates an empty concatenation.
+    pub fn new() -> Self {
+        Self {
+            parts: Vec::new(),
+            ends: Vec::new(),
+        }
+    }
+
+    /// Appends a vector, without doing any rebalancing yet.
+    pub fn append(&mut self, other: CatVec<T, ORD, LEAF, P, M>) {
+        if other.is_empty() {
+            return;
+        }
+        self.ends.push(self.len() + other.len());
+        self.parts.push(other);
+    }
+
+    /// Pushes an element to the back. It goes into the last piece, which buffers pushes as usual.
+    pub fn push_back(&mut self, val: T) {
+        match self.parts.last_mut() {
+            Some(last) => {
+                last.push_back(val);
+                *self.ends.last_mut().unwrap() += 1;
+            }
+            None => {
+                let mut part = CatVec::new();
+                part.push_back(val);
+                self.append(part);
+            }
+        }
+    }
+
+    /// Gets a reference to the element at a particular position, looking it up in the piece that contains it.
+    pub fn get(&self, i: usize) -> Option<&T> {
+        let idx = self.ends.partition_point(|&end| end <= i);
+        let start = idx.checked_sub(1).map_or(0, |j| self.ends[j]);
+        self.parts.get(idx)?.get(i - start)
+    }
+
+    /// Iterates over references to the elements, piece by piece.
+    pub fn iter(&self) -> impl Iterator<Item = &T> + Clone + '_ {
+        self.parts.iter().flat_map(|part| part.iter())
+    }
+
+    /// The number of pieces that haven't been joined yet.
+    pub fn pieces(&self) -> usize {
+        self.parts.len()
+    }
+
+    /// Length of the whole concatenation.
+    pub fn len(&self) -> usize {
+        self.ends.last().copied().unwrap_or(0)
+    }
+
+    /// Returns true if the concatenation is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Joins all the pieces into one vector, which stays in place for further appends.
+    pub fn force(&mut self) -> &CatVec<T, ORD, LEAF, P, M> {
+        while self.parts.len() > 1 {
+            let mut parts = core::mem::take(&mut self.parts).into_iter();
+            while let Some(mut left) = parts.next() {
+                if let Some(right) = parts.next() {
+                    left.append(right);
+                }
+                self.parts.push(left);
+            }
+        }
+        if self.parts.is_empty() {
+            self.parts.push(CatVec::new());
+        }
+        self.ends = Vec::from([self.parts[0].len()]);
+        &self.parts[0]
+    }
+
+    /// Joins all the pieces into one vector.
+    pub fn into_catvec(mut self) -> CatVec<T, ORD, LEAF, P, M> {
+        self.force();
+        self.parts.pop().unwrap()
+    }
+}
+
+impl<T: Clone, const ORD: usize, const LEAF: usize, P: SharedPointerKind, M: Measure<T>> Default
+    for LazyCatVec<T, ORD, LEAF, P, M>
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Clone, const ORD: usize, const LEAF: usize, P: SharedPointerKind, M: Measure<T>>
+    Extend<CatVec<T, ORD, LEAF, P, M>> for LazyCatVec<T, ORD, LEAF, P, M>
+{
+    fn extend<I: IntoIterator<Item = CatVec<T, ORD, LEAF, P, M>>>(&mut self, iter: I) {
+        iter.into_iter().for_each(|v| self.append(v))
+    }
+}
+
+impl<T: Clone, const ORD: usize, const LEAF: usize, P: SharedPointerKind, M: Measure<T>>
+    FromIterator<CatVec<T, ORD, LEAF, P, M>> for LazyCatVec<T, ORD, LEAF, P, M>
+{
+    fn from_iter<I: IntoIterator<Item = CatVec<T, ORD, LEAF, P, M>>>(iter: I) -> Self {
+        let mut lazy = Self::new();
+        lazy.extend(iter);
+        lazy
+    }
+}
+
+impl<T: Clone, const ORD: usize, const LEAF: usize, P: SharedPointerKind, M: Measure<T>>
+    From<LazyCatVec<T, ORD, LEAF, P, M>> for CatVec<T, ORD, LEAF, P, M>
+{
+    fn from(lazy: LazyCatVec<T, ORD, LEAF, P, M>) -> Self {
+        lazy.into_catvec()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::CatVec;
+
+    use super::LazyCatVec;
+
+    #[test]
+    fn fragments() {
+        let mut shadow = Vec::new();
+        let mut lazy: LazyCatVec<usize, 4, 4> = LazyCatVec::new();
+        for i in 0..1000 {
+            let fragment: Vec<usize> = (0..i % 7).map(|j| i * 10 + j).collect();
+            shadow.extend_from_slice(&fragment);
+            lazy.append(fragment.into());
+        }
+        lazy.push_back(5);
+        shadow.push(5);
+        assert_eq!(lazy.len(), shadow.len());
+        assert!(lazy.pieces() > 800);
+        for i in (0..shadow.len()).step_by(7) {
+            assert_eq!(lazy.get(i), Some(&shadow[i]));
+        }
+        assert_eq!(lazy.get(shadow.len()), None);
+        assert!(lazy.iter().eq(shadow.iter()));
+        assert_eq!(lazy.force().len(), shadow.len());
+        lazy.push_back(6);
+        shadow.push(6);
+        assert_eq!(lazy.pieces(), 1);
+        let v: CatVec<usize, 4, 4> = lazy.into();
+        v.check_invariants();
+        assert_eq!(Vec::from(v), shadow);
+    }
+}
@@ -0,0 +1,225 @@
+use alloc::vec::Vec;
+use core::ops::{Deref, RangeBounds};
+
+use archery::{ArcK, SharedPointer, SharedPointerKind};
+use arrayvec::ArrayVec;
+
+use crate::{btree::Tree, range_bounds, CatVec, Measure};
+
+/// A reference-counted view of part of one leaf of a [CatVec], returned by [CatVec::shared_leaves]. It keeps the leaf alive on its own, so it can outlive the vector and be sent off to a serializer or another thread without copying any elements. It dereferences to a slice.
+pub struct SharedLeaf<
+    T: Clone,
+    const ORD: usize,
+    const LEAF: usize = 32,
+    P: SharedPointerKind = ArcK,
+    M: Measure<T> = (),
+> {
+    storage: Storage<T, ORD, LEAF, P, M>,
+    start: usize,
+    end: usize,
+}
+
+enum Storage<T: Clone, const ORD: usize, const LEAF: usize, P: SharedPointerKind, M: Measure<T>> {
+    /// A leaf node of the tree.
+    Node(SharedPointer<Tree<T, ORD, LEAF, P, M>, P>),
+    /// The head or tail buffer.
+    Buffer(SharedPointer<ArrayVec<T, LEAF>, P>),
+}
+
+impl<T: Clone, const ORD: usize, const LEAF: usize, P: SharedPointerKind, M: Measure<T>> Clone
+    for SharedLeaf<T, ORD, LEAF, P, M>
+{
+    fn clone(&self) -> Self {
+        Self {
+            storage: match &self.storage {
+                Storage::Node(node) => Storage::Node(node.clone()),
+                Storage::Buffer(buffer) => Storage::Buffer(buffer.clone()),
+            },
+            start: self.start,
+            end: self.end,
+        }
+    }
+}
+
+impl<T: Clone, const ORD: usize, const LEAF: usize, P: SharedPointerKind, M: Measure<T>>
+    SharedLeaf<T, ORD, LEAF, P, M>
+{
+    fn whole(storage: Storage<T, ORD, LEAF, P, M>) -> Self {
+        let mut leaf = Self {
+            storage,
+            start: 0,
+            end: 0,
+        };
+        leaf.end = leaf.contents().len();
+        leaf
+    }
+
+    fn contents(&self) -> &[T] {
+        match &self.storage {
+            Storage::Node(node) => match node.as_ref() {
+                Tree::Array(arr) => arr,
+                Tree::Internal(_) => unreachable!("shared leaf pointing at an internal node"),
+            },
+            Storage::Buffer(buffer) => buffer,
+        }
+    }
+
+    /// Narrows the view to a subrange of itself, still without copying. Panics if the range is out of bounds.
+    pub fn slice(&self, range: impl RangeBounds<usize>) -> Self {
+        let (start, end) = range_bounds(range, self.len());
+        assert!(
+            start <= end && end <= self.len(),
+            "range {}..{} out of bounds for a leaf slice of length {}",
+            start,
+            end,
+            self.len()
+        );
+        let mut sliced = self.clone();
+        sliced.start = self.start + start;
+        sliced.end = self.start + end;
+        sliced
+    }
+
+    /// Returns true if both views point into the same leaf.
+    pub fn same_leaf(&self, other: &Self) -> bool {
+        match (&self.storage, &other.storage) {
+            (Storage::Node(a), Storage::Node(b)) => SharedPointer::ptr_eq(a, b),
+            (Storage::Buffer(a), Storage::Buffer(b)) => SharedPointer::ptr_eq(a, b),
+            _ => false,
+        }
+    }
+}
+
+impl<T: Clone, const ORD: usize, const LEAF: usize, P: SharedPointerKind, M: Measure<T>> Deref
+    for SharedLeaf<T, ORD, LEAF, P, M>
+{
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        &self.contents()[self.start..self.end]
+    }
+}
+
+impl<T: Clone, const ORD: usize, const LEAF: usize, P: SharedPointerKind, M: Measure<T>> AsRef<[T]>
+    for SharedLeaf<T, ORD, LEAF, P, M>
+{
+    fn as_ref(&self) -> &[T] {
+        self
+    }
+}
+
+impl<
+        T: Clone + core::fmt::Debug,
+        const ORD: usize,
+        const LEAF: usize,
+        P: SharedPointerKind,
+        M: Measure<T>,
+    > core::fmt::Debug for SharedLeaf<T, ORD, LEAF, P, M>
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<T: Clone, const ORD: usize, const LEAF: usize, P: SharedPointerKind, M: Measure<T>>
+    CatVec<T, ORD, LEAF, P, M>
+{
+    /// Returns the leaves in order, including the head and tail buffers, as reference-counted views that share the leaves' storage. Only a vector small enough to be a single leaf copies it, since such a leaf isn't behind a pointer of its own.
+    pub fn shared_leaves(&self) -> impl Iterator<Item = SharedLeaf<T, ORD, LEAF, P, M>> {
+        let mut pointers = Vec::new();
+        self.inner.leaf_pointers(&mut pointers);
+        let mut leaves = Vec::with_capacity(pointers.len() + 2);
+        leaves.push(SharedLeaf::whole(Storage::Buffer(self.head.clone())));
+        match self.inner.as_ref() {
+            Tree::Array(_) => leaves.push(self.root_leaf()),
+            Tree::Internal(_) => leaves.extend(
+                pointers
+                    .into_iter()
+                    .map(|node| SharedLeaf::whole(Storage::Node(node.clone()))),
+            ),
+        }
+        leaves.push(SharedLeaf::whole(Storage::Buffer(self.tail.clone())));
+        leaves.into_iter().filter(|leaf| !leaf.is_empty())
+    }
+
+    /// Finds the leaf containing the given position, counting the head and tail buffers as leaves, and returns the position of its first element along with a shared view of the whole leaf. Returns None if the position is out of bounds.
+    pub fn shared_leaf_at(&self, i: usize) -> Option<(usize, SharedLeaf<T, ORD, LEAF, P, M>)> {
+        let head = self.head.len();
+        let tree_end = head + self.inner.len();
+        if i >= self.len() {
+            return None;
+        }
+        if i < head {
+            return Some((0, SharedLeaf::whole(Storage::Buffer(self.head.clone()))));
+        }
+        if i >= tree_end {
+            return Some((
+                tree_end,
+                SharedLeaf::whole(Storage::Buffer(self.tail.clone())),
+            ));
+        }
+        let mut node: &Tree<T, ORD, LEAF, P, M> = &self.inner;
+        let mut pointer = None;
+        let mut start = head;
+        let mut idx = i - head;
+        while let Some((child, offset)) = node.find_child(idx) {
+            let child = &node.children().unwrap()[child];
+            start += offset;
+            idx -= offset;
+            node = child;
+            pointer = Some(child);
+        }
+        let leaf = match pointer {
+            Some(pointer) => SharedLeaf::whole(Storage::Node(pointer.clone())),
+            None => self.root_leaf(),
+        };
+        Some((start, leaf))
+    }
+
+    /// A root that is itself a leaf isn't behind a pointer, so sharing it takes a copy.
+    fn root_leaf(&self) -> SharedLeaf<T, ORD, LEAF, P, M> {
+        let root = Tree::Array(self.inner.leaf_at(0).1.iter().cloned().collect());
+        SharedLeaf::whole(Storage::Node(SharedPointer::new(root)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::CatVec;
+
+    use super::SharedLeaf;
+
+    #[test]
+    fn shared_leaves() {
+        let mut v: CatVec<usize, 4, 4> = (0..100).collect::<Vec<_>>().into();
+        v.push_front(1000);
+        v.push_back(2000);
+        let flatten = |leaves: &[SharedLeaf<usize, 4, 4>]| -> Vec<usize> {
+            leaves
+                .iter()
+                .flat_map(|leaf| leaf.iter().copied())
+                .collect()
+        };
+        let leaves: Vec<_> = v.shared_leaves().collect();
+        let shadow = Vec::from(v.clone());
+        assert_eq!(flatten(&leaves), shadow);
+        assert!(leaves.iter().all(|leaf| leaf.len() <= 4));
+        let again: Vec<_> = v.shared_leaves().collect();
+        assert!(leaves.iter().zip(again.iter()).all(|(a, b)| a.same_leaf(b)));
+        let middle = leaves[3].slice(1..3);
+        assert_eq!(&middle[..], &leaves[3][1..3]);
+        assert!(middle.same_leaf(&leaves[3]));
+        assert_eq!(&middle.slice(1..)[..], &leaves[3][2..3]);
+        let (start, leaf) = v.shared_leaf_at(50).unwrap();
+        assert!(start <= 50 && 50 < start + leaf.len());
+        assert_eq!(leaf[50 - start], shadow[50]);
+        assert!(v.shared_leaf_at(shadow.len()).is_none());
+        drop(v);
+        assert_eq!(flatten(&leaves), shadow);
+        let small: CatVec<usize, 4, 4> = vec![1, 2].into();
+        assert_eq!(
+            flatten(&small.shared_leaves().collect::<Vec<_>>()),
+            vec![1, 2]
+        );
+    }
+}
@@ -1,143 +1,1680 @@
-use std::{
-    ops::{Bound, RangeBounds},
-    sync::Arc,
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+use alloc::{boxed::Box, collections::VecDeque, vec::Vec};
+use core::{
+    cmp::Ordering,
+    convert::TryFrom,
+    ops::{Bound, ControlFlow, Range, RangeBounds},
 };
 
-use btree::Tree;
-use tap::Tap;
+use archery::SharedPointer;
+use arrayvec::ArrayVec;
+use btree::{Leaves, Tree};
+use focus::Focus;
+
+/// Internal instrumentation, which compiles to nothing unless the `debug-trace` feature is enabled.
+macro_rules! trace {
+    ($($arg:tt)*) => {{
+        #[cfg(feature = "debug-trace")]
+        log::trace!($($arg)*);
+        #[cfg(not(feature = "debug-trace"))]
+        if false {
+            let _ = format_args!($($arg)*);
+        }
+    }};
+}
 
+#[cfg(feature = "arrow")]
+mod arrow;
 mod btree;
+#[cfg(feature = "bytes")]
+mod buf;
+mod bytes;
+mod cursor;
+mod diff;
+mod error;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(feature = "merkle")]
+mod file;
+mod focus;
+#[cfg(feature = "std")]
+mod graphviz;
+mod history;
+#[cfg(feature = "im")]
+mod im;
+mod invariant;
+#[cfg(feature = "std")]
+mod io;
+mod iter;
+mod lazy;
+mod leaf;
+mod measure;
+#[cfg(feature = "merkle")]
+mod merkle;
+#[cfg(feature = "rayon")]
+mod par;
+#[cfg(feature = "rand")]
+mod rand;
+mod rle;
+mod shared;
+mod stats;
+#[cfg(feature = "merkle")]
+mod store;
+#[cfg(feature = "merkle")]
+mod stored;
+mod string;
+mod visit;
+#[cfg(feature = "wasm")]
+mod wasm;
 
-/// A persistent, efficiently concatenable and sliceable vector. The const-generic type parameter ORD is the maximum fanout factor; a value from 32 to 128 usually works well.
-#[derive(Clone)]
-pub struct CatVec<T: Clone, const ORD: usize> {
-    inner: Box<Tree<T, ORD>>,
+pub use archery::{ArcK, RcK, SharedPointerKind};
+pub use bytes::{CatBytes, FindIter};
+pub use cursor::{Cursor, CursorMut};
+pub use diff::{Conflict, Edit, Hunk, Patch, PatchError};
+pub use error::{CatVecError, SliceError};
+#[cfg(feature = "merkle")]
+pub use file::{NodeFile, Pod};
+pub use history::History;
+pub use invariant::{InvariantViolation, ViolationKind};
+#[cfg(feature = "std")]
+pub use io::ByteCursor;
+pub use iter::{ChunkBy, Chunks, Iter, Runs, Split, Windows};
+pub use lazy::LazyCatVec;
+pub use leaf::SharedLeaf;
+pub use measure::{Measure, TextMetrics, TextSummary};
+#[cfg(feature = "merkle")]
+pub use merkle::Proof;
+#[cfg(feature = "rayon")]
+pub use par::ParIter;
+pub use rle::RleCatVec;
+pub use shared::SharedCatVec;
+pub use stats::{MemoryUsage, SharingStats, TreeStats};
+#[cfg(feature = "merkle")]
+pub use store::{CollectableStore, LoadError, NodeStore, StoredNode};
+#[cfg(feature = "merkle")]
+pub use stored::StoredCatVec;
+pub use string::CatString;
+pub use visit::NodeInfo;
+#[cfg(feature = "wasm")]
+pub use wasm::JsCatVec;
+
+/// A persistent, efficiently concatenable and sliceable vector. The const-generic type parameter ORD is the maximum fanout factor of internal nodes; a value from 32 to 128 usually works well. LEAF is the number of elements a leaf holds, which defaults to 32. Small element types do better with bigger leaves, as in `CatVec<u8, 32, 256>`. P is the kind of reference-counted pointer linking the nodes: [ArcK] by default, or [RcK], as in [CatVecLocal], for vectors that never leave their thread, which spares every clone and copy-on-write check an atomic operation. With [ArcK], the vector is [Send] and [Sync] whenever its elements are, so clones can be handed to other threads as snapshots. M is a [Measure] that internal nodes cache for each of their children, such as the line breaks that [CatString] counts; the default, `()`, measures nothing. ORD and LEAF must both be at least 2, or the vector fails to compile.
+///
+/// Elements pushed to the back are collected in a tail buffer of up to LEAF elements outside the tree, which is moved into the tree as one leaf when it fills up, so that pushing is amortized O(1). Likewise, a head buffer makes pushing and popping at the front amortized O(1). Diagnostics that describe the tree, such as [CatVec::tree_stats] or [CatVec::visit_nodes], don't include the buffers.
+pub struct CatVec<
+    T: Clone,
+    const ORD: usize,
+    const LEAF: usize = 32,
+    P: SharedPointerKind = ArcK,
+    M: Measure<T> = (),
+> {
+    inner: Box<Tree<T, ORD, LEAF, P, M>>,
+    /// Elements logically preceding everything in the tree.
+    head: SharedPointer<ArrayVec<T, LEAF>, P>,
+    /// Elements logically following everything in the tree.
+    tail: SharedPointer<ArrayVec<T, LEAF>, P>,
+    focus: Focus<T>,
 }
 
-impl<T: Clone + PartialEq, const ORD: usize> PartialEq<CatVec<T, ORD>> for CatVec<T, ORD> {
-    fn eq(&self, other: &Self) -> bool {
-        let first_length: usize = self.len();
-        let second_length: usize = other.len();
+/// A [CatVec] whose nodes are linked by [RcK] pointers, for single-threaded code. It has the same API, but clones and copy-on-write checks don't pay for atomic reference counts, which adds up in workloads that copy a lot. Unlike a plain [CatVec], it is neither [Send] nor [Sync].
+pub type CatVecLocal<T, const ORD: usize, const LEAF: usize = 32, M = ()> =
+    CatVec<T, ORD, LEAF, RcK, M>;
 
-        let do_lengths_match: bool = first_length == second_length;
+impl<T: Clone, const ORD: usize, const LEAF: usize, P: SharedPointerKind, M: Measure<T>> Clone
+    for CatVec<T, ORD, LEAF, P, M>
+{
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            head: self.head.clone(),
+            tail: self.tail.clone(),
+            focus: self.focus.clone(),
+        }
+    }
+}
 
-        if do_lengths_match {
-            let do_all_indexes_match: bool = (0..first_length).all(|index| {
-                let first_index: Option<&T> = self.get(index);
-                let second_index: Option<&T> = other.get(index);
+// Snapshots are meant to be shared across threads, so with the default pointer kind, the vector and the types borrowing from it must be Send and Sync whenever the elements are. These never run, but stop compiling if a field, such as a cache, ever breaks that.
+#[allow(dead_code)]
+const _: () = {
+    fn send_sync<X: Send + Sync>() {}
 
-                first_index.expect("Failed to unrwap first index") == second_index.expect("Failed to unrwap second index")
-            });
+    fn snapshots_are_send_and_sync<
+        'a,
+        T: Clone + Send + Sync + 'a,
+        const ORD: usize,
+        const LEAF: usize,
+        M: Measure<T> + 'a,
+    >() {
+        send_sync::<CatVec<T, ORD, LEAF, ArcK, M>>();
+        send_sync::<Iter<'a, T, ORD, LEAF, ArcK, M>>();
+        send_sync::<Cursor<'a, T, ORD, LEAF, ArcK, M>>();
+        send_sync::<CursorMut<'a, T, ORD, LEAF, ArcK, M>>();
+        send_sync::<SharedLeaf<T, ORD, LEAF, ArcK, M>>();
+        send_sync::<History<T, ORD, LEAF, ArcK, M>>();
+        send_sync::<LazyCatVec<T, ORD, LEAF, ArcK, M>>();
+        send_sync::<SharedCatVec<T, ORD, LEAF, ArcK>>();
+        send_sync::<RleCatVec<T, ORD, LEAF, ArcK>>();
+        send_sync::<CatString<ORD, LEAF, ArcK>>();
+    }
+};
 
-            do_all_indexes_match
-        } else {
-            do_lengths_match
-        }
+impl<
+        T: Clone + PartialEq,
+        const ORD: usize,
+        const LEAF: usize,
+        P: SharedPointerKind,
+        M: Measure<T>,
+    > PartialEq<CatVec<T, ORD, LEAF, P, M>> for CatVec<T, ORD, LEAF, P, M>
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len()
+            && zip_leaves(self.leaves(), other.leaves(), |x, y| {
+                if x == y {
+                    ControlFlow::Continue(())
+                } else {
+                    ControlFlow::Break(())
+                }
+            })
+            .is_continue()
     }
 }
 
-impl<T: Clone + Eq, const ORD: usize> Eq for CatVec<T, ORD> {}
+impl<T: Clone + Eq, const ORD: usize, const LEAF: usize, P: SharedPointerKind, M: Measure<T>> Eq
+    for CatVec<T, ORD, LEAF, P, M>
+{
+}
+
+impl<
+        T: Clone + PartialOrd,
+        const ORD: usize,
+        const LEAF: usize,
+        P: SharedPointerKind,
+        M: Measure<T>,
+    > PartialOrd for CatVec<T, ORD, LEAF, P, M>
+{
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        match zip_leaves(self.leaves(), other.leaves(), |x, y| {
+            match x.partial_cmp(y) {
+                Some(Ordering::Equal) => ControlFlow::Continue(()),
+                ord => ControlFlow::Break(ord),
+            }
+        }) {
+            ControlFlow::Break(ord) => ord,
+            ControlFlow::Continue(()) => Some(self.len().cmp(&other.len())),
+        }
+    }
+}
 
+impl<T: Clone + Ord, const ORD: usize, const LEAF: usize, P: SharedPointerKind, M: Measure<T>> Ord
+    for CatVec<T, ORD, LEAF, P, M>
+{
+    fn cmp(&self, other: &Self) -> Ordering {
+        match zip_leaves(self.leaves(), other.leaves(), |x, y| match x.cmp(y) {
+            Ordering::Equal => ControlFlow::Continue(()),
+            ord => ControlFlow::Break(ord),
+        }) {
+            ControlFlow::Break(ord) => ord,
+            ControlFlow::Continue(()) => self.len().cmp(&other.len()),
+        }
+    }
+}
 
-impl<T: Clone, V: AsRef<[T]>, const ORD: usize> From<V> for CatVec<T, ORD> {
+impl<
+        T: Clone,
+        V: AsRef<[T]>,
+        const ORD: usize,
+        const LEAF: usize,
+        P: SharedPointerKind,
+        M: Measure<T>,
+    > From<V> for CatVec<T, ORD, LEAF, P, M>
+{
     fn from(v: V) -> Self {
-        v.as_ref()
-            .iter()
-            .fold(CatVec::new(), |a, b| a.tap_mut(|a| a.push_back(b.clone())))
+        Self {
+            inner: Tree::build(v.as_ref().iter().cloned()).into(),
+            head: Default::default(),
+            tail: Default::default(),
+            focus: Focus::new(),
+        }
     }
 }
 
-impl<T: Clone, const ORD: usize> From<CatVec<T, ORD>> for Vec<T> {
-    fn from(cv: CatVec<T, ORD>) -> Self {
+impl<T: Clone, const ORD: usize, const LEAF: usize, P: SharedPointerKind, M: Measure<T>>
+    From<CatVec<T, ORD, LEAF, P, M>> for Vec<T>
+{
+    /// Moves the elements out of the vector in one pass over the leaves. Only elements in leaves shared with other vectors are cloned.
+    fn from(cv: CatVec<T, ORD, LEAF, P, M>) -> Self {
         let mut result = Vec::with_capacity(cv.len());
-        for i in 0..cv.len() {
-            result.push(cv.get(i).unwrap().clone());
-        }
+        let CatVec {
+            inner, head, tail, ..
+        } = cv;
+        let move_buffer = |buffer: SharedPointer<ArrayVec<T, LEAF>, P>, result: &mut Vec<T>| {
+            match SharedPointer::try_unwrap(buffer) {
+                Ok(buffer) => result.extend(buffer),
+                Err(shared) => result.extend_from_slice(&shared),
+            }
+        };
+        move_buffer(head, &mut result);
+        inner.move_into(&mut result);
+        move_buffer(tail, &mut result);
         result
     }
 }
 
-impl<T: Clone + std::fmt::Debug, const ORD: usize> std::fmt::Debug for CatVec<T, ORD> {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+/// The conversion the other way is [CatVec::from_vec_deque], a constructor rather than a `From<VecDeque<T>>` impl, since that impl would overlap the one for `AsRef<[T]>` types.
+impl<T: Clone, const ORD: usize, const LEAF: usize, P: SharedPointerKind, M: Measure<T>>
+    From<CatVec<T, ORD, LEAF, P, M>> for VecDeque<T>
+{
+    /// Moves the elements out into a `Vec`, which the deque then takes over without copying.
+    fn from(cv: CatVec<T, ORD, LEAF, P, M>) -> Self {
+        Vec::from(cv).into()
+    }
+}
+
+impl<
+        T: Clone + core::fmt::Debug,
+        const ORD: usize,
+        const LEAF: usize,
+        P: SharedPointerKind,
+        M: Measure<T>,
+    > core::fmt::Debug for CatVec<T, ORD, LEAF, P, M>
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         let v: Vec<_> = self.clone().into();
-        std::fmt::Debug::fmt(&v, f)
+        core::fmt::Debug::fmt(&v, f)
     }
 }
 
-impl<T: Clone + std::fmt::Debug, const ORD: usize> CatVec<T, ORD> {
+impl<
+        T: Clone + core::fmt::Debug,
+        const ORD: usize,
+        const LEAF: usize,
+        P: SharedPointerKind,
+        M: Measure<T>,
+    > CatVec<T, ORD, LEAF, P, M>
+{
     /// Debug graphviz.
+    #[cfg(feature = "std")]
     pub fn debug_graphviz(&self) {
-        Arc::new(*self.inner.clone()).eprint_graphviz();
+        self.inner.eprint_graphviz();
     }
 }
 
-impl<T: Clone, const ORD: usize> CatVec<T, ORD> {
+impl<T: Clone, const ORD: usize, const LEAF: usize, P: SharedPointerKind, M: Measure<T>>
+    CatVec<T, ORD, LEAF, P, M>
+{
     /// Creates a new empty CatVec.
     pub fn new() -> Self {
         Self {
             inner: Tree::new().into(),
+            head: Default::default(),
+            tail: Default::default(),
+            focus: Focus::new(),
+        }
+    }
+
+    /// Builds a vector out of a [VecDeque], moving its elements straight into packed leaves rather than pushing them one at a time. A `From<VecDeque<T>>` impl would collide with the one for `AsRef<[T]>` types, so this is a plain constructor.
+    pub fn from_vec_deque(v: VecDeque<T>) -> Self {
+        Self {
+            inner: Tree::build(v).into(),
+            head: Default::default(),
+            tail: Default::default(),
+            focus: Focus::new(),
         }
     }
 
-    /// Gets a reference to the element at a particular position.
+    /// Gets a reference to the element at a particular position. Looking up positions close to the previous lookup is fast, since the last leaf visited is remembered. Threads reading one vector share that memo and contend for it on every miss, so for scattered lookups from many threads, give each thread a clone.
     pub fn get(&self, i: usize) -> Option<&T> {
-        self.inner.get(i)
+        let Some(i) = i.checked_sub(self.head.len()) else {
+            return self.head.get(i);
+        };
+        // SAFETY: the focus is cleared by tree_mut before the tree is ever modified.
+        if let Some(x) = unsafe { self.focus.get(i) } {
+            return Some(x);
+        }
+        if i >= self.inner.len() {
+            return self.tail.get(i - self.inner.len());
+        }
+        let (start, leaf) = self.inner.leaf_at(i);
+        self.focus.set(start, leaf);
+        leaf.get(i - start)
     }
 
-    /// Gets a mutable reference to the element at a particular position.
-    pub fn get_mut(&mut self, i: usize) -> Option<&mut T> {
-        self.inner.get_mut(i)
+    /// Finds the leaf containing the given position, counting the head and tail as the first and last leaves, and returns the position of its first element along with its contents. The position equal to the length maps to the last leaf.
+    pub(crate) fn leaf_at(&self, i: usize) -> (usize, &[T]) {
+        let head = self.head.len();
+        let tree_end = head + self.inner.len();
+        if i < head || (i == head && tree_end == head && self.tail.is_empty()) {
+            (0, &self.head)
+        } else if i >= tree_end && !self.tail.is_empty() {
+            (tree_end, &self.tail)
+        } else {
+            let (start, leaf) = self.inner.leaf_at(i - head);
+            (start + head, leaf)
+        }
+    }
+
+    /// Iterates over the leaves in order, including the head and tail.
+    fn leaves(&self) -> Leaves<'_, T, ORD, LEAF, P, M> {
+        self.inner.leaves().with_ends(&self.head, &self.tail)
+    }
+
+    /// Gives mutable access to the tree, leaving the head and tail buffers alone. All modifications of the tree must go through here, so that the focus never points at a stale leaf.
+    fn tree_mut(&mut self) -> &mut Tree<T, ORD, LEAF, P, M> {
+        self.focus.clear();
+        &mut self.inner
+    }
+
+    /// Gives mutable access to the tree and both buffers at once, for edits that span them. Like [CatVec::tree_mut], it forgets the focus first.
+    #[allow(clippy::type_complexity)]
+    fn parts_mut(
+        &mut self,
+    ) -> (
+        &mut Tree<T, ORD, LEAF, P, M>,
+        &mut SharedPointer<ArrayVec<T, LEAF>, P>,
+        &mut SharedPointer<ArrayVec<T, LEAF>, P>,
+    ) {
+        self.focus.clear();
+        (&mut self.inner, &mut self.head, &mut self.tail)
+    }
+
+    /// Gives mutable access to the tree, with the head and tail buffers moved into it, so that positions in the tree are positions in the vector.
+    fn inner_mut(&mut self) -> &mut Tree<T, ORD, LEAF, P, M> {
+        self.flush_head();
+        self.flush_tail();
+        self.tree_mut()
+    }
+
+    /// Moves the head buffer into the tree as its first leaf.
+    fn flush_head(&mut self) {
+        if !self.head.is_empty() {
+            let head = Tree::build(take_buffer(&mut self.head));
+            let tree = self.tree_mut();
+            let rest = core::mem::replace(tree, head);
+            tree.concat(rest);
+            tree.collapse_root();
+        }
+    }
+
+    /// Moves the tail buffer into the tree as its last leaf.
+    fn flush_tail(&mut self) {
+        if !self.tail.is_empty() {
+            let tail = Tree::build(take_buffer(&mut self.tail));
+            let tree = self.tree_mut();
+            tree.concat(tail);
+            tree.collapse_root();
+        }
+    }
+
+    /// Returns an iterator over the elements of the vector.
+    pub fn iter(&self) -> Iter<'_, T, ORD, LEAF, P, M> {
+        Iter::new(self.leaves(), self.len())
+    }
+
+    /// Returns all the elements as one contiguous slice, if they are all in the same leaf, which is usually the case for vectors of up to LEAF elements. An empty vector gives an empty slice. Lets small vectors be handed to code that works on slices without iterating or copying.
+    pub fn as_single_slice(&self) -> Option<&[T]> {
+        let mut leaves = self.leaves();
+        match (leaves.next(), leaves.next()) {
+            (None, _) => Some(&[]),
+            (Some(leaf), None) => Some(leaf),
+            _ => None,
+        }
+    }
+
+    /// Calls `f` on each leaf of the vector in order, as a contiguous slice. Empty leaves are skipped. This is the fast path for linear scans such as hashing, encoding or computing statistics, since it hands out whole slices without the per-element bookkeeping of [CatVec::iter].
+    pub fn for_each_chunk(&self, f: impl FnMut(&[T])) {
+        self.leaves().for_each(f)
+    }
+
+    /// Folds over the leaves of the vector in order, passing each one as a contiguous slice. Like [CatVec::for_each_chunk], but threads an accumulator through.
+    pub fn fold_chunks<B>(&self, init: B, f: impl FnMut(B, &[T]) -> B) -> B {
+        self.leaves().fold(init, f)
+    }
+
+    /// Returns non-overlapping chunks of `size` elements, independent of how the elements are laid out in leaves, to be stepped through with [Chunks::next_chunk]. The last chunk may be shorter. Panics if `size` is zero.
+    pub fn chunks(&self, size: usize) -> Chunks<'_, T, ORD, LEAF, P, M> {
+        Chunks::new(self.iter(), size)
+    }
+
+    /// Returns all overlapping windows of `size` elements, like [slice::windows], to be stepped through with [Windows::next_window]. Panics if `size` is zero.
+    pub fn windows(&self, size: usize) -> Windows<'_, T, ORD, LEAF, P, M> {
+        Windows::new(self.iter(), size)
+    }
+
+    /// Returns an iterator over maximal runs of elements in which every consecutive pair satisfies the predicate, like [slice::chunk_by].
+    pub fn chunk_by<F: FnMut(&T, &T) -> bool>(
+        &self,
+        pred: F,
+    ) -> ChunkBy<'_, T, ORD, LEAF, P, M, F> {
+        ChunkBy::new(self.iter(), pred)
+    }
+
+    /// Returns an iterator over the maximal runs of equal consecutive elements, as pairs of a run's length and its element, scanning a leaf slice at a time.
+    pub fn runs(&self) -> Runs<'_, T, ORD, LEAF, P, M>
+    where
+        T: PartialEq,
+    {
+        Runs::new(self.leaves())
+    }
+
+    /// Returns an iterator over the pieces of the vector separated by elements matching the predicate, like [slice::split], but with each piece a sub-vector sharing structure with this one, as from [CatVec::subvec], so that no leaf in the middle of a piece is copied. The separators are left out; consecutive separators, or one at either end, yield empty pieces.
+    pub fn split<F: FnMut(&T) -> bool>(&self, pred: F) -> Split<'_, T, ORD, LEAF, P, M, F> {
+        Split::new(self, pred)
+    }
+
+    /// Returns a read-only cursor positioned at the given index. Moving the cursor to nearby positions is amortized O(1).
+    pub fn cursor(&self, idx: usize) -> Cursor<'_, T, ORD, LEAF, P, M> {
+        Cursor::new(&self.inner, &self.head, &self.tail, idx)
+    }
+
+    /// Returns a cursor positioned at the given index that can edit the vector in place. Pending edits are spliced into the vector when the cursor is dropped.
+    pub fn cursor_mut(&mut self, idx: usize) -> CursorMut<'_, T, ORD, LEAF, P, M> {
+        CursorMut::new(self, idx)
     }
 
-    /// Slices a subset of the vector. "Zooms into" a part of the vector.
+    /// Slices a subset of the vector. "Zooms into" a part of the vector. Panics if the range starts after it ends or ends past the end of the vector, with a message giving the range and the length.
     pub fn slice_into(&mut self, range: impl RangeBounds<usize>) {
-        let start = match range.start_bound() {
-            Bound::Excluded(i) => Some(*i + 1),
-            Bound::Included(i) => Some(*i),
-            Bound::Unbounded => None,
-        };
-        let end = match range.end_bound() {
-            Bound::Excluded(i) => Some(*i),
-            Bound::Included(i) => Some(*i + 1),
-            Bound::Unbounded => None,
-        };
-        if let Some(end) = end {
-            self.inner.take_head(end)
+        if let Err(e) = self.try_slice_into(range) {
+            panic!("{}", e)
+        }
+    }
+
+    /// Like [CatVec::slice_into], but returns an error instead of panicking if the range is inverted or out of bounds, leaving the vector as it was.
+    pub fn try_slice_into(&mut self, range: impl RangeBounds<usize>) -> Result<(), SliceError> {
+        let len = self.len();
+        let (start, end) = range_bounds(range, len);
+        if start > end || end > len {
+            return Err(SliceError { start, end, len });
+        }
+        if end < len {
+            self.inner_mut().take_head(end)
+        }
+        if start > 0 {
+            self.inner_mut().drop_head(start)
+        }
+        self.normalize();
+        Ok(())
+    }
+
+    /// Calls `f` on each element in the range, in order, in a single walk down the tree. Only the leaves overlapping the range, and the nodes above them, are copied if shared, and the cached measures are brought up to date on the way back up. Much faster than calling [CatVec::get_mut] on each position, which descends from the root every time. Panics if the range is inverted or out of bounds.
+    pub fn apply_in_place(&mut self, range: impl RangeBounds<usize>, mut f: impl FnMut(&mut T)) {
+        let [head, tree, tail] = self.split_range(range);
+        if !head.is_empty() {
+            SharedPointer::make_mut(&mut self.head)[head]
+                .iter_mut()
+                .for_each(&mut f);
+        }
+        if !tree.is_empty() {
+            self.tree_mut().apply(tree.start, tree.end, &mut f);
         }
-        if let Some(start) = start {
-            self.inner.drop_head(start)
+        if !tail.is_empty() {
+            SharedPointer::make_mut(&mut self.tail)[tail]
+                .iter_mut()
+                .for_each(f);
+        }
+    }
+
+    /// Splits a range of positions into the parts falling in the head buffer, the tree and the tail buffer, each relative to the start of its part. Panics if the range is inverted or out of bounds.
+    fn split_range(&self, range: impl RangeBounds<usize>) -> [Range<usize>; 3] {
+        let len = self.len();
+        let (start, end) = range_bounds(range, len);
+        if start > end || end > len {
+            panic!("{}", SliceError { start, end, len })
         }
+        let head = self.head.len();
+        let tree_end = head + self.inner.len();
+        [
+            start.min(head)..end.min(head),
+            start.clamp(head, tree_end) - head..end.clamp(head, tree_end) - head,
+            start.max(tree_end) - tree_end..end.max(tree_end) - tree_end,
+        ]
     }
 
     /// Concatenates this vector with another one. Consumes the other vector.
-    pub fn append(&mut self, other: Self) {
-        self.inner.concat(*other.inner)
+    pub fn append(&mut self, mut other: Self) {
+        self.flush_tail();
+        other.flush_head();
+        let tree = self.tree_mut();
+        tree.concat(*other.inner);
+        tree.collapse_root();
+        self.tail = other.tail;
+    }
+
+    /// Appends clones of all the elements of a slice. The tail buffer is topped up first; the rest is cut into full leaves, built into a compact tree, and concatenated on, so the cost is one pass over the slice plus one concatenation, rather than a push per element.
+    pub fn extend_from_slice(&mut self, items: &[T]) {
+        let room = (LEAF - self.tail.len()).min(items.len());
+        let (front, rest) = items.split_at(room);
+        if !front.is_empty() {
+            SharedPointer::make_mut(&mut self.tail).extend(front.iter().cloned());
+        }
+        if rest.is_empty() {
+            return;
+        }
+        let (full, partial) = rest.split_at(rest.len() - rest.len() % LEAF);
+        self.append(CatVec {
+            inner: Tree::build_from_leaves(full.chunks(LEAF).map(leaf_from_slice)).into(),
+            head: Default::default(),
+            tail: SharedPointer::new(leaf_from_slice(partial)),
+            focus: Focus::new(),
+        });
+    }
+
+    /// Splits the vector in two at the given position, keeping the elements before it and returning the rest, like [Vec::split_off]. Panics if the position is past the end.
+    ///
+    /// Nodes are moved into the halves rather than shared between them: a uniquely owned vector is split in place, copying nothing, and a shared one copies only the nodes on the path to the split. To keep the whole vector as well as a part, [CatVec::subvec] is cheaper.
+    pub fn split_off(&mut self, at: usize) -> Self {
+        let len = self.len();
+        assert!(
+            at <= len,
+            "`at` split index (is {}) should be <= len (is {})",
+            at,
+            len
+        );
+        if at == len {
+            return Self::new();
+        }
+        if at == 0 {
+            return core::mem::take(self);
+        }
+        let mut right = Self::new();
+        *right.inner = self.inner_mut().split_off(at);
+        right.normalize();
+        self.normalize();
+        right
+    }
+
+    /// Collapses chains of single-child nodes at the top of the tree, so that its height reflects the actual size. Slicing and appending already do this.
+    pub fn normalize(&mut self) {
+        self.inner_mut().collapse_root()
     }
 
-    /// Inserts the given element at the given position, shifting all elements after that rightwards.
+    /// Inserts the given element at the given position, shifting all elements after that rightwards. Panics if the position is past the end, like [Vec::insert].
     pub fn insert(&mut self, idx: usize, val: T) {
-        self.inner.insert(idx, val);
+        let len = self.len();
+        assert!(
+            idx <= len,
+            "insertion index (is {}) should be <= len (is {})",
+            idx,
+            len
+        );
+        self.inner_mut().insert(idx, val);
     }
 
-    /// Pushes to the back of the vector.
-    pub fn push_back(&mut self, val: T) {
+    /// Like [CatVec::insert], but returns an error instead of panicking if the position is past the end.
+    pub fn try_insert(&mut self, idx: usize, val: T) -> Result<(), CatVecError> {
         let len = self.len();
-        self.insert(len, val)
+        if idx > len {
+            return Err(CatVecError::OutOfBounds { idx, len });
+        }
+        self.inner_mut().insert(idx, val);
+        Ok(())
+    }
+
+    /// Removes and returns the element at the given position, shifting all elements after it leftwards. Panics if the position is out of bounds.
+    pub fn remove(&mut self, idx: usize) -> T {
+        match self.try_remove(idx) {
+            Ok(val) => val,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    /// Like [CatVec::remove], but returns an error instead of panicking if the position is out of bounds.
+    pub fn try_remove(&mut self, idx: usize) -> Result<T, CatVecError> {
+        let len = self.len();
+        self.cursor_mut(idx)
+            .remove_current()
+            .ok_or(CatVecError::OutOfBounds { idx, len })
+    }
+
+    /// Returns a range of the vector as a vector of its own, in O(log n) time. Only the nodes along the two ends of the range are copied and trimmed; the subtrees between them are shared with this vector, so no interior leaf is copied. This is the way to hand out windows of a big vector to independent owners. Panics if the range is inverted or out of bounds, like [CatVec::slice_into].
+    pub fn subvec(&self, range: impl RangeBounds<usize>) -> Self {
+        let mut sub = self.clone();
+        sub.slice_into(range);
+        sub
+    }
+
+    /// Like [CatVec::subvec], but returns an error instead of panicking if the range is out of bounds or inverted.
+    pub fn try_slice(&self, range: impl RangeBounds<usize>) -> Result<Self, CatVecError> {
+        let mut slice = self.clone();
+        slice.try_slice_into(range)?;
+        Ok(slice)
+    }
+
+    /// Pushes to the back of the vector. Amortized O(1), since the element goes into the tail buffer, and the tree is only touched once every LEAF pushes.
+    pub fn push_back(&mut self, val: T) {
+        if self.tail.is_full() {
+            self.flush_tail();
+        }
+        SharedPointer::make_mut(&mut self.tail).push(val);
+    }
+
+    /// Pushes to the front of the vector. Amortized O(1), like [CatVec::push_back], using the head buffer.
+    pub fn push_front(&mut self, val: T) {
+        if self.head.is_full() {
+            self.flush_head();
+        }
+        SharedPointer::make_mut(&mut self.head).insert(0, val);
+    }
+
+    /// Removes and returns the first element, or None if the vector is empty. Amortized O(1): when the head buffer runs out, the first leaf of the tree is moved into it as a whole.
+    pub fn pop_front(&mut self) -> Option<T> {
+        if self.head.is_empty() {
+            if self.inner.len() == 0 {
+                if self.tail.is_empty() {
+                    return None;
+                }
+                return Some(SharedPointer::make_mut(&mut self.tail).remove(0));
+            }
+            let leaf: ArrayVec<T, LEAF> = self.inner.leaf_at(0).1.iter().cloned().collect();
+            let tree = self.tree_mut();
+            tree.drop_head(leaf.len());
+            tree.collapse_root();
+            self.head = SharedPointer::new(leaf);
+        }
+        Some(SharedPointer::make_mut(&mut self.head).remove(0))
+    }
+
+    /// Returns true if the two vectors are cheaply known to be the same version, i.e. one is an unmodified clone of the other. A false result doesn't mean the contents differ.
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        let same = |a: &SharedPointer<ArrayVec<T, LEAF>, P>,
+                    b: &SharedPointer<ArrayVec<T, LEAF>, P>| {
+            SharedPointer::ptr_eq(a, b) || (a.is_empty() && b.is_empty())
+        };
+        same(&self.head, &other.head)
+            && same(&self.tail, &other.tail)
+            && self.inner.ptr_eq(&other.inner)
+    }
+
+    /// Measures how much of this vector's structure is shared with another vector, such as an older version of it.
+    pub fn shared_bytes_with(&self, other: &Self) -> SharingStats {
+        let mut stats = SharingStats::compute(&self.inner, &other.inner);
+        for buffer in [&self.head, &self.tail] {
+            let shared = SharedPointer::ptr_eq(buffer, &other.head)
+                || SharedPointer::ptr_eq(buffer, &other.tail);
+            stats.add_buffer(buffer, shared);
+        }
+        stats
+    }
+
+    /// Estimates the heap memory used by the vector, counting nodes shared within it once.
+    pub fn memory_usage(&self) -> MemoryUsage {
+        let mut usage = MemoryUsage::compute(&self.inner);
+        for buffer in [&self.head, &self.tail] {
+            if !buffer.is_empty() {
+                usage.nodes += 1;
+                usage.node_bytes += core::mem::size_of::<ArrayVec<T, LEAF>>();
+                usage.element_bytes += core::mem::size_of_val(buffer.as_slice());
+            }
+        }
+        usage
+    }
+
+    /// The number of internal levels above the leaves. This is zero for vectors small enough to fit in a single leaf, which are stored without any internal nodes.
+    pub fn height(&self) -> usize {
+        self.inner.height()
+    }
+
+    /// Summarizes the shape of the underlying tree.
+    pub fn tree_stats(&self) -> TreeStats {
+        TreeStats::compute(&self.inner)
     }
 
     /// Length of vector.
     pub fn len(&self) -> usize {
-        self.inner.len()
+        self.head.len() + self.inner.len() + self.tail.len()
+    }
+
+    /// Returns true if the vector has no elements.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns true if the vector contains an element equal to the given value.
+    pub fn contains(&self, x: &T) -> bool
+    where
+        T: PartialEq,
+    {
+        self.leaves().any(|leaf| leaf.contains(x))
+    }
+
+    /// Returns the index of the first element satisfying the predicate, scanning leaf by leaf.
+    pub fn position(&self, mut pred: impl FnMut(&T) -> bool) -> Option<usize> {
+        let mut offset = 0;
+        for leaf in self.leaves() {
+            if let Some(i) = leaf.iter().position(&mut pred) {
+                return Some(offset + i);
+            }
+            offset += leaf.len();
+        }
+        None
+    }
+
+    /// Binary searches this sorted vector for the given element. Behaves like [slice::binary_search].
+    pub fn binary_search(&self, x: &T) -> Result<usize, usize>
+    where
+        T: Ord,
+    {
+        self.binary_search_by(|p| p.cmp(x))
+    }
+
+    /// Binary searches this sorted vector with a comparator function. Behaves like [slice::binary_search_by].
+    pub fn binary_search_by(&self, mut f: impl FnMut(&T) -> Ordering) -> Result<usize, usize> {
+        let head = self.head.len();
+        let offset = |r: Result<usize, usize>, by: usize| r.map(|i| i + by).map_err(|i| i + by);
+        match (self.head.last(), self.tail.first()) {
+            (Some(last), _) if f(last) != Ordering::Less => self.head.binary_search_by(f),
+            (_, Some(first)) if f(first) != Ordering::Greater => {
+                offset(self.tail.binary_search_by(f), head + self.inner.len())
+            }
+            _ => offset(self.inner.binary_search_by(f), head),
+        }
+    }
+
+    /// Binary searches this sorted vector with a key extraction function. Behaves like [slice::binary_search_by_key].
+    pub fn binary_search_by_key<B: Ord>(
+        &self,
+        b: &B,
+        mut f: impl FnMut(&T) -> B,
+    ) -> Result<usize, usize> {
+        self.binary_search_by(|k| f(k).cmp(b))
+    }
+
+    /// Inserts an element into this sorted vector, keeping it sorted. Equal elements are inserted after existing ones. Returns the index of the insertion.
+    pub fn insert_sorted(&mut self, val: T) -> usize
+    where
+        T: Ord,
+    {
+        self.inner_mut().insert_sorted_by(T::cmp, val)
+    }
+
+    /// Inserts an element into a vector sorted by the given key, keeping it sorted. Returns the index of the insertion.
+    pub fn insert_sorted_by_key<K: Ord>(&mut self, val: T, mut f: impl FnMut(&T) -> K) -> usize {
+        let key = f(&val);
+        self.inner_mut()
+            .insert_sorted_by(|p, _| f(p).cmp(&key), val)
+    }
+
+    /// Merges two sorted vectors into a new sorted vector. Runs that don't interleave with the other vector are moved over as whole shared subtrees, rather than element by element. The merge is stable: on ties, elements from `self` come first.
+    pub fn merge_sorted(&self, other: &Self) -> Self
+    where
+        T: Ord,
+    {
+        self.merge_sorted_by(other, T::cmp)
+    }
+
+    /// Merges two vectors sorted by the given comparator. See [CatVec::merge_sorted].
+    pub fn merge_sorted_by(&self, other: &Self, mut cmp: impl FnMut(&T, &T) -> Ordering) -> Self {
+        let mut result = Self::new();
+        let mut left = self.clone();
+        let mut right = other.clone();
+        while let Some(right_head) = right.get(0).cloned() {
+            let n = left.upper_bound_by(|p| cmp(p, &right_head));
+            result.append(left.split_off_head(n));
+            let left_head = match left.get(0) {
+                Some(x) => x.clone(),
+                None => break,
+            };
+            let n = right.lower_bound_by(|p| cmp(p, &left_head));
+            result.append(right.split_off_head(n));
+        }
+        result.append(left);
+        result.append(right);
+        result
+    }
+
+    /// Sorts the vector. The sort is stable.
+    pub fn sort(&mut self)
+    where
+        T: Ord,
+    {
+        self.sort_by(T::cmp)
+    }
+
+    /// Sorts the vector with a comparator function. The sort is stable.
+    ///
+    /// Each leaf is sorted on its own, and the sorted leaves are then combined pairwise with [CatVec::merge_sorted_by], so already-ordered stretches of the vector are carried over as shared subtrees.
+    pub fn sort_by(&mut self, mut cmp: impl FnMut(&T, &T) -> Ordering) {
+        let mut runs: Vec<Self> = self
+            .leaves()
+            .map(|leaf| {
+                let mut leaf = leaf.to_vec();
+                leaf.sort_by(&mut cmp);
+                Self::from(leaf)
+            })
+            .collect();
+        while runs.len() > 1 {
+            runs = runs
+                .chunks(2)
+                .map(|pair| match pair {
+                    [a, b] => a.merge_sorted_by(b, &mut cmp),
+                    [a] => a.clone(),
+                    _ => unreachable!(),
+                })
+                .collect();
+        }
+        *self = runs.pop().unwrap_or_default();
+    }
+
+    /// Removes the first `n` elements, returning them as a new vector.
+    fn split_off_head(&mut self, n: usize) -> Self {
+        let rest = self.split_off(n);
+        core::mem::replace(self, rest)
+    }
+
+    /// Finds the index just past the last element comparing less than or equal to the target.
+    fn upper_bound_by(&self, mut f: impl FnMut(&T) -> Ordering) -> usize {
+        match self.binary_search_by(|p| f(p).then(Ordering::Less)) {
+            Ok(i) | Err(i) => i,
+        }
+    }
+
+    /// Finds the index of the first element comparing greater than or equal to the target.
+    fn lower_bound_by(&self, mut f: impl FnMut(&T) -> Ordering) -> usize {
+        match self.binary_search_by(|p| f(p).then(Ordering::Greater)) {
+            Ok(i) | Err(i) => i,
+        }
+    }
+
+    /// Rebuilds the vector into the most compact shape, with full leaves and minimal height, in a single pass. Useful for restoring indexing performance of a long-lived vector after many slices and concatenations.
+    pub fn compact(&mut self) {
+        let compacted = Tree::build(self.iter().cloned());
+        *self.inner_mut() = compacted;
+    }
+
+    /// Returns a copy of the vector rebuilt into its canonical shape, the one [CatVec::compact] produces, which depends only on the number of elements. Vectors with the same contents and parameters have the same canonical shape however they were built, so structure-derived values such as the storage hash agree.
+    pub fn canonicalize(&self) -> Self {
+        let mut canonical = self.clone();
+        canonical.compact();
+        canonical
     }
 
     /// Check invariant.
     pub fn check_invariants(&self) {
         self.inner.check_invariants();
     }
+
+    /// Checks invariants, reporting which one is broken and where, instead of panicking.
+    pub fn validate(&self) -> Result<(), InvariantViolation> {
+        self.inner.validate(&mut Vec::new())
+    }
+}
+
+// Handing out mutable references to elements would let the measures cached for them go stale, so only unmeasured vectors can do it.
+impl<T: Clone, const ORD: usize, const LEAF: usize, P: SharedPointerKind> CatVec<T, ORD, LEAF, P> {
+    /// Gets a mutable reference to the element at a particular position.
+    pub fn get_mut(&mut self, i: usize) -> Option<&mut T> {
+        let head = self.head.len();
+        let tree_end = head + self.inner.len();
+        if i < head {
+            return SharedPointer::make_mut(&mut self.head).get_mut(i);
+        }
+        if i >= tree_end {
+            return SharedPointer::make_mut(&mut self.tail).get_mut(i - tree_end);
+        }
+        self.tree_mut().get_mut(i - head)
+    }
+
+    /// Gets mutable references to the elements at two distinct positions at once, such as for swapping them. Returns None if either position is out of bounds or they are the same position.
+    pub fn get2_mut(&mut self, i: usize, j: usize) -> Option<(&mut T, &mut T)> {
+        let len = self.len();
+        if i == j || i >= len || j >= len {
+            return None;
+        }
+        let head_len = self.head.len();
+        let tree_end = head_len + self.inner.len();
+        let region = |k: usize| (k >= head_len) as u8 + (k >= tree_end) as u8;
+        if region(i) == region(j) {
+            return match region(i) {
+                0 => {
+                    let [x, y] = SharedPointer::make_mut(&mut self.head)
+                        .get_disjoint_mut([i, j])
+                        .ok()?;
+                    Some((x, y))
+                }
+                1 => self.tree_mut().get2_mut(i - head_len, j - head_len),
+                _ => {
+                    let [x, y] = SharedPointer::make_mut(&mut self.tail)
+                        .get_disjoint_mut([i - tree_end, j - tree_end])
+                        .ok()?;
+                    Some((x, y))
+                }
+            };
+        }
+        // The two positions are in different parts, so borrow the parts separately.
+        let (inner, head, tail) = self.parts_mut();
+        let mut parts = (Some(head), Some(inner), Some(tail));
+        let x = part_get_mut(&mut parts, i, head_len, tree_end)?;
+        let y = part_get_mut(&mut parts, j, head_len, tree_end)?;
+        Some((x, y))
+    }
 }
 
-impl<T: Clone, const ORD: usize> Default for CatVec<T, ORD> {
+/// Walks two sequences of slices in step, calling `f` on each pair of equal-length pieces they line up into, until `f` breaks or either sequence runs out. Comparing the pieces as whole slices, rather than element by element, lets the comparisons of byte slices compile down to `memcmp`.
+fn zip_leaves<'a, T: 'a, B>(
+    mut a: impl Iterator<Item = &'a [T]>,
+    mut b: impl Iterator<Item = &'a [T]>,
+    mut f: impl FnMut(&'a [T], &'a [T]) -> ControlFlow<B>,
+) -> ControlFlow<B> {
+    let (mut x, mut y): (&[T], &[T]) = (&[], &[]);
+    loop {
+        if x.is_empty() {
+            match a.next() {
+                Some(leaf) => x = leaf,
+                None => return ControlFlow::Continue(()),
+            }
+        }
+        if y.is_empty() {
+            match b.next() {
+                Some(leaf) => y = leaf,
+                None => return ControlFlow::Continue(()),
+            }
+        }
+        let n = x.len().min(y.len());
+        f(&x[..n], &y[..n])?;
+        x = &x[n..];
+        y = &y[n..];
+    }
+}
+
+/// The head, tree and tail of a vector, each of which can be taken once to get a mutable reference into it.
+type Parts<'a, T, const ORD: usize, const LEAF: usize, P, M> = (
+    Option<&'a mut SharedPointer<ArrayVec<T, LEAF>, P>>,
+    Option<&'a mut Tree<T, ORD, LEAF, P, M>>,
+    Option<&'a mut SharedPointer<ArrayVec<T, LEAF>, P>>,
+);
+
+/// Gets a mutable reference to the element at position `k` out of whichever of `parts` holds it, taking that part.
+fn part_get_mut<'a, T: Clone, const ORD: usize, const LEAF: usize, P: SharedPointerKind>(
+    parts: &mut Parts<'a, T, ORD, LEAF, P, ()>,
+    k: usize,
+    head_len: usize,
+    tree_end: usize,
+) -> Option<&'a mut T> {
+    if k < head_len {
+        SharedPointer::make_mut(parts.0.take()?).get_mut(k)
+    } else if k < tree_end {
+        parts.1.take()?.get_mut(k - head_len)
+    } else {
+        SharedPointer::make_mut(parts.2.take()?).get_mut(k - tree_end)
+    }
+}
+
+/// Resolves a range against a length into its start and end positions, without checking that they are in bounds. Bounds at `usize::MAX` saturate rather than overflow, so they come out out of bounds.
+fn range_bounds(range: impl RangeBounds<usize>, len: usize) -> (usize, usize) {
+    let start = match range.start_bound() {
+        Bound::Excluded(i) => i.saturating_add(1),
+        Bound::Included(i) => *i,
+        Bound::Unbounded => 0,
+    };
+    let end = match range.end_bound() {
+        Bound::Excluded(i) => *i,
+        Bound::Included(i) => i.saturating_add(1),
+        Bound::Unbounded => len,
+    };
+    (start, end)
+}
+
+/// Clones up to one leaf's worth of elements into a leaf.
+fn leaf_from_slice<T: Clone, const LEAF: usize>(items: &[T]) -> ArrayVec<T, LEAF> {
+    ArrayVec::try_from(items).unwrap()
+}
+
+/// Takes the contents of a head or tail buffer, leaving it empty, without copying unless the buffer is shared.
+fn take_buffer<T: Clone, const LEAF: usize, P: SharedPointerKind>(
+    buffer: &mut SharedPointer<ArrayVec<T, LEAF>, P>,
+) -> ArrayVec<T, LEAF> {
+    SharedPointer::try_unwrap(core::mem::take(buffer)).unwrap_or_else(|buffer| (*buffer).clone())
+}
+
+impl<'a, T: Clone, const ORD: usize, const LEAF: usize, P: SharedPointerKind, M: Measure<T>>
+    IntoIterator for &'a CatVec<T, ORD, LEAF, P, M>
+{
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T, ORD, LEAF, P, M>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<T: Clone, const ORD: usize, const LEAF: usize, P: SharedPointerKind, M: Measure<T>> Default
+    for CatVec<T, ORD, LEAF, P, M>
+{
     fn default() -> Self {
         Self::new()
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::collections::VecDeque;
+    use core::cmp::Ordering;
+
+    use archery::ArcK;
+
+    use crate::{CatVec, CatVecError, CatVecLocal, TextMetrics};
+
+    #[test]
+    fn checked_slice_into() {
+        let mut v: CatVec<usize, 4, 4> = (0..100).collect::<Vec<_>>().into();
+        v.push_front(1000);
+        let (start, end) = (50, 40);
+        let inverted = v.try_slice_into(start..end).unwrap_err();
+        assert_eq!(inverted.to_string(), "range starts at 50 but ends at 40");
+        let past_end = v.try_slice_into(90..=101).unwrap_err();
+        assert_eq!(
+            past_end.to_string(),
+            "range 90..102 out of bounds for a vector of length 101"
+        );
+        assert!(v.try_slice_into(..=usize::MAX).is_err());
+        assert!(v.try_slice_into(102..).is_err());
+        assert_eq!(v.len(), 101);
+        v.try_slice_into(101..).unwrap();
+        assert!(v.is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "range 3..12 out of bounds for a vector of length 10")]
+    fn slice_into_past_end() {
+        let mut v: CatVec<usize, 4, 4> = (0..10).collect::<Vec<_>>().into();
+        v.slice_into(3..12);
+    }
+
+    #[test]
+    fn subvec() {
+        let mut v: CatVec<usize, 4, 4> = (0..10_000).collect::<Vec<_>>().into();
+        v.push_front(7);
+        let sub = v.subvec(1234..8765);
+        sub.check_invariants();
+        assert!(sub.iter().copied().eq(1233..8764));
+        let sharing = sub.shared_bytes_with(&v);
+        assert!(sharing.unique_elements <= 2 * 4 * sub.height());
+        assert!(sharing.shared_elements >= sub.len() - 2 * 4 * sub.height());
+        assert_eq!(v.len(), 10_001);
+        assert!(v.subvec(..).iter().eq(v.iter()));
+        assert!(v.subvec(500..500).is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "insertion index (is 11) should be <= len (is 10)")]
+    fn insert_past_end() {
+        let mut v: CatVec<usize, 4, 4> = (0..10).collect::<Vec<_>>().into();
+        v.insert(11, 0);
+    }
+
+    #[test]
+    fn fallible_api() {
+        let mut v: CatVec<usize, 4, 4> = (0..100).collect::<Vec<_>>().into();
+        let mut shadow: Vec<usize> = (0..100).collect();
+        assert_eq!(
+            v.try_insert(101, 7),
+            Err(CatVecError::OutOfBounds { idx: 101, len: 100 })
+        );
+        v.try_insert(100, 7).unwrap();
+        shadow.insert(100, 7);
+        assert_eq!(
+            v.try_remove(101),
+            Err(CatVecError::OutOfBounds { idx: 101, len: 101 })
+        );
+        for i in [0, 50, 98, 37] {
+            assert_eq!(v.remove(i), shadow.remove(i));
+            v.check_invariants();
+        }
+        assert_eq!(Vec::from(v.clone()), shadow);
+        let slice = v.try_slice(10..20).unwrap();
+        assert_eq!(Vec::from(slice), shadow[10..20]);
+        assert_eq!(
+            v.try_slice(10..v.len() + 1),
+            Err(CatVecError::InvalidRange {
+                start: 10,
+                end: 98,
+                len: 97
+            })
+        );
+        let (start, end) = (20, 10);
+        assert!(v.try_slice(start..end).is_err());
+        assert!(v.try_slice(..).unwrap().iter().eq(shadow.iter()));
+    }
+
+    #[test]
+    fn contains_and_position() {
+        let v: CatVec<usize, 5> = (0..100).collect::<Vec<_>>().into();
+        assert!(v.contains(&42));
+        assert!(!v.contains(&100));
+        assert_eq!(v.position(|x| *x == 73), Some(73));
+        assert_eq!(v.position(|x| *x > 1000), None);
+    }
+
+    #[test]
+    fn binary_search() {
+        let v: CatVec<usize, 5> = (0..200).map(|i| i * 2).collect::<Vec<_>>().into();
+        for i in 0..400 {
+            let expected = if i % 2 == 0 {
+                Ok(i / 2)
+            } else {
+                Err(i / 2 + 1)
+            };
+            assert_eq!(v.binary_search(&i), expected);
+        }
+        assert_eq!(v.binary_search(&1000), Err(200));
+        assert_eq!(v.binary_search_by_key(&20, |x| x * 10), Ok(1));
+    }
+
+    #[test]
+    fn insert_sorted() {
+        let mut v: CatVec<u8, 5> = CatVec::new();
+        let mut shadow = Vec::new();
+        for i in 0..200usize {
+            let x = (i * 37 % 101) as u8;
+            let idx = shadow.partition_point(|y| *y <= x);
+            assert_eq!(v.insert_sorted(x), idx);
+            shadow.insert(idx, x);
+        }
+        assert_eq!(Vec::from(v.clone()), shadow);
+        let below = shadow.iter().filter(|x| **x < 10).count();
+        assert_eq!(v.insert_sorted_by_key(7, |x| x / 10), below);
+
+        // equal keys go after existing ones, through splits at every level
+        let mut v: CatVec<(usize, usize), 4, 4> = CatVec::new();
+        let mut shadow = Vec::new();
+        for i in 0..2000 {
+            let x = (i * 7919 % 53, i);
+            let idx = shadow.partition_point(|y: &(usize, usize)| y.0 <= x.0);
+            assert_eq!(v.insert_sorted_by_key(x, |p| p.0), idx);
+            shadow.insert(idx, x);
+        }
+        v.check_invariants();
+        assert_eq!(Vec::from(v), shadow);
+    }
+
+    #[test]
+    fn merge_sorted() {
+        let evens: Vec<usize> = (0..300).map(|i| i * 2).collect();
+        let odds: Vec<usize> = (0..100).map(|i| i * 6 + 1).collect();
+        let high: Vec<usize> = (1000..1300).collect();
+        let a: CatVec<usize, 5> = evens.clone().into();
+        let b: CatVec<usize, 5> = odds.clone().into();
+        let c: CatVec<usize, 5> = high.clone().into();
+        for (x, y, sx, sy) in [(&a, &b, &evens, &odds), (&c, &a, &high, &evens)] {
+            let merged = x.merge_sorted(y);
+            merged.check_invariants();
+            let mut expected = sx.clone();
+            expected.extend_from_slice(sy);
+            expected.sort_unstable();
+            assert_eq!(Vec::from(merged), expected);
+        }
+    }
+
+    #[test]
+    fn chunks() {
+        let v: CatVec<usize, 5> = (0..103).collect::<Vec<_>>().into();
+        assert!(v.iter().copied().eq(0..103));
+        assert_eq!(v.iter().nth(57), Some(&57));
+        let mut chunks = v.chunks(10);
+        assert_eq!(chunks.remaining(), 11);
+        let mut collected: Vec<Vec<usize>> = Vec::new();
+        while let Some(chunk) = chunks.next_chunk() {
+            collected.push(chunk.iter().map(|x| **x).collect());
+        }
+        assert_eq!(collected.len(), 11);
+        assert_eq!(collected[3], (30..40).collect::<Vec<_>>());
+        assert_eq!(collected[10], vec![100, 101, 102]);
+        assert_eq!(chunks.remaining(), 0);
+        for size in [1, 4, 5, 7, 103] {
+            let mut windows = v.windows(size);
+            assert_eq!(windows.remaining(), 104 - size);
+            let mut start = 0;
+            while let Some(window) = windows.next_window() {
+                assert!(window.iter().map(|x| **x).eq(start..start + size));
+                start += 1;
+                assert_eq!(windows.remaining(), 104 - size - start);
+            }
+            assert_eq!(start, 104 - size);
+        }
+        assert!(v.windows(200).next_window().is_none());
+        let runs: Vec<usize> = v
+            .chunk_by(|a, b| a / 10 == b / 10)
+            .map(|c| c.count())
+            .collect();
+        assert_eq!(runs, [vec![10; 10], vec![3]].concat());
+    }
+
+    #[test]
+    fn runs() {
+        let shadow: Vec<u8> = (0..4000u32)
+            .map(|i| (i / 37 % 3 + i / 1000) as u8)
+            .collect();
+        let mut v: CatVec<u8, 4, 16> = shadow[3..].into();
+        for &b in shadow[..3].iter().rev() {
+            v.push_front(b);
+        }
+        let runs: Vec<(usize, u8)> = v.runs().map(|(n, &x)| (n, x)).collect();
+        let mut expected: Vec<(usize, u8)> = Vec::new();
+        for &x in &shadow {
+            match expected.last_mut() {
+                Some((n, y)) if *y == x => *n += 1,
+                _ => expected.push((1, x)),
+            }
+        }
+        assert_eq!(runs, expected);
+        assert_eq!(CatVec::<u8, 4>::new().runs().count(), 0);
+    }
+
+    #[test]
+    fn split() {
+        let shadow: Vec<u8> = (0..3000)
+            .map(|i| if i % 97 < 3 { b'\n' } else { i as u8 | 1 })
+            .collect();
+        let mut v: CatVec<u8, 4, 16> = shadow[1..].into();
+        v.push_front(b'\n');
+        let lines: Vec<CatVec<u8, 4, 16>> = v.split(|&b| b == b'\n').collect();
+        let expected: Vec<&[u8]> = shadow.split(|&b| b == b'\n').collect();
+        assert_eq!(lines.len(), expected.len());
+        for (line, expected) in lines.iter().zip(expected) {
+            line.check_invariants();
+            assert_eq!(Vec::from(line.clone()), expected);
+        }
+        assert_eq!(CatVec::<u8, 4>::new().split(|_| true).count(), 1);
+        let all: CatVec<u8, 4> = [0, 0][..].into();
+        assert_eq!(all.split(|_| true).filter(|p| p.is_empty()).count(), 3);
+    }
+
+    #[test]
+    fn iter_folds() {
+        let mut v: CatVec<usize, 4, 4> = (1..100).collect::<Vec<_>>().into();
+        v.push_front(0);
+        assert_eq!(v.iter().sum::<usize>(), (0..100).sum::<usize>());
+        assert_eq!(v.iter().count(), 100);
+        let mut it = v.iter();
+        it.nth(5);
+        assert_eq!(it.clone().fold(0, |n, _| n + 1), 94);
+        assert_eq!(it.find(|&&x| x % 7 == 6), Some(&6));
+        assert_eq!(it.len(), 93);
+        assert_eq!(it.position(|&x| x == 50), Some(43));
+        assert_eq!(it.next(), Some(&51));
+        assert!(it.clone().all(|&x| x > 51));
+        assert!(!it.any(|&x| x == 1000));
+        assert_eq!(it.len(), 0);
+        assert_eq!(it.next(), None);
+    }
+
+    #[test]
+    fn apply_in_place() {
+        let mut v: CatVec<usize, 4, 4> = (1..200).collect::<Vec<_>>().into();
+        v.push_front(0);
+        v.push_back(200);
+        let snapshot = v.clone();
+        let mut expected: Vec<usize> = (0..201).collect();
+        for (start, end) in [(0, 0), (0, 1), (3, 150), (150, 201), (0, 201), (77, 78)] {
+            v.apply_in_place(start..end, |x| *x = *x * 3 + 1);
+            expected[start..end]
+                .iter_mut()
+                .for_each(|x| *x = *x * 3 + 1);
+            v.check_invariants();
+            assert_eq!(Vec::from(v.clone()), expected);
+        }
+        assert!(snapshot.iter().copied().eq(0..201));
+        let mut v: CatVec<u8, 4, 4, ArcK, TextMetrics> = b"aaaa\n".repeat(20).as_slice().into();
+        v.apply_in_place(10..50, |b| {
+            if *b == b'\n' {
+                *b = b'a'
+            }
+        });
+        v.check_invariants();
+        assert_eq!(v.measure().line_breaks, 12);
+    }
+
+    #[test]
+    fn snapshots_across_threads() {
+        let mut v: CatVec<usize, 4, 4> = (0..1000).collect::<Vec<_>>().into();
+        // warm the lookup memo, so that the threads race on it
+        assert_eq!(v.get(500), Some(&500));
+        let snapshot = v.clone();
+        std::thread::scope(|s| {
+            for t in 0..4 {
+                let snapshot = &snapshot;
+                s.spawn(move || {
+                    for i in (t..1000).step_by(7) {
+                        assert_eq!(snapshot.get(i), Some(&i));
+                    }
+                    assert_eq!(snapshot.iter().sum::<usize>(), 499_500);
+                });
+            }
+            let owned = snapshot.clone();
+            s.spawn(move || {
+                let mut owned = owned;
+                owned.push_back(1000);
+                assert_eq!(owned.len(), 1001);
+            });
+            v.insert(0, 7);
+        });
+        assert_eq!(snapshot.len(), 1000);
+        assert_eq!(v.get(0), Some(&7));
+    }
+
+    #[test]
+    fn split_off() {
+        let shadow: Vec<usize> = (0..2000).collect();
+        for at in [0, 1, 3, 4, 5, 63, 64, 65, 1000, 1999, 2000] {
+            let mut v: CatVec<usize, 4, 4> = shadow.clone().into();
+            let right = v.split_off(at);
+            v.check_invariants();
+            right.check_invariants();
+            assert_eq!(Vec::from(v.clone()), shadow[..at]);
+            assert_eq!(Vec::from(right.clone()), shadow[at..]);
+            // the halves of a uniquely owned vector were moved apart, not shared
+            assert_eq!(v.shared_bytes_with(&right).shared_nodes, 0);
+
+            let mut v: CatVec<usize, 4, 4> = shadow.clone().into();
+            let snapshot = v.clone();
+            let right = v.split_off(at);
+            assert_eq!(Vec::from(v), shadow[..at]);
+            assert_eq!(Vec::from(right), shadow[at..]);
+            assert_eq!(Vec::from(snapshot), shadow);
+        }
+    }
+
+    #[test]
+    fn vec_deque() {
+        let mut deque: VecDeque<usize> = (100..1000).collect();
+        for i in (0..100).rev() {
+            deque.push_front(i);
+        }
+        // wrapped around its buffer, so that it is in two slices
+        assert!(!deque.as_slices().1.is_empty());
+        let v: CatVec<usize, 4, 4> = CatVec::from_vec_deque(deque.clone());
+        v.check_invariants();
+        assert!(v.iter().eq(deque.iter()));
+        let mut v = v;
+        v.push_front(7);
+        v.push_back(8);
+        deque.push_front(7);
+        deque.push_back(8);
+        assert_eq!(VecDeque::from(v), deque);
+    }
+
+    #[test]
+    fn extend_from_slice() {
+        let mut v: CatVec<String, 4, 4> = CatVec::new();
+        let mut shadow: Vec<String> = Vec::new();
+        for n in [0, 1, 3, 4, 5, 17, 64, 2] {
+            let piece: Vec<String> = (0..n).map(|i| format!("{}-{}", n, i)).collect();
+            v.extend_from_slice(&piece);
+            shadow.extend_from_slice(&piece);
+            v.check_invariants();
+        }
+        let snapshot = v.clone();
+        v.extend_from_slice(&shadow);
+        assert_eq!(Vec::from(snapshot), shadow);
+        shadow.extend_from_within(..);
+        assert_eq!(Vec::from(v), shadow);
+    }
+
+    #[test]
+    fn chunk_scans() {
+        let mut v: CatVec<usize, 4, 4> = (10..100).collect::<Vec<_>>().into();
+        v.push_front(9);
+        v.push_back(100);
+        let mut seen = Vec::new();
+        v.for_each_chunk(|leaf| {
+            assert!(!leaf.is_empty() && leaf.len() <= 4);
+            seen.extend_from_slice(leaf);
+        });
+        assert_eq!(seen, (9..101).collect::<Vec<_>>());
+        assert_eq!(
+            v.fold_chunks(0, |acc, leaf| acc + leaf.iter().sum::<usize>()),
+            (9..101).sum::<usize>()
+        );
+        assert_eq!(CatVec::<usize, 4>::new().fold_chunks(0, |n, _| n + 1), 0);
+    }
+
+    #[test]
+    fn push_back() {
+        let mut v: CatVec<usize, 4> = CatVec::new();
+        for i in 0..103 {
+            v.push_back(i);
+            assert_eq!(v.len(), i + 1);
+        }
+        v.check_invariants();
+        let w = v.clone();
+        assert!(v.ptr_eq(&w));
+        assert_eq!(
+            v.iter().copied().collect::<Vec<_>>(),
+            (0..103).collect::<Vec<_>>()
+        );
+        assert_eq!(v.get(102), Some(&102));
+        assert_eq!(v.cursor(101).next(), Some(&102));
+        assert_eq!(v.binary_search(&101), Ok(101));
+        assert_eq!(v.binary_search(&500), Err(103));
+        *v.get_mut(102).unwrap() = 1000;
+        assert_eq!(w.get(102), Some(&102));
+        v.slice_into(100..);
+        assert_eq!(Vec::from(v), vec![100, 101, 1000]);
+    }
+
+    #[test]
+    fn small_vectors() {
+        let mut v: CatVec<usize, 4, 4> = vec![1, 2, 3].into();
+        assert_eq!(v.height(), 0);
+        assert_eq!(v.tree_stats().node_count, 1);
+        for i in 0..20 {
+            v.insert(1, i);
+        }
+        v.check_invariants();
+        assert!(v.height() > 0);
+        v.slice_into(5..8);
+        v.check_invariants();
+        assert_eq!(v.height(), 0);
+        assert_eq!(Vec::from(v.clone()), vec![15, 14, 13]);
+        v.append(CatVec::new());
+        v.insert(0, 100);
+        assert_eq!(v.height(), 0);
+        assert_eq!(CatVec::<usize, 4, 4>::new().height(), 0);
+        assert_eq!(v.as_single_slice(), Some(&[100, 15, 14, 13][..]));
+        v.push_back(0);
+        assert_eq!(v.as_single_slice(), None);
+        let mut w: CatVec<usize, 4, 4> = CatVec::new();
+        assert_eq!(w.as_single_slice(), Some(&[][..]));
+        w.push_front(1);
+        assert_eq!(w.as_single_slice(), Some(&[1][..]));
+    }
+
+    #[test]
+    fn rc_pointers() {
+        let mut v: CatVecLocal<usize, 4, 4> = (0..100).collect::<Vec<_>>().into();
+        let w = v.clone();
+        v.insert(50, 1000);
+        v.push_front(2000);
+        v.check_invariants();
+        assert_eq!(v.len(), 102);
+        assert_eq!(v.get(51), Some(&1000));
+        assert_eq!(Vec::from(w.clone()), (0..100).collect::<Vec<_>>());
+        assert!(v.shared_bytes_with(&w).shared_nodes > 0);
+        let mut u = w.clone();
+        u.append(w);
+        assert_eq!(u.len(), 200);
+    }
+
+    #[test]
+    fn deque() {
+        let mut v: CatVec<usize, 4> = (10..20).collect::<Vec<_>>().into();
+        for i in (0..10).rev() {
+            v.push_front(i);
+        }
+        v.push_back(20);
+        v.check_invariants();
+        assert_eq!(Vec::from(v.clone()), (0..21).collect::<Vec<_>>());
+        assert_eq!(v.cursor(3).prev(), Some(&2));
+        for i in 0..21 {
+            assert_eq!(v.pop_front(), Some(i));
+            assert_eq!(v.len(), 20 - i);
+        }
+        assert_eq!(v.pop_front(), None);
+    }
+
+    #[test]
+    fn cursor() {
+        let v: CatVec<usize, 4> = (0..500).collect::<Vec<_>>().into();
+        let mut cursor = v.cursor(0);
+        for i in 0..500 {
+            assert_eq!(cursor.current(), Some(&i));
+            cursor.next();
+        }
+        assert_eq!(cursor.current(), None);
+        assert_eq!(cursor.prev(), Some(&499));
+        assert_eq!(cursor.seek(123), Some(&123));
+        assert_eq!(cursor.prev(), Some(&122));
+        assert_eq!(cursor.seek(1000), None);
+        assert_eq!(cursor.position(), 500);
+    }
+
+    #[test]
+    fn cursor_mut() {
+        let mut v: CatVec<usize, 4> = (0..100).collect::<Vec<_>>().into();
+        let mut shadow: Vec<usize> = (0..100).collect();
+        {
+            let mut cursor = v.cursor_mut(10);
+            cursor.insert_before(1000);
+            cursor.insert_after(1001);
+            assert_eq!(cursor.remove_current(), Some(10));
+            assert_eq!(cursor.replace(1002), Ok(1001));
+            cursor.seek(80);
+            assert_eq!(cursor.remove_current(), Some(79));
+            cursor.seek(cursor.len());
+            cursor.insert_after(1003);
+            assert_eq!(cursor.current(), Some(&1003));
+        }
+        shadow.splice(10..11, [1000, 1002]);
+        shadow.remove(80);
+        shadow.push(1003);
+        v.check_invariants();
+        assert_eq!(Vec::from(v), shadow);
+
+        // Removing the last element of a region leaves the cursor at its end, on an element that isn't loaded.
+        let mut v: CatVec<usize, 4, 2> = (0..6).collect::<Vec<_>>().into();
+        {
+            let mut cursor = v.cursor_mut(1);
+            assert_eq!(cursor.remove_current(), Some(1));
+            cursor.insert_after(99);
+            assert_eq!(cursor.current(), Some(&2));
+        }
+        assert_eq!(Vec::from(v.clone()), [0, 2, 99, 3, 4, 5]);
+        let mut v: CatVec<usize, 4> = (0..6).collect::<Vec<_>>().into();
+        {
+            let mut cursor = v.cursor_mut(1);
+            assert_eq!(cursor.remove_current(), Some(1));
+            cursor.insert_after(99);
+        }
+        assert_eq!(Vec::from(v), [0, 2, 99, 3, 4, 5]);
+    }
+
+    #[test]
+    fn ptr_eq() {
+        let v: CatVec<usize, 4, 4> = (0..100).collect::<Vec<_>>().into();
+        let mut w = v.clone();
+        assert!(v.ptr_eq(&w));
+        *w.get_mut(50).unwrap() = 1000;
+        assert!(!v.ptr_eq(&w));
+        let u: CatVec<usize, 4, 4> = (0..100).collect::<Vec<_>>().into();
+        assert!(!v.ptr_eq(&u));
+    }
+
+    #[test]
+    fn compact() {
+        let mut v: CatVec<usize, 4, 4> = CatVec::new();
+        for i in 0..300 {
+            v.insert(v.len() / 2, i);
+        }
+        let shadow = Vec::from(v.clone());
+        v.compact();
+        v.check_invariants();
+        assert_eq!(Vec::from(v.clone()), shadow);
+        assert_eq!(v.height(), 4);
+        assert_eq!(v.tree_stats().leaf_count, 75);
+    }
+
+    #[test]
+    fn leaf_capacity() {
+        let mut v: CatVec<u8, 4, 16> = CatVec::new();
+        for i in 0..=255 {
+            v.insert(v.len() / 2, i);
+        }
+        v.check_invariants();
+        let shadow = Vec::from(v.clone());
+        v.compact();
+        v.check_invariants();
+        assert_eq!(Vec::from(v.clone()), shadow);
+        assert_eq!(v.tree_stats().leaf_count, 16);
+        assert_eq!(v.height(), 2);
+        v.visit_nodes(|node| assert_eq!(node.capacity, if node.is_leaf { 16 } else { 4 }));
+    }
+
+    #[test]
+    fn sort() {
+        let shadow: Vec<(u8, usize)> = (0..500).map(|i| ((i * 7919 % 13) as u8, i)).collect();
+        let mut v: CatVec<(u8, usize), 5> = shadow.clone().into();
+        v.sort_by(|a, b| a.0.cmp(&b.0));
+        v.check_invariants();
+        let mut expected = shadow;
+        expected.sort_by_key(|a| a.0);
+        assert_eq!(Vec::from(v), expected);
+    }
+
+    #[test]
+    fn get2_mut() {
+        let mut shadow: Vec<usize> = (0..1000).collect();
+        let mut v: CatVec<usize, 4, 8> = shadow.clone().into();
+        for i in 0..5 {
+            v.push_front(i);
+            shadow.insert(0, i);
+            v.push_back(i);
+            shadow.push(i);
+        }
+        let snapshot = v.clone();
+        let original = shadow.clone();
+        let len = shadow.len();
+        for k in 0..2000 {
+            let (i, j) = (k * 7919 % len, k * 104_729 % len);
+            if i == j {
+                assert!(v.get2_mut(i, j).is_none());
+                continue;
+            }
+            let (x, y) = v.get2_mut(i, j).unwrap();
+            core::mem::swap(x, y);
+            shadow.swap(i, j);
+        }
+        assert!(v.get2_mut(0, len).is_none());
+        v.check_invariants();
+        assert_eq!(Vec::from(v), shadow);
+        assert_eq!(Vec::from(snapshot), original);
+    }
+
+    #[test]
+    fn comparison() {
+        let shadow: Vec<u8> = (0..3000).map(|i| (i * 31 % 251) as u8).collect();
+        let v: CatVec<u8, 4, 16> = shadow.as_slice().into();
+        // The same elements, laid out in differently aligned leaves.
+        let mut w: CatVec<u8, 4, 16> = shadow[7..].into();
+        for &b in shadow[..7].iter().rev() {
+            w.push_front(b);
+        }
+        assert!(v == w);
+        assert_eq!(v.cmp(&w), Ordering::Equal);
+        for i in [0, 6, 7, 100, 2999] {
+            let mut w = w.clone();
+            *w.get_mut(i).unwrap() ^= 1;
+            let mut expected = shadow.clone();
+            expected[i] ^= 1;
+            assert!(v != w);
+            assert_eq!(v.cmp(&w), shadow.cmp(&expected));
+            assert_eq!(w.partial_cmp(&v), expected.partial_cmp(&shadow));
+        }
+        let prefix: CatVec<u8, 4, 16> = shadow[..2000].into();
+        assert!(prefix < v);
+        assert_eq!(CatVec::<u8, 4, 16>::new().cmp(&v), Ordering::Less);
+        let floats: CatVec<f64, 4> = [1.0, f64::NAN].into();
+        assert_eq!(floats.partial_cmp(&floats), None);
+    }
+}
@@ -0,0 +1,268 @@
+use core::ops::RangeBounds;
+
+use archery::SharedPointerKind;
+
+use crate::{range_bounds, CatVec};
+
+/// A summary of runs of elements that internal nodes cache for each of their children, like the measure of a finger tree. The summary of a whole subtree is then available without visiting its elements, which makes queries like "how many line breaks come before this position" take O(log n) time.
+///
+/// Vectors are measured by `()` by default, which summarizes nothing and costs nothing. Any other monoid can be plugged in by implementing this trait on a marker type and naming it as the last type parameter of [CatVec]: element counts with a filter, byte sizes, weights for weighted sampling, and so on. Summaries are kept up to date through every push, concatenation, split and rebuild, at the cost of one `combine` per child whenever a node changes.
+pub trait Measure<T> {
+    /// The summary of a run of elements. Summaries are compared by [crate::CatVec::check_invariants], and are shared between threads along with the nodes that cache them.
+    type Summary: Clone + PartialEq + core::fmt::Debug + Send + Sync;
+
+    /// The summary of no elements, which leaves any other summary unchanged when combined with it.
+    fn identity() -> Self::Summary;
+
+    /// The summary of a single element.
+    fn measure(x: &T) -> Self::Summary;
+
+    /// The summary of two adjacent runs, from the summaries of each. Must be associative, since the tree groups runs however its shape happens to be; it need not be commutative.
+    fn combine(left: &Self::Summary, right: &Self::Summary) -> Self::Summary;
+}
+
+impl<T> Measure<T> for () {
+    type Summary = ();
+
+    fn identity() {}
+
+    fn measure(_: &T) {}
+
+    fn combine(_: &(), _: &()) {}
+}
+
+/// Counts the characters and line breaks in a run of UTF-8 bytes. This is the measure behind the indexes of [crate::CatString].
+pub struct TextMetrics;
+
+/// The summary of a run of UTF-8 bytes under [TextMetrics].
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct TextSummary {
+    /// The number of characters that start in the run.
+    pub chars: usize,
+    /// The number of `\n` bytes in the run.
+    pub line_breaks: usize,
+}
+
+impl Measure<u8> for TextMetrics {
+    type Summary = TextSummary;
+
+    fn identity() -> TextSummary {
+        TextSummary::default()
+    }
+
+    fn measure(x: &u8) -> TextSummary {
+        TextSummary {
+            // every byte except a continuation byte, which looks like 0b10xxxxxx, starts a character
+            chars: ((*x as i8) >= -0x40) as usize,
+            line_breaks: (*x == b'\n') as usize,
+        }
+    }
+
+    fn combine(left: &TextSummary, right: &TextSummary) -> TextSummary {
+        TextSummary {
+            chars: left.chars + right.chars,
+            line_breaks: left.line_breaks + right.line_breaks,
+        }
+    }
+}
+
+/// Summarizes a run of elements that aren't in the tree, such as a leaf or a head or tail buffer.
+pub(crate) fn measure_slice<T, M: Measure<T>>(xs: &[T]) -> M::Summary {
+    xs.iter()
+        .fold(M::identity(), |acc, x| M::combine(&acc, &M::measure(x)))
+}
+
+impl<T: Clone, const ORD: usize, const LEAF: usize, P: SharedPointerKind, M: Measure<T>>
+    CatVec<T, ORD, LEAF, P, M>
+{
+    /// The measure of all the elements. Only the head and tail buffers are visited element by element; the tree's share comes from the summaries cached in its root.
+    pub fn measure(&self) -> M::Summary {
+        M::combine(
+            &M::combine(&measure_slice::<T, M>(&self.head), &self.inner.measure()),
+            &measure_slice::<T, M>(&self.tail),
+        )
+    }
+
+    /// The measure of the first `idx` elements, in O(log n) time. Panics if `idx` is out of bounds.
+    pub(crate) fn measure_prefix(&self, idx: usize) -> M::Summary {
+        assert!(
+            idx <= self.len(),
+            "index {} out of bounds for a vector of length {}",
+            idx,
+            self.len()
+        );
+        let head = self.head.len();
+        if idx <= head {
+            return measure_slice::<T, M>(&self.head[..idx]);
+        }
+        let tree_len = self.inner.len();
+        let acc = measure_slice::<T, M>(&self.head);
+        let acc = M::combine(&acc, &self.inner.measure_prefix((idx - head).min(tree_len)));
+        if idx <= head + tree_len {
+            return acc;
+        }
+        M::combine(
+            &acc,
+            &measure_slice::<T, M>(&self.tail[..idx - head - tree_len]),
+        )
+    }
+
+    /// The measure of a range of elements, in O(log n) time. Summaries needn't have inverses, so rather than taking the difference of two prefixes, this combines the cached measures of the subtrees that cover the range. Panics if the range is out of bounds.
+    pub fn measure_range(&self, range: impl RangeBounds<usize>) -> M::Summary {
+        let (start, end) = range_bounds(range, self.len());
+        assert!(
+            start <= end && end <= self.len(),
+            "range {}..{} out of bounds for a vector of length {}",
+            start,
+            end,
+            self.len()
+        );
+        let head = self.head.len();
+        let tree_len = self.inner.len();
+        // clamps the range to the part of the vector in `lo..hi`, relative to `lo`
+        let clamp = |lo: usize, hi: usize| (start.clamp(lo, hi) - lo, end.clamp(lo, hi) - lo);
+        let (hs, he) = clamp(0, head);
+        let (ts, te) = clamp(head, head + tree_len);
+        let (ls, le) = clamp(head + tree_len, self.len());
+        let tree = if ts < te {
+            self.inner.measure_range(ts, te)
+        } else {
+            M::identity()
+        };
+        M::combine(
+            &M::combine(&measure_slice::<T, M>(&self.head[hs..he]), &tree),
+            &measure_slice::<T, M>(&self.tail[ls..le]),
+        )
+    }
+
+    /// Finds the first position at which the measure of the elements up to and including it satisfies the predicate, in O(log n) time. The predicate must be monotone: once it holds for some prefix, it must hold for every longer one. That lets the search skip every subtree whose cached measure doesn't get there, descending the tree once.
+    ///
+    /// For example, with line breaks counted, the position of the 1000th line break is the first position where the count reaches 1000; with weights summed, picking a uniformly random point below the total and finding where the running sum exceeds it samples an element in proportion to its weight. Returns None if no prefix satisfies the predicate.
+    pub fn find_by_measure(&self, mut pred: impl FnMut(&M::Summary) -> bool) -> Option<usize> {
+        let head = self.head.len();
+        let acc = match find_in_slice::<T, M>(&self.head, M::identity(), &mut pred) {
+            Ok(pos) => return Some(pos),
+            Err(acc) => acc,
+        };
+        let acc = match self.inner.find_by_measure(acc, &mut pred) {
+            Ok(pos) => return Some(head + pos),
+            Err(acc) => acc,
+        };
+        find_in_slice::<T, M>(&self.tail, acc, &mut pred)
+            .ok()
+            .map(|pos| head + self.inner.len() + pos)
+    }
+}
+
+/// Scans a run of elements that aren't in the tree for the first position where the measure up to and including it, combined onto `acc`, satisfies the predicate. If there is none, returns the measure of the whole run combined onto `acc`.
+pub(crate) fn find_in_slice<T, M: Measure<T>>(
+    xs: &[T],
+    mut acc: M::Summary,
+    pred: &mut impl FnMut(&M::Summary) -> bool,
+) -> Result<usize, M::Summary> {
+    for (i, x) in xs.iter().enumerate() {
+        acc = M::combine(&acc, &M::measure(x));
+        if pred(&acc) {
+            return Ok(i);
+        }
+    }
+    Err(acc)
+}
+
+#[cfg(test)]
+mod tests {
+    use archery::ArcK;
+
+    use super::Measure;
+    use crate::CatVec;
+
+    /// Keeps the first and last elements of a run, which only combines associatively in order.
+    struct Ends;
+
+    impl Measure<usize> for Ends {
+        type Summary = Option<(usize, usize)>;
+
+        fn identity() -> Self::Summary {
+            None
+        }
+
+        fn measure(x: &usize) -> Self::Summary {
+            Some((*x, *x))
+        }
+
+        fn combine(left: &Self::Summary, right: &Self::Summary) -> Self::Summary {
+            match (left, right) {
+                (Some((first, _)), Some((_, last))) => Some((*first, *last)),
+                (Some(_), None) => *left,
+                (None, _) => *right,
+            }
+        }
+    }
+
+    /// Sums weights.
+    struct Weight;
+
+    impl Measure<u64> for Weight {
+        type Summary = u64;
+
+        fn identity() -> u64 {
+            0
+        }
+
+        fn measure(x: &u64) -> u64 {
+            *x
+        }
+
+        fn combine(left: &u64, right: &u64) -> u64 {
+            left + right
+        }
+    }
+
+    #[test]
+    fn weighted_search() {
+        let weights: Vec<u64> = (0..1000).map(|i| i % 7).collect();
+        let mut v: CatVec<u64, 4, 4, ArcK, Weight> = weights.clone().into();
+        v.push_front(3);
+        let mut shadow = weights;
+        shadow.insert(0, 3);
+        let total = v.measure();
+        assert_eq!(total, shadow.iter().sum::<u64>());
+        for target in (0..total).step_by(11) {
+            let expected = shadow
+                .iter()
+                .scan(0, |sum, w| {
+                    *sum += w;
+                    Some(*sum)
+                })
+                .position(|sum| sum > target);
+            assert_eq!(v.find_by_measure(|&sum| sum > target), expected);
+        }
+        assert_eq!(v.find_by_measure(|&sum| sum > total), None);
+    }
+
+    #[test]
+    fn custom_measure() {
+        let mut v: CatVec<usize, 4, 4, ArcK, Ends> = (0..500).collect::<Vec<_>>().into();
+        assert_eq!(v.measure(), Some((0, 499)));
+        v.push_front(1000);
+        v.push_back(2000);
+        let mut w = v.clone();
+        w.slice_into(100..300);
+        v.append(w);
+        v.check_invariants();
+        assert_eq!(v.measure(), Some((1000, 298)));
+        for i in [0, 1, 250, 502, 600, v.len()] {
+            let expected = (i > 0).then(|| (*v.get(0).unwrap(), *v.get(i - 1).unwrap()));
+            assert_eq!(v.measure_prefix(i), expected);
+        }
+        assert_eq!(CatVec::<usize, 4, 4, ArcK, Ends>::new().measure(), None);
+        for start in (0..=v.len()).step_by(37) {
+            for end in (start..=v.len()).step_by(23) {
+                let expected =
+                    (start < end).then(|| (*v.get(start).unwrap(), *v.get(end - 1).unwrap()));
+                assert_eq!(v.measure_range(start..end), expected);
+            }
+        }
+        assert_eq!(v.measure_range(..), v.measure());
+    }
+}
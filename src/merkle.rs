@@ -0,0 +1,677 @@
+use alloc::{boxed::Box, vec, vec::Vec};
+use core::{
+    hash::{Hash, Hasher},
+    ops::RangeBounds,
+};
+
+use archery::SharedPointerKind;
+
+use crate::{range_bounds, CatVec, Measure, Tree};
+
+/// Feeds whatever a [Hash] implementation writes into a blake3 hasher. Integers are written little-endian and `usize`s as 64 bits, so hashes come out the same on every platform.
+struct Blake3Writer<'a>(&'a mut blake3::Hasher);
+
+impl Hasher for Blake3Writer<'_> {
+    fn finish(&self) -> u64 {
+        unreachable!("only used for its input")
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        self.0.update(bytes);
+    }
+
+    fn write_u16(&mut self, i: u16) {
+        self.write(&i.to_le_bytes())
+    }
+
+    fn write_u32(&mut self, i: u32) {
+        self.write(&i.to_le_bytes())
+    }
+
+    fn write_u64(&mut self, i: u64) {
+        self.write(&i.to_le_bytes())
+    }
+
+    fn write_u128(&mut self, i: u128) {
+        self.write(&i.to_le_bytes())
+    }
+
+    fn write_usize(&mut self, i: usize) {
+        self.write_u64(i as u64)
+    }
+
+    fn write_i16(&mut self, i: i16) {
+        self.write_u16(i as u16)
+    }
+
+    fn write_i32(&mut self, i: i32) {
+        self.write_u32(i as u32)
+    }
+
+    fn write_i64(&mut self, i: i64) {
+        self.write_u64(i as u64)
+    }
+
+    fn write_i128(&mut self, i: i128) {
+        self.write_u128(i as u128)
+    }
+
+    fn write_isize(&mut self, i: isize) {
+        self.write_u64(i as u64)
+    }
+}
+
+/// The hash of a single element: its [Hash] output, behind a domain separator.
+pub(crate) fn element_hash<T: Hash>(x: &T) -> [u8; 32] {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(&[0]);
+    x.hash(&mut Blake3Writer(&mut hasher));
+    *hasher.finalize().as_bytes()
+}
+
+/// The hash of a node, from the length and hash of each of its children. Committing to the lengths lets a proof pin down where an element sits, not just that it is somewhere in the tree.
+pub(crate) fn node_hash(children: impl IntoIterator<Item = (usize, [u8; 32])>) -> [u8; 32] {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(&[1]);
+    for (len, hash) in children {
+        hasher.update(&(len as u64).to_le_bytes());
+        hasher.update(&hash);
+    }
+    *hasher.finalize().as_bytes()
+}
+
+/// The hash of a leaf, which is a node whose children are single elements.
+pub(crate) fn leaf_hash<T: Hash>(items: &[T]) -> [u8; 32] {
+    node_hash(items.iter().map(|x| (1, element_hash(x))))
+}
+
+/// The hash of a chunk of the canonical chunking, from the runs it is made of.
+fn chunk_hash(runs: &[Run]) -> [u8; 32] {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(&[2]);
+    for run in runs {
+        hasher.update(&(run.count as u64).to_le_bytes());
+        hasher.update(&(run.len as u64).to_le_bytes());
+        hasher.update(&run.hash);
+    }
+    *hasher.finalize().as_bytes()
+}
+
+/// A chunk as a single item of the level above it.
+fn chunk_item(runs: &[Run]) -> Run {
+    Run {
+        count: 1,
+        len: runs.iter().map(|r| r.count * r.len).sum(),
+        hash: chunk_hash(runs),
+    }
+}
+
+/// Adds a run to the end of a list, merging it into the last run if they repeat the same item.
+fn push_run(runs: &mut Vec<Run>, run: Run) {
+    match runs.last_mut() {
+        Some(last) if last.hash == run.hash && last.len == run.len => last.count += run.count,
+        _ => runs.push(run),
+    }
+}
+
+/// `count` copies of one item of a level of the canonical chunking: an element at the bottom level, and a chunk of the level below above it, covering `len` elements.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub(crate) struct Run {
+    count: usize,
+    len: usize,
+    hash: [u8; 32],
+}
+
+/// The canonical chunking of a stretch of a vector, as far as it is decided by the stretch alone.
+///
+/// At every level, equal neighbouring items are collapsed into runs, and a chunk ends after each run whose hash is smaller than the hashes of the runs on both sides. Whether the first and last runs of a stretch end a chunk depends on what comes before and after it, so they are left open until the stretch is appended to its neighbours.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub(crate) enum Chunking {
+    /// No chunk is known to end inside the stretch.
+    Open(Vec<Run>),
+    /// Some chunks end inside the stretch. `lead` holds the runs up to the end of the first, `trail` the runs after the end of the last, which is a run hashing to `last`, and `mid` the chunking one level up of the whole chunks in between.
+    Closed {
+        lead: Vec<Run>,
+        mid: Box<Chunking>,
+        last: [u8; 32],
+        trail: Vec<Run>,
+    },
+}
+
+impl Default for Chunking {
+    fn default() -> Self {
+        Chunking::Open(Vec::new())
+    }
+}
+
+impl Chunking {
+    /// The chunking of a stretch of elements.
+    pub(crate) fn elements<T: Hash>(items: &[T]) -> Self {
+        let mut runs = Vec::new();
+        for x in items {
+            push_run(
+                &mut runs,
+                Run {
+                    count: 1,
+                    len: 1,
+                    hash: element_hash(x),
+                },
+            );
+        }
+        Self::from_runs(runs)
+    }
+
+    /// The chunking of a list of runs, in which no two neighbours repeat the same item.
+    fn from_runs(runs: Vec<Run>) -> Self {
+        let ends: Vec<usize> = (1..runs.len().saturating_sub(1))
+            .filter(|&i| runs[i].hash < runs[i - 1].hash && runs[i].hash < runs[i + 1].hash)
+            .collect();
+        let (first, last) = match (ends.first(), ends.last()) {
+            (Some(&first), Some(&last)) => (first, last),
+            _ => return Chunking::Open(runs),
+        };
+        let mut items = Vec::new();
+        for pair in ends.windows(2) {
+            push_run(&mut items, chunk_item(&runs[pair[0] + 1..=pair[1]]));
+        }
+        Chunking::Closed {
+            lead: runs[..=first].to_vec(),
+            mid: Box::new(Self::from_runs(items)),
+            last: runs[last].hash,
+            trail: runs[last + 1..].to_vec(),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        matches!(self, Chunking::Open(runs) if runs.is_empty())
+    }
+
+    /// The chunking of this stretch followed by another. Only the runs on either side of the seam can end chunks that weren't known to end before, so this takes time in the size of the two chunkings, not of the stretches.
+    pub(crate) fn append(self, other: &Chunking) -> Self {
+        if self.is_empty() {
+            return other.clone();
+        }
+        if other.is_empty() {
+            return self;
+        }
+        let (left, mut runs) = match self {
+            Chunking::Open(runs) => (None, runs),
+            Chunking::Closed {
+                lead,
+                mid,
+                last,
+                trail,
+            } => (Some((lead, *mid, last)), trail),
+        };
+        let (other_runs, right) = match other {
+            Chunking::Open(runs) => (runs, None),
+            Chunking::Closed {
+                lead,
+                mid,
+                last,
+                trail,
+            } => (lead, Some((mid, last, trail))),
+        };
+        // the last run of this stretch and the first of the other, which are one run if they repeat the same item
+        let seam = runs.len() - 1;
+        for &run in other_runs {
+            push_run(&mut runs, run);
+        }
+        let merged = runs.len() < seam + 1 + other_runs.len();
+        let seam = [seam, if merged { seam } else { seam + 1 }];
+        let before = left.as_ref().map(|(_, _, last)| last);
+        let mut ends: Vec<usize> = IntoIterator::into_iter(seam)
+            .filter(|&i| {
+                let prev = if i > 0 {
+                    Some(&runs[i - 1].hash)
+                } else {
+                    before
+                };
+                match (prev, runs.get(i + 1)) {
+                    (Some(prev), Some(next)) => runs[i].hash < *prev && runs[i].hash < next.hash,
+                    _ => false,
+                }
+            })
+            .collect();
+        if right.is_some() {
+            ends.push(runs.len() - 1);
+        }
+        ends.dedup();
+        if ends.is_empty() {
+            return match left {
+                Some((lead, mid, last)) => Chunking::Closed {
+                    lead,
+                    mid: Box::new(mid),
+                    last,
+                    trail: runs,
+                },
+                None => Chunking::Open(runs),
+            };
+        }
+        // after a known end, the first chunk of the joined runs is whole
+        let (lead, mid, mut start) = match left {
+            Some((lead, mid, _)) => (lead, mid, 0),
+            None => (runs[..=ends[0]].to_vec(), Chunking::default(), ends[0] + 1),
+        };
+        let mut items = Vec::new();
+        let first = start;
+        for &end in ends.iter().filter(|&&end| end >= first) {
+            push_run(&mut items, chunk_item(&runs[start..=end]));
+            start = end + 1;
+        }
+        let mut mid = mid.append(&Self::from_runs(items));
+        let (last, trail) = match right {
+            Some((other_mid, last, trail)) => {
+                mid = mid.append(other_mid);
+                (*last, trail.clone())
+            }
+            None => (runs[ends[ends.len() - 1]].hash, runs[start..].to_vec()),
+        };
+        Chunking::Closed {
+            lead,
+            mid: Box::new(mid),
+            last,
+            trail,
+        }
+    }
+
+    /// The root hash of a whole vector with this chunking. The runs at either end of the vector don't end chunks, and chunking stops once a level above the elements is down to a single item.
+    fn finish(self) -> [u8; 32] {
+        let mut level = self;
+        let mut bottom = true;
+        loop {
+            match level {
+                Chunking::Open(runs) => {
+                    return match runs.as_slice() {
+                        [run] if !bottom && run.count == 1 => run.hash,
+                        _ => chunk_hash(&runs),
+                    }
+                }
+                Chunking::Closed {
+                    lead, mid, trail, ..
+                } => {
+                    level = Self::from_runs(vec![chunk_item(&lead)])
+                        .append(&mid)
+                        .append(&Self::from_runs(vec![chunk_item(&trail)]));
+                    bottom = false;
+                }
+            }
+        }
+    }
+
+    /// The number of elements the chunking covers, or None if it is malformed: if it has an empty run, an empty lead or trail, or more elements than fit in a `usize`.
+    fn len(&self) -> Option<usize> {
+        fn runs_len(runs: &[Run]) -> Option<usize> {
+            runs.iter().try_fold(0usize, |total, run| {
+                if run.count == 0 || run.len == 0 {
+                    return None;
+                }
+                total.checked_add(run.count.checked_mul(run.len)?)
+            })
+        }
+        match self {
+            Chunking::Open(runs) => runs_len(runs),
+            Chunking::Closed {
+                lead, mid, trail, ..
+            } => {
+                if lead.is_empty() || trail.is_empty() {
+                    return None;
+                }
+                runs_len(lead)?
+                    .checked_add(mid.len()?)?
+                    .checked_add(runs_len(trail)?)
+            }
+        }
+    }
+}
+
+impl<T: Clone + Hash, const ORD: usize, const LEAF: usize, P: SharedPointerKind, M: Measure<T>>
+    CatVec<T, ORD, LEAF, P, M>
+{
+    /// A blake3 hash committing to the contents of the vector alone, so vectors with equal elements have equal root hashes however they were built, pushed, sliced or concatenated.
+    ///
+    /// The hash doesn't follow the tree. Instead, the elements are cut into chunks at boundaries set by the elements themselves, and the chunks are hashed and cut again, level by level, until one hash is left, in the manner of a prolly tree. Equal neighbours are collapsed into runs first, and a chunk ends after each run that hashes lower than both of its neighbours, so a boundary depends only on the runs next to it and an edit only moves the boundaries around it. Internal nodes cache the part of the chunking their elements decide, so after an edit only the nodes along the edited path are chunked again, which for typical contents takes O(log² n) time.
+    ///
+    /// Elements whose hashes only ever rise along the vector have no boundaries between them, and so make a single chunk that is rehashed in full after every edit. That takes contents chosen for the purpose; constant runs, small alphabets and repeating patterns all chunk normally.
+    pub fn root_hash(&self) -> [u8; 32] {
+        Chunking::elements(&self.head)
+            .append(&self.inner.chunking())
+            .append(&Chunking::elements(&self.tail))
+            .finish()
+    }
+
+    /// A blake3 hash of the vector as it is stored: the hash of a node whose children are the head buffer, the tree and the tail buffer, with each internal node hashed from the lengths and hashes of its children. This is the hash [CatVec::persist] returns and [CatVec::load] takes. Internal nodes cache their hashes, so after an edit only the nodes along the edited path are rehashed.
+    ///
+    /// Unlike [CatVec::root_hash], this depends on the shape of the tree, so two vectors with the same contents but different histories usually have different storage hashes. Persisting the [CatVec::canonicalize]d vector gives one that depends on the contents alone.
+    pub fn storage_hash(&self) -> [u8; 32] {
+        node_hash([
+            (self.head.len(), leaf_hash(&self.head)),
+            (self.inner.len(), self.inner.hash()),
+            (self.tail.len(), leaf_hash(&self.tail)),
+        ])
+    }
+
+    /// Proves that the element at the given position is part of the vector committed to by [CatVec::root_hash], or returns None if the position is out of bounds. The proof holds the chunkings of the elements before and after the proven one, which take O(log n) space for typical contents.
+    pub fn prove(&self, idx: usize) -> Option<Proof> {
+        if idx >= self.len() {
+            return None;
+        }
+        self.prove_range(idx..idx + 1)
+    }
+
+    /// Proves that a contiguous range of elements is part of the vector committed to by [CatVec::root_hash], or returns None if the range is out of bounds. The proof holds the chunkings of the elements before and after the range, and the verifier supplies the elements of the range, so for typical contents it takes O(log n) space however long the range is.
+    pub fn prove_range(&self, range: impl RangeBounds<usize>) -> Option<Proof> {
+        let (start, end) = range_bounds(range, self.len());
+        if start > end || end > self.len() {
+            return None;
+        }
+        Some(Proof {
+            before: self.chunking_of(0, start),
+            after: self.chunking_of(end, self.len()),
+        })
+    }
+
+    /// The chunking of the elements in `start..end`, made from the cached chunkings of the nodes inside the range and the elements of the nodes overlapping its ends.
+    fn chunking_of(&self, start: usize, end: usize) -> Chunking {
+        let head = self.head.len();
+        let tree_end = head + self.inner.len();
+        let mut chunking = Chunking::elements(&self.head[start.min(head)..end.min(head)]);
+        chunking = tree_chunking(
+            &self.inner,
+            start.clamp(head, tree_end) - head,
+            end.clamp(head, tree_end) - head,
+            chunking,
+        );
+        let tail = start.max(tree_end) - tree_end..end.max(tree_end) - tree_end;
+        chunking.append(&Chunking::elements(&self.tail[tail]))
+    }
+}
+
+/// Appends the chunking of the elements of a tree in `start..end` to `chunking`.
+fn tree_chunking<
+    T: Clone + Hash,
+    const ORD: usize,
+    const LEAF: usize,
+    P: SharedPointerKind,
+    M: Measure<T>,
+>(
+    tree: &Tree<T, ORD, LEAF, P, M>,
+    start: usize,
+    end: usize,
+    chunking: Chunking,
+) -> Chunking {
+    if start >= end {
+        return chunking;
+    }
+    if start == 0 && end == tree.len() {
+        return chunking.append(&tree.chunking());
+    }
+    let children = match tree {
+        Tree::Array(items) => return chunking.append(&Chunking::elements(&items[start..end])),
+        Tree::Internal(_) => tree.children().unwrap(),
+    };
+    let mut offset = 0;
+    children.iter().fold(chunking, |chunking, child| {
+        let child_start = offset;
+        offset += child.len();
+        tree_chunking(
+            child,
+            start.clamp(child_start, offset) - child_start,
+            end.clamp(child_start, offset) - child_start,
+            chunking,
+        )
+    })
+}
+
+/// A Merkle proof that elements sit at given positions of a vector with a given [CatVec::root_hash], made by [CatVec::prove] or [CatVec::prove_range]. Checking it takes only the root hash and the elements themselves, not the rest of the vector.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Proof {
+    /// The chunking of the elements before the proven ones.
+    before: Chunking,
+    /// The chunking of the elements after the proven ones.
+    after: Chunking,
+}
+
+impl Proof {
+    /// Checks that `value` is the element at position `idx` of the vector whose root hash is `root_hash`. The value is hashed through its [Hash] implementation, so it must have the same type as the vector's elements: `&2` would be taken as an `i32`.
+    pub fn verify<T: Hash>(&self, root_hash: &[u8; 32], idx: usize, value: &T) -> bool {
+        self.verify_range(root_hash, idx, core::slice::from_ref(value))
+    }
+
+    /// Checks that `values` are the elements starting at position `start` of the vector whose root hash is `root_hash`, as proven by [CatVec::prove_range]. As with [Proof::verify], the values must have the same type as the vector's elements.
+    pub fn verify_range<T: Hash>(&self, root_hash: &[u8; 32], start: usize, values: &[T]) -> bool {
+        let fits = match (self.before.len(), self.after.len()) {
+            (Some(before), Some(after)) => {
+                before == start
+                    && before
+                        .checked_add(values.len())
+                        .and_then(|len| len.checked_add(after))
+                        .is_some()
+            }
+            _ => false,
+        };
+        fits && self
+            .before
+            .clone()
+            .append(&Chunking::elements(values))
+            .append(&self.after)
+            .finish()
+            == *root_hash
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use archery::ArcK;
+
+    use super::{chunk_item, element_hash, leaf_hash, node_hash, push_run, Run};
+    use crate::{CatVec, Tree};
+
+    /// Hashes a tree from scratch, ignoring the cached hashes.
+    fn fresh_hash(tree: &Tree<u64, 4, 4, ArcK, ()>) -> [u8; 32] {
+        match tree {
+            Tree::Array(items) => leaf_hash(items),
+            Tree::Internal(_) => node_hash(
+                tree.children()
+                    .unwrap()
+                    .iter()
+                    .map(|c| (c.len(), fresh_hash(c))),
+            ),
+        }
+    }
+
+    fn fresh_storage_hash(v: &CatVec<u64, 4, 4>) -> [u8; 32] {
+        node_hash([
+            (v.head.len(), leaf_hash(&v.head)),
+            (v.inner.len(), fresh_hash(&v.inner)),
+            (v.tail.len(), leaf_hash(&v.tail)),
+        ])
+    }
+
+    /// Computes the root hash straight from its definition, chunking the whole sequence level by level.
+    fn fresh_root_hash(items: &[u64]) -> [u8; 32] {
+        let mut runs = Vec::new();
+        for x in items {
+            push_run(
+                &mut runs,
+                Run {
+                    count: 1,
+                    len: 1,
+                    hash: element_hash(x),
+                },
+            );
+        }
+        let mut bottom = true;
+        loop {
+            if let [run] = runs.as_slice() {
+                if !bottom && run.count == 1 {
+                    return run.hash;
+                }
+            }
+            let mut chunks = Vec::new();
+            let mut start = 0;
+            for i in 1..runs.len().saturating_sub(1) {
+                if runs[i].hash < runs[i - 1].hash && runs[i].hash < runs[i + 1].hash {
+                    push_run(&mut chunks, chunk_item(&runs[start..=i]));
+                    start = i + 1;
+                }
+            }
+            push_run(&mut chunks, chunk_item(&runs[start..]));
+            runs = chunks;
+            bottom = false;
+        }
+    }
+
+    #[test]
+    fn cached_hashes() {
+        let mut v: CatVec<u64, 4, 4> = (0..1000).collect::<Vec<_>>().into();
+        let original = v.clone();
+        let original_hash = v.root_hash();
+        assert_eq!(
+            original_hash,
+            fresh_root_hash(&v.iter().copied().collect::<Vec<_>>())
+        );
+        assert_eq!(v.storage_hash(), fresh_storage_hash(&v));
+        for i in 0..200u64 {
+            match i % 6 {
+                0 => v.insert((i as usize * 37) % v.len(), i),
+                1 => v.push_back(i),
+                2 => v.push_front(i),
+                3 => *v.get_mut((i as usize * 13) % v.len()).unwrap() += 1,
+                4 => v.apply_in_place(i as usize..i as usize * 3, |x| *x ^= i),
+                _ => {
+                    let mut other = original.clone();
+                    other.slice_into(i as usize..i as usize + 50);
+                    v.append(other);
+                }
+            }
+            assert_eq!(
+                v.root_hash(),
+                fresh_root_hash(&v.iter().copied().collect::<Vec<_>>())
+            );
+            assert_eq!(v.storage_hash(), fresh_storage_hash(&v));
+        }
+        v.check_invariants();
+        assert_ne!(v.root_hash(), original_hash);
+        assert_eq!(original.root_hash(), original_hash);
+        assert_eq!(v.clone().root_hash(), v.root_hash());
+        assert_ne!(
+            CatVec::<u64, 4, 4>::from(vec![1, 2]).root_hash(),
+            CatVec::<u64, 4, 4>::from(vec![2, 1]).root_hash()
+        );
+    }
+
+    #[test]
+    fn canonical_hashes() {
+        let patterns: [fn(u64) -> u64; 5] =
+            [|i| i, |_| 7, |i| i % 2, |i| i * i / 7 % 5, |i| i / 30 % 3];
+        for pattern in patterns {
+            let contents: Vec<u64> = (0..700).map(pattern).collect();
+            let root = fresh_root_hash(&contents);
+            let built: CatVec<u64, 4, 4> = contents.clone().into();
+            let mut pushed: CatVec<u64, 4, 4> = CatVec::new();
+            for &x in contents.iter().rev() {
+                pushed.push_front(x);
+            }
+            let mut glued: CatVec<u64, 4, 4> = contents[..123].into();
+            glued.append(contents[123..].into());
+            let mut sliced: CatVec<u64, 4, 4> = CatVec::new();
+            for piece in contents.chunks(37) {
+                let mut padded: CatVec<u64, 4, 4> = [&[1, 2, 3], piece, &[4]].concat().into();
+                padded.slice_into(3..3 + piece.len());
+                sliced.append(padded);
+            }
+            let mut inserted: CatVec<u64, 4, 4> = contents
+                .iter()
+                .step_by(2)
+                .copied()
+                .collect::<Vec<_>>()
+                .into();
+            for i in (1..contents.len()).step_by(2) {
+                inserted.insert(i, contents[i]);
+            }
+            let mut edited = built.clone();
+            *edited.get_mut(350).unwrap() += 1;
+            assert_ne!(edited.root_hash(), root);
+            *edited.get_mut(350).unwrap() -= 1;
+            let wide: CatVec<u64, 16, 64> = contents.clone().into();
+            assert_ne!(pushed.storage_hash(), glued.storage_hash());
+            for v in [&built, &pushed, &glued, &sliced, &inserted, &edited] {
+                v.check_invariants();
+                assert_eq!(v.iter().copied().collect::<Vec<_>>(), contents);
+                assert_eq!(v.root_hash(), root);
+                assert_eq!(v.canonicalize().storage_hash(), built.storage_hash());
+            }
+            assert_eq!(wide.root_hash(), root);
+            glued.push_back(1);
+            assert_ne!(glued.root_hash(), root);
+            glued.remove(glued.len() - 1);
+            glued.push_front(1);
+            assert_ne!(glued.root_hash(), root);
+        }
+        for contents in [vec![], vec![5], vec![5, 5], vec![5, 6]] {
+            let v: CatVec<u64, 4, 4> = contents.clone().into();
+            assert_eq!(v.root_hash(), fresh_root_hash(&contents));
+        }
+    }
+
+    #[test]
+    fn inclusion_proofs() {
+        let mut v: CatVec<u64, 4, 4> = (0..500).collect::<Vec<_>>().into();
+        v.push_front(1000);
+        v.push_back(2000);
+        let root = v.root_hash();
+        for i in (0..v.len()).step_by(7).chain([1, v.len() - 1]) {
+            let value = *v.get(i).unwrap();
+            let proof = v.prove(i).unwrap();
+            assert!(proof.verify(&root, i, &value));
+            assert!(!proof.verify(&root, i, &(value + 1)));
+            assert!(!proof.verify(&root, i + 1, &value));
+            assert!(!proof.verify(&[0; 32], i, &value));
+        }
+        assert!(v.prove(v.len()).is_none());
+        let proof = v.prove(3).unwrap();
+        v.push_back(0);
+        assert!(!proof.verify(&v.root_hash(), 3, &2u64));
+        assert!(v.prove(3).unwrap().verify(&v.root_hash(), 3, &2u64));
+    }
+
+    #[test]
+    fn range_proofs() {
+        let mut v: CatVec<u64, 4, 4> = (0..300).collect::<Vec<_>>().into();
+        v.push_front(1000);
+        v.push_back(2000);
+        let mut other: CatVec<u64, 4, 4> = (0..300).map(|i| i * 3).collect::<Vec<_>>().into();
+        other.slice_into(17..250);
+        v.append(other);
+        v.push_back(3000);
+        let root = v.root_hash();
+        let values: Vec<u64> = v.iter().copied().collect();
+        for (start, end) in [
+            (0, 1),
+            (0, 40),
+            (5, 5),
+            (1, 301),
+            (290, 330),
+            (400, values.len()),
+            (0, values.len()),
+        ] {
+            let proof = v.prove_range(start..end).unwrap();
+            assert!(proof.verify_range(&root, start, &values[start..end]));
+            if end > start {
+                assert!(!proof.verify_range(&root, start, &values[start..end - 1]));
+                assert!(!proof.verify_range(&root, start + 1, &values[start..end]));
+                let mut tampered = values[start..end].to_vec();
+                tampered[(end - start) / 2] += 1;
+                assert!(!proof.verify_range(&root, start, &tampered));
+            }
+        }
+        let page = v.prove_range(10..20).unwrap();
+        assert!(page.verify_range(&root, 10, &values[10..20]));
+        assert!(!page.verify_range(&root, 10, &values[10..21]));
+        assert!(v.prove_range(10..values.len() + 1).is_none());
+    }
+}
@@ -0,0 +1,512 @@
+use alloc::boxed::Box;
+use core::ops::RangeBounds;
+
+use archery::{ArcK, SharedPointer, SharedPointerKind};
+use rayon::{
+    iter::plumbing::{bridge, Consumer, Producer, ProducerCallback, UnindexedConsumer},
+    prelude::*,
+};
+
+use crate::{btree::Tree, focus::Focus, CatVec, Measure};
+
+impl<T: Clone, const ORD: usize, const LEAF: usize, P: SharedPointerKind, M: Measure<T>>
+    CatVec<T, ORD, LEAF, P, M>
+where
+    Self: Send,
+{
+    /// The number of elements each parallel task builds into a subtree. This is exactly enough for a full subtree of height two, so that the pieces are compact and so is their concatenation.
+    const PAR_CHUNK: usize = LEAF * ORD * ORD;
+
+    /// Builds a vector from a slice on all cores. Each task builds a full subtree out of one run of the slice, and the subtrees are then concatenated pairwise, so that the concatenations are balanced as well.
+    pub fn from_par_slice(slice: &[T]) -> Self
+    where
+        T: Sync,
+    {
+        slice
+            .par_chunks(Self::PAR_CHUNK)
+            .map(Self::from)
+            .reduce(Self::new, Self::concat_pair)
+    }
+
+    /// Builds a vector from a parallel iterator on all cores. Each task pushes its share of the elements into a vector of its own, and these are then concatenated pairwise.
+    pub fn from_par_iter(iter: impl IntoParallelIterator<Item = T>) -> Self {
+        iter.into_par_iter()
+            .fold(Self::new, |mut v, x| {
+                v.push_back(x);
+                v
+            })
+            .reduce(Self::new, Self::concat_pair)
+    }
+
+    fn concat_pair(mut left: Self, right: Self) -> Self {
+        left.append(right);
+        left
+    }
+}
+
+impl<
+        T: Clone + Send + Sync,
+        const ORD: usize,
+        const LEAF: usize,
+        P: SharedPointerKind + Send + Sync,
+        M: Measure<T>,
+    > CatVec<T, ORD, LEAF, P, M>
+{
+    /// Returns a parallel iterator over references to the elements. Work is divided along the boundaries of subtrees, splitting leaves only once there's nothing bigger left to hand out.
+    pub fn par_iter(&self) -> ParIter<'_, T, ORD, LEAF, P, M> {
+        ParIter { vec: self }
+    }
+}
+
+impl<
+        T: Clone + Send + Sync,
+        const ORD: usize,
+        const LEAF: usize,
+        P: SharedPointerKind + Send + Sync,
+        M: Measure<T>,
+    > CatVec<T, ORD, LEAF, P, M>
+{
+    /// Calls the given function on every leaf, as a slice, on all cores. The head and tail buffers count as leaves too.
+    pub fn par_for_each_leaf(&self, f: impl Fn(&[T]) + Sync + Send) {
+        let leaves: Vec<&[T]> = self.leaves().collect();
+        leaves.into_par_iter().for_each(f)
+    }
+
+    /// Folds the elements on all cores, like rayon's `fold` followed by `reduce`. Each task folds whole leaves starting from `identity()`, and the partial results are combined in order with `reduce`.
+    pub fn par_fold<A: Send>(
+        &self,
+        identity: impl Fn() -> A + Sync + Send,
+        fold: impl Fn(A, &T) -> A + Sync + Send,
+        reduce: impl Fn(A, A) -> A + Sync + Send,
+    ) -> A {
+        let leaves: Vec<&[T]> = self.leaves().collect();
+        leaves
+            .into_par_iter()
+            .fold(&identity, |acc, leaf| leaf.iter().fold(acc, &fold))
+            .reduce(&identity, reduce)
+    }
+
+    /// Like [CatVec::apply_in_place], but on all cores. Only the nodes on the paths to the leaves overlapping the range are copied if shared, so that every task owns the leaves it writes to, and sibling subtrees are then updated as separate tasks.
+    pub fn par_apply_in_place(
+        &mut self,
+        range: impl RangeBounds<usize>,
+        f: impl Fn(&mut T) + Sync + Send,
+    ) {
+        let [head, tree, tail] = self.split_range(range);
+        if !head.is_empty() {
+            SharedPointer::make_mut(&mut self.head)[head]
+                .par_iter_mut()
+                .for_each(&f);
+        }
+        if !tree.is_empty() {
+            self.tree_mut().par_apply(tree.start, tree.end, &f);
+        }
+        if !tail.is_empty() {
+            SharedPointer::make_mut(&mut self.tail)[tail]
+                .par_iter_mut()
+                .for_each(&f);
+        }
+    }
+
+    /// Maps every element on all cores into a new, unmeasured vector of exactly the same shape. Sibling subtrees are mapped as separate tasks, and each leaf is mapped by a single task.
+    pub fn par_map<U: Clone + Send + Sync>(
+        &self,
+        f: impl Fn(&T) -> U + Sync + Send,
+    ) -> CatVec<U, ORD, LEAF, P> {
+        let map_buffer = |buffer: &[T]| SharedPointer::new(buffer.iter().map(&f).collect());
+        CatVec {
+            inner: Box::new(map_tree(&self.inner, &f)),
+            head: map_buffer(&self.head),
+            tail: map_buffer(&self.tail),
+            focus: Focus::new(),
+        }
+    }
+}
+
+// Like [CatVec::get_mut], handing out mutable references would let cached measures go stale, so only unmeasured vectors can do it.
+impl<
+        T: Clone + Send + Sync,
+        const ORD: usize,
+        const LEAF: usize,
+        P: SharedPointerKind + Send + Sync,
+    > CatVec<T, ORD, LEAF, P>
+{
+    /// Returns a parallel iterator over mutable references to the elements. Every shared node is copied first, so that each leaf can be handed to a different task; to update only part of a shared vector, [CatVec::par_apply_in_place] copies less.
+    pub fn par_iter_mut(&mut self) -> impl ParallelIterator<Item = &mut T> + '_ {
+        let (inner, head, tail) = self.parts_mut();
+        let mut leaves = Vec::new();
+        if !head.is_empty() {
+            leaves.push(&mut SharedPointer::make_mut(head)[..]);
+        }
+        inner.leaves_mut(&mut leaves);
+        if !tail.is_empty() {
+            leaves.push(&mut SharedPointer::make_mut(tail)[..]);
+        }
+        leaves.into_par_iter().flat_map_iter(|leaf| leaf.iter_mut())
+    }
+}
+
+fn map_tree<
+    T: Clone + Send + Sync,
+    U: Clone + Send + Sync,
+    const ORD: usize,
+    const LEAF: usize,
+    P: SharedPointerKind + Send + Sync,
+    M: Measure<T>,
+>(
+    node: &Tree<T, ORD, LEAF, P, M>,
+    f: &(impl Fn(&T) -> U + Sync + Send),
+) -> Tree<U, ORD, LEAF, P, ()> {
+    match node {
+        Tree::Array(leaf) => Tree::Array(leaf.iter().map(f).collect()),
+        Tree::Internal(int) => {
+            let children: Vec<_> = node
+                .children()
+                .unwrap()
+                .par_iter()
+                .map(|child| SharedPointer::new(map_tree(child, f)))
+                .collect();
+            Tree::Internal(int.with_children(children.into_iter().collect()))
+        }
+    }
+}
+
+impl<T: Clone + Send, const ORD: usize, const LEAF: usize, P: SharedPointerKind, M: Measure<T>>
+    FromParallelIterator<T> for CatVec<T, ORD, LEAF, P, M>
+where
+    Self: Send,
+{
+    fn from_par_iter<I: IntoParallelIterator<Item = T>>(iter: I) -> Self {
+        CatVec::from_par_iter(iter)
+    }
+}
+
+impl<T: Clone + Send, const ORD: usize, const LEAF: usize, P: SharedPointerKind, M: Measure<T>>
+    ParallelExtend<T> for CatVec<T, ORD, LEAF, P, M>
+where
+    Self: Send,
+{
+    /// Builds the new elements into a vector of their own on all cores, then appends it.
+    fn par_extend<I: IntoParallelIterator<Item = T>>(&mut self, iter: I) {
+        self.append(CatVec::from_par_iter(iter))
+    }
+}
+
+/// A parallel iterator over references to the elements of a [CatVec], returned by [CatVec::par_iter].
+pub struct ParIter<
+    'a,
+    T: Clone,
+    const ORD: usize,
+    const LEAF: usize = 32,
+    P: SharedPointerKind = ArcK,
+    M: Measure<T> = (),
+> {
+    vec: &'a CatVec<T, ORD, LEAF, P, M>,
+}
+
+impl<
+        'a,
+        T: Clone + Send + Sync,
+        const ORD: usize,
+        const LEAF: usize,
+        P: SharedPointerKind + Send + Sync,
+        M: Measure<T>,
+    > ParallelIterator for ParIter<'a, T, ORD, LEAF, P, M>
+{
+    type Item = &'a T;
+
+    fn drive_unindexed<C: UnindexedConsumer<Self::Item>>(self, consumer: C) -> C::Result {
+        bridge(self, consumer)
+    }
+
+    fn opt_len(&self) -> Option<usize> {
+        Some(self.vec.len())
+    }
+}
+
+impl<
+        'a,
+        T: Clone + Send + Sync,
+        const ORD: usize,
+        const LEAF: usize,
+        P: SharedPointerKind + Send + Sync,
+        M: Measure<T>,
+    > IndexedParallelIterator for ParIter<'a, T, ORD, LEAF, P, M>
+{
+    fn len(&self) -> usize {
+        self.vec.len()
+    }
+
+    fn drive<C: Consumer<Self::Item>>(self, consumer: C) -> C::Result {
+        bridge(self, consumer)
+    }
+
+    fn with_producer<CB: ProducerCallback<Self::Item>>(self, callback: CB) -> CB::Output {
+        let vec = self.vec;
+        let pieces = IntoIterator::into_iter([
+            Piece::Slice(&vec.head[..]),
+            Piece::Node(&*vec.inner),
+            Piece::Slice(&vec.tail[..]),
+        ])
+        .filter(|piece| piece.len() > 0)
+        .collect();
+        callback.callback(PieceProducer { pieces })
+    }
+}
+
+impl<
+        'a,
+        T: Clone + Send + Sync,
+        const ORD: usize,
+        const LEAF: usize,
+        P: SharedPointerKind + Send + Sync,
+        M: Measure<T>,
+    > IntoParallelIterator for &'a CatVec<T, ORD, LEAF, P, M>
+{
+    type Iter = ParIter<'a, T, ORD, LEAF, P, M>;
+    type Item = &'a T;
+
+    fn into_par_iter(self) -> Self::Iter {
+        self.par_iter()
+    }
+}
+
+/// A contiguous part of a vector, as handed out to a parallel task: either a whole subtree or part of a leaf.
+enum Piece<'a, T: Clone, const ORD: usize, const LEAF: usize, P: SharedPointerKind, M: Measure<T>> {
+    Node(&'a Tree<T, ORD, LEAF, P, M>),
+    Slice(&'a [T]),
+}
+
+impl<'a, T: Clone, const ORD: usize, const LEAF: usize, P: SharedPointerKind, M: Measure<T>>
+    Piece<'a, T, ORD, LEAF, P, M>
+{
+    fn len(&self) -> usize {
+        match self {
+            Piece::Node(node) => node.len(),
+            Piece::Slice(slice) => slice.len(),
+        }
+    }
+}
+
+/// Produces the elements of a run of pieces, splitting them at subtree boundaries where it can.
+struct PieceProducer<
+    'a,
+    T: Clone,
+    const ORD: usize,
+    const LEAF: usize,
+    P: SharedPointerKind,
+    M: Measure<T>,
+> {
+    pieces: Vec<Piece<'a, T, ORD, LEAF, P, M>>,
+}
+
+impl<
+        'a,
+        T: Clone + Send + Sync,
+        const ORD: usize,
+        const LEAF: usize,
+        P: SharedPointerKind + Send + Sync,
+        M: Measure<T>,
+    > Producer for PieceProducer<'a, T, ORD, LEAF, P, M>
+{
+    type Item = &'a T;
+    type IntoIter = PieceIter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        let mut slices = Vec::new();
+        let mut remaining = 0;
+        for piece in self.pieces {
+            remaining += piece.len();
+            match piece {
+                Piece::Node(node) => slices.extend(node.leaves()),
+                Piece::Slice(slice) => slices.push(slice),
+            }
+        }
+        PieceIter {
+            inner: slices.into_iter().flatten(),
+            remaining,
+        }
+    }
+
+    fn split_at(mut self, mut index: usize) -> (Self, Self) {
+        let mut i = 0;
+        loop {
+            let len = self.pieces[i].len();
+            if index == 0 {
+                let right = self.pieces.split_off(i);
+                return (self, PieceProducer { pieces: right });
+            }
+            if index >= len {
+                index -= len;
+                i += 1;
+                continue;
+            }
+            // the split falls inside this piece, so open it up
+            match self.pieces[i] {
+                Piece::Node(Tree::Internal(_)) => {
+                    let node = self.pieces.remove(i);
+                    if let Piece::Node(node) = node {
+                        let children =
+                            node.children().unwrap().iter().map(|child| match &**child {
+                                Tree::Array(leaf) => Piece::Slice(&leaf[..]),
+                                child => Piece::Node(child),
+                            });
+                        self.pieces.splice(i..i, children);
+                    }
+                }
+                Piece::Node(Tree::Array(leaf)) => self.pieces[i] = Piece::Slice(&leaf[..]),
+                Piece::Slice(slice) => {
+                    let (left, right) = slice.split_at(index);
+                    let mut rest = self.pieces.split_off(i + 1);
+                    self.pieces[i] = Piece::Slice(left);
+                    rest.insert(0, Piece::Slice(right));
+                    return (self, PieceProducer { pieces: rest });
+                }
+            }
+        }
+    }
+}
+
+/// The sequential iterator over the elements of a run of pieces.
+struct PieceIter<'a, T> {
+    inner: core::iter::Flatten<alloc::vec::IntoIter<&'a [T]>>,
+    remaining: usize,
+}
+
+impl<'a, T> Iterator for PieceIter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let x = self.inner.next()?;
+        self.remaining -= 1;
+        Some(x)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<'a, T> DoubleEndedIterator for PieceIter<'a, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let x = self.inner.next_back()?;
+        self.remaining -= 1;
+        Some(x)
+    }
+}
+
+impl<'a, T> ExactSizeIterator for PieceIter<'a, T> {}
+
+#[cfg(test)]
+mod tests {
+    use rayon::prelude::*;
+
+    use crate::CatVec;
+
+    #[test]
+    fn parallel_construction() {
+        let shadow: Vec<usize> = (0..100_000).collect();
+        let v: CatVec<usize, 4, 4> = CatVec::from_par_slice(&shadow);
+        v.check_invariants();
+        assert_eq!(Vec::from(v), shadow);
+        let v: CatVec<usize, 4, 4> = CatVec::from_par_iter((0..100_000).into_par_iter());
+        v.check_invariants();
+        assert_eq!(Vec::from(v), shadow);
+        let empty: CatVec<usize, 4, 4> = CatVec::from_par_slice(&[]);
+        assert!(empty.is_empty());
+    }
+
+    #[test]
+    fn leaf_tasks() {
+        let mut v: CatVec<usize, 4, 4> = (0..10_000).collect::<Vec<_>>().into();
+        v.insert(5000, 1);
+        v.push_front(2);
+        v.push_back(3);
+        let shadow = Vec::from(v.clone());
+        let mapped = v.par_map(|x| x * 3);
+        mapped.check_invariants();
+        assert_eq!(mapped.tree_stats(), v.tree_stats());
+        assert_eq!(
+            Vec::from(mapped),
+            shadow.iter().map(|x| x * 3).collect::<Vec<_>>()
+        );
+        let sum = v.par_fold(|| 0, |acc, x| acc + x, |a, b| a + b);
+        assert_eq!(sum, shadow.iter().sum::<usize>());
+        let count = std::sync::atomic::AtomicUsize::new(0);
+        v.par_for_each_leaf(|leaf| {
+            assert!(!leaf.is_empty() && leaf.len() <= 4);
+            count.fetch_add(leaf.len(), std::sync::atomic::Ordering::Relaxed);
+        });
+        assert_eq!(count.into_inner(), shadow.len());
+    }
+
+    #[test]
+    fn par_iter() {
+        let shadow: Vec<usize> = (0..10_000).collect();
+        let mut v: CatVec<usize, 4, 4> = shadow.clone().into();
+        for i in 0..10 {
+            v.push_front(i);
+            v.push_back(i);
+        }
+        let shadow: Vec<usize> = Vec::from(v.clone());
+        assert_eq!(v.par_iter().copied().collect::<Vec<_>>(), shadow);
+        assert_eq!(
+            (&v).into_par_iter().sum::<usize>(),
+            shadow.iter().sum::<usize>()
+        );
+        let doubled: CatVec<usize, 4, 4> = v.par_iter().map(|x| x * 2).collect();
+        doubled.check_invariants();
+        assert!(doubled.iter().zip(shadow.iter()).all(|(a, b)| *a == b * 2));
+        let mut extended = v.clone();
+        extended.par_extend((0..1000).into_par_iter());
+        extended.check_invariants();
+        assert_eq!(extended.len(), shadow.len() + 1000);
+        assert_eq!(extended.get(shadow.len() + 999), Some(&999));
+        assert_eq!(v.par_iter().position_first(|&x| x == 5000), Some(5010));
+        let pairs: Vec<_> = v
+            .par_iter()
+            .zip(v.par_iter().skip(1))
+            .map(|(a, b)| (*a, *b))
+            .collect();
+        assert_eq!(pairs.len(), shadow.len() - 1);
+        assert!(pairs
+            .iter()
+            .zip(shadow.windows(2))
+            .all(|(p, w)| p.0 == w[0] && p.1 == w[1]));
+        assert_eq!(
+            v.par_iter().rev().copied().collect::<Vec<_>>(),
+            shadow.iter().rev().copied().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn parallel_mutation() {
+        let mut v: CatVec<usize, 4, 4> = (0..10_000).collect::<Vec<_>>().into();
+        v.push_front(7);
+        v.push_back(8);
+        let snapshot = v.clone();
+        let mut shadow = Vec::from(v.clone());
+        v.par_iter_mut().for_each(|x| *x += 1);
+        shadow.iter_mut().for_each(|x| *x += 1);
+        v.check_invariants();
+        assert_eq!(Vec::from(v.clone()), shadow);
+        for (start, end) in [(0, 1), (0, 10_002), (17, 5000), (9_990, 10_002), (300, 300)] {
+            let shared = v.clone();
+            v.par_apply_in_place(start..end, |x| *x = *x * 2 + 1);
+            shadow[start..end].iter_mut().for_each(|x| *x = *x * 2 + 1);
+            v.check_invariants();
+            assert_eq!(Vec::from(v.clone()), shadow);
+            assert_ne!(shared.iter().eq(v.iter()), start < end);
+        }
+        assert!(snapshot
+            .iter()
+            .copied()
+            .eq(IntoIterator::into_iter([7]).chain(0..10_000).chain([8])));
+
+        let mut text: CatVec<u8, 4, 4, archery::ArcK, crate::TextMetrics> =
+            b"abc\n".repeat(1000).as_slice().into();
+        text.par_apply_in_place(400..2000, |b| *b = b'x');
+        text.check_invariants();
+        assert_eq!(text.measure().line_breaks, 600);
+    }
+}
@@ -0,0 +1,150 @@
+use alloc::vec::Vec;
+use core::ops::RangeBounds;
+
+use ::rand::{Rng, RngExt};
+use archery::{SharedPointer, SharedPointerKind};
+
+use crate::{range_bounds, CatVec, Measure, SliceError};
+
+impl<T: Clone, const ORD: usize, const LEAF: usize, P: SharedPointerKind, M: Measure<T>>
+    CatVec<T, ORD, LEAF, P, M>
+{
+    /// Returns a uniformly random element, in O(log n) time, or None if the vector is empty.
+    pub fn choose<R: Rng + ?Sized>(&self, rng: &mut R) -> Option<&T> {
+        if self.is_empty() {
+            return None;
+        }
+        self.get(rng.random_range(0..self.len()))
+    }
+
+    /// Draws `k` positions from a range, independently and with replacement, each with probability proportional to the weight of the element there, as summed by the measure. Each draw takes one O(log n) descent with [CatVec::find_by_measure], so no weights are visited one by one. Returns no positions if the range has no weight at all. Panics if the range is inverted or out of bounds.
+    pub fn sample<R: Rng + ?Sized>(
+        &self,
+        range: impl RangeBounds<usize>,
+        k: usize,
+        rng: &mut R,
+    ) -> Vec<usize>
+    where
+        M: Measure<T, Summary = u64>,
+    {
+        let (start, end) = range_bounds(range, self.len());
+        if start > end || end > self.len() {
+            panic!(
+                "{}",
+                SliceError {
+                    start,
+                    end,
+                    len: self.len()
+                }
+            )
+        }
+        let before = self.measure_prefix(start);
+        let total = self.measure_range(start..end);
+        if total == 0 {
+            return Vec::new();
+        }
+        (0..k)
+            .map(|_| {
+                let target = before + rng.random_range(0..total);
+                self.find_by_measure(|&sum| sum > target)
+                    .expect("target is below the total weight")
+            })
+            .collect()
+    }
+}
+
+// Shuffling moves elements between leaves without visiting the cached measures, so only unmeasured vectors can do it.
+impl<T: Clone, const ORD: usize, const LEAF: usize, P: SharedPointerKind> CatVec<T, ORD, LEAF, P> {
+    /// Shuffles the elements in place with a Fisher-Yates shuffle, swapping elements directly between leaves. Every shared leaf is copied once, up front, and the shape of the tree doesn't change.
+    pub fn shuffle<R: Rng + ?Sized>(&mut self, rng: &mut R) {
+        let len = self.len();
+        let (inner, head, tail) = self.parts_mut();
+        let mut leaves: Vec<&mut [T]> = Vec::new();
+        leaves.push(SharedPointer::make_mut(head).as_mut_slice());
+        inner.leaves_mut(&mut leaves);
+        leaves.push(SharedPointer::make_mut(tail).as_mut_slice());
+        leaves.retain(|leaf| !leaf.is_empty());
+        let starts: Vec<usize> = leaves
+            .iter()
+            .scan(0, |pos, leaf| {
+                let start = *pos;
+                *pos += leaf.len();
+                Some(start)
+            })
+            .collect();
+        let locate = |i: usize| {
+            let leaf = starts.partition_point(|&start| start <= i) - 1;
+            (leaf, i - starts[leaf])
+        };
+        for i in (1..len).rev() {
+            let ((a, x), (b, y)) = (locate(i), locate(rng.random_range(0..=i)));
+            if a == b {
+                leaves[a].swap(x, y);
+            } else {
+                let [p, q] = leaves
+                    .get_disjoint_mut([a, b])
+                    .expect("leaves are distinct and in bounds");
+                core::mem::swap(&mut p[x], &mut q[y]);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ::rand::{rngs::SmallRng, SeedableRng};
+    use archery::ArcK;
+
+    use crate::{CatVec, Measure};
+
+    /// Sums weights.
+    struct Weight;
+
+    impl Measure<u64> for Weight {
+        type Summary = u64;
+
+        fn identity() -> u64 {
+            0
+        }
+
+        fn measure(x: &u64) -> u64 {
+            *x
+        }
+
+        fn combine(left: &u64, right: &u64) -> u64 {
+            left + right
+        }
+    }
+
+    #[test]
+    fn random_operations() {
+        let mut rng = SmallRng::seed_from_u64(7);
+        let mut v: CatVec<usize, 4, 4> = (0..1000).collect::<Vec<_>>().into();
+        v.push_front(1000);
+        let snapshot = v.clone();
+        let chosen = v.choose(&mut rng).unwrap();
+        assert!(*chosen <= 1000);
+        assert_eq!(CatVec::<usize, 4>::new().choose(&mut rng), None);
+
+        v.shuffle(&mut rng);
+        v.check_invariants();
+        let mut sorted = Vec::from(v.clone());
+        assert_ne!(sorted, Vec::from(snapshot.clone()));
+        sorted.sort();
+        assert_eq!(sorted, (0..1001).collect::<Vec<_>>());
+        assert_eq!(snapshot.get(0), Some(&1000));
+
+        // Only the odd positions in the range have any weight, and position 13 has five times as much as the rest.
+        let weights: Vec<u64> = (0..500)
+            .map(|i| (i % 2) as u64 * if i == 13 { 5 } else { 1 })
+            .collect();
+        let w: CatVec<u64, 4, 4, ArcK, Weight> = weights.into();
+        let drawn = w.sample(10..20, 4000, &mut rng);
+        assert_eq!(drawn.len(), 4000);
+        assert!(drawn.iter().all(|&i| (10..20).contains(&i) && i % 2 == 1));
+        let heavy = drawn.iter().filter(|&&i| i == 13).count();
+        // 5/9 of the weight, so around 2222 draws
+        assert!((2000..2450).contains(&heavy));
+        assert!(w.sample(10..10, 5, &mut rng).is_empty());
+    }
+}
@@ -0,0 +1,342 @@
+use core::{iter::FromIterator, ops::RangeBounds};
+
+use archery::{ArcK, SharedPointerKind};
+
+use crate::{range_bounds, CatVec, Measure, SliceError};
+
+/// A value repeated some number of times.
+#[derive(Clone, Debug, PartialEq)]
+struct Run<T> {
+    value: T,
+    count: usize,
+}
+
+/// Counts the elements in a run of runs, so that the tree can find the run holding a position.
+struct RunLength;
+
+impl<T> Measure<Run<T>> for RunLength {
+    type Summary = usize;
+
+    fn identity() -> usize {
+        0
+    }
+
+    fn measure(run: &Run<T>) -> usize {
+        run.count
+    }
+
+    fn combine(left: &usize, right: &usize) -> usize {
+        left + right
+    }
+}
+
+/// A vector stored as runs of equal consecutive elements, for contents dominated by long runs, such as sparse bitmaps or mostly zeroed state. Each leaf holds `(value, count)` pairs, and the tree caches the total count under every node, so finding the element at a position takes O(log n) time in the number of runs rather than elements. Like [CatVec], it concatenates and slices in O(log n) time, and clones share all their structure.
+///
+/// Adjacent runs always have different values, so a vector has only one representation, and no run is empty.
+pub struct RleCatVec<
+    T: Clone,
+    const ORD: usize,
+    const LEAF: usize = 32,
+    P: SharedPointerKind = ArcK,
+> {
+    runs: CatVec<Run<T>, ORD, LEAF, P, RunLength>,
+    len: usize,
+}
+
+impl<T: Clone, const ORD: usize, const LEAF: usize, P: SharedPointerKind> Clone
+    for RleCatVec<T, ORD, LEAF, P>
+{
+    fn clone(&self) -> Self {
+        Self {
+            runs: self.runs.clone(),
+            len: self.len,
+        }
+    }
+}
+
+impl<T: Clone + PartialEq, const ORD: usize, const LEAF: usize, P: SharedPointerKind>
+    RleCatVec<T, ORD, LEAF, P>
+{
+    /// Creates a new empty vector.
+    pub fn new() -> Self {
+        Self {
+            runs: CatVec::new(),
+            len: 0,
+        }
+    }
+
+    /// Creates a vector of `count` copies of a value.
+    pub fn from_elem(value: T, count: usize) -> Self {
+        let mut v = Self::new();
+        v.push_back_n(value, count);
+        v
+    }
+
+    /// Length of the vector, counting every element of every run.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns true if the vector is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The number of runs.
+    pub fn run_count(&self) -> usize {
+        self.runs.len()
+    }
+
+    /// Finds the run holding the element at a position, which must be in bounds, returning the index of the run and the position of the element within it.
+    fn locate(&self, i: usize) -> (usize, usize) {
+        let run = self
+            .runs
+            .find_by_measure(|&count| count > i)
+            .expect("position out of bounds");
+        (run, i - self.runs.measure_prefix(run))
+    }
+
+    /// Gets a reference to the element at a particular position, in O(log n) time.
+    pub fn get(&self, i: usize) -> Option<&T> {
+        if i >= self.len {
+            return None;
+        }
+        let (run, _) = self.locate(i);
+        self.runs.get(run).map(|run| &run.value)
+    }
+
+    /// Iterates over the runs in order, as pairs of a run's length and its value.
+    pub fn runs(&self) -> impl Iterator<Item = (usize, &T)> + Clone + '_ {
+        self.runs.iter().map(|run| (run.count, &run.value))
+    }
+
+    /// Iterates over references to the elements, repeating each run's value as many times as it occurs.
+    pub fn iter(&self) -> impl Iterator<Item = &T> + Clone + '_ {
+        self.runs()
+            .flat_map(|(count, value)| core::iter::repeat_n(value, count))
+    }
+
+    /// Pushes to the back of the vector, extending the last run if it has the same value.
+    pub fn push_back(&mut self, value: T) {
+        self.push_back_n(value, 1)
+    }
+
+    /// Pushes `count` copies of a value to the back of the vector, as one run, or as part of the last run if it has the same value.
+    pub fn push_back_n(&mut self, value: T, count: usize) {
+        if count == 0 {
+            return;
+        }
+        self.len += count;
+        let last = self.runs.len().wrapping_sub(1);
+        match self.runs.get(last) {
+            Some(run) if run.value == value => self
+                .runs
+                .apply_in_place(last..last + 1, |run| run.count += count),
+            _ => self.runs.push_back(Run { value, count }),
+        }
+    }
+
+    /// Concatenates another vector onto this one, merging the runs on either side of the seam if they have the same value.
+    pub fn append(&mut self, mut other: Self) {
+        if other.is_empty() {
+            return;
+        }
+        let first = other.runs.get(0).expect("nonempty vector has no runs");
+        let last = self.runs.len().wrapping_sub(1);
+        if matches!(self.runs.get(last), Some(run) if run.value == first.value) {
+            let count = first.count;
+            self.runs
+                .apply_in_place(last..last + 1, |run| run.count += count);
+            other.runs.slice_into(1..);
+        }
+        self.len += other.len;
+        self.runs.append(other.runs);
+    }
+
+    /// Slices a subset of the vector, in O(log n) time, trimming the runs at either end. Panics if the range starts after it ends or ends past the end of the vector.
+    pub fn slice_into(&mut self, range: impl RangeBounds<usize>) {
+        let (start, end) = range_bounds(range, self.len);
+        if start > end || end > self.len {
+            panic!(
+                "{}",
+                SliceError {
+                    start,
+                    end,
+                    len: self.len
+                }
+            )
+        }
+        if start == end {
+            *self = Self::new();
+            return;
+        }
+        let (first, skip) = self.locate(start);
+        let (last, keep) = self.locate(end - 1);
+        self.runs.slice_into(first..=last);
+        let runs = self.runs.len();
+        if runs == 1 {
+            self.runs.apply_in_place(.., |run| run.count = end - start);
+        } else {
+            self.runs.apply_in_place(..1, |run| run.count -= skip);
+            self.runs
+                .apply_in_place(runs - 1.., |run| run.count = keep + 1);
+        }
+        self.len = end - start;
+    }
+
+    /// Returns a range of the vector as a vector of its own, sharing structure with this one. Panics if the range is inverted or out of bounds, like [RleCatVec::slice_into].
+    pub fn subvec(&self, range: impl RangeBounds<usize>) -> Self {
+        let mut sub = self.clone();
+        sub.slice_into(range);
+        sub
+    }
+
+    /// Replaces the element at a particular position, in O(log n) time, splitting the run holding it if need be. Panics if the position is out of bounds.
+    pub fn set(&mut self, i: usize, value: T) {
+        assert!(
+            i < self.len,
+            "index {} out of bounds for a vector of length {}",
+            i,
+            self.len
+        );
+        let right = self.subvec(i + 1..);
+        self.slice_into(..i);
+        self.push_back(value);
+        self.append(right);
+    }
+
+    /// Inserts the given element at the given position, shifting all elements after that rightwards. Panics if the position is past the end.
+    pub fn insert(&mut self, i: usize, value: T) {
+        assert!(
+            i <= self.len,
+            "index {} out of bounds for a vector of length {}",
+            i,
+            self.len
+        );
+        let right = self.subvec(i..);
+        self.slice_into(..i);
+        self.push_back(value);
+        self.append(right);
+    }
+
+    /// Checks invariants, both those of the tree of runs and that runs are nonempty, with adjacent ones different.
+    pub fn check_invariants(&self) {
+        self.runs.check_invariants();
+        assert_eq!(self.runs.measure(), self.len, "cached length is stale");
+        assert!(self.runs.iter().all(|run| run.count > 0), "empty run");
+        let mut runs = self.runs.iter();
+        if let Some(mut prev) = runs.next() {
+            for run in runs {
+                assert!(run.value != prev.value, "adjacent runs are equal");
+                prev = run;
+            }
+        }
+    }
+}
+
+impl<T: Clone + PartialEq, const ORD: usize, const LEAF: usize, P: SharedPointerKind> Default
+    for RleCatVec<T, ORD, LEAF, P>
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Clone + PartialEq, const ORD: usize, const LEAF: usize, P: SharedPointerKind>
+    FromIterator<T> for RleCatVec<T, ORD, LEAF, P>
+{
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut v = Self::new();
+        for x in iter {
+            v.push_back(x);
+        }
+        v
+    }
+}
+
+impl<T: Clone + PartialEq, const ORD: usize, const LEAF: usize, P: SharedPointerKind>
+    From<RleCatVec<T, ORD, LEAF, P>> for alloc::vec::Vec<T>
+{
+    fn from(v: RleCatVec<T, ORD, LEAF, P>) -> Self {
+        v.iter().cloned().collect()
+    }
+}
+
+// Since runs are kept in their one canonical form, equal vectors have equal runs.
+impl<T: Clone + PartialEq, const ORD: usize, const LEAF: usize, P: SharedPointerKind> PartialEq
+    for RleCatVec<T, ORD, LEAF, P>
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.len == other.len && self.runs == other.runs
+    }
+}
+
+impl<T: Clone + Eq, const ORD: usize, const LEAF: usize, P: SharedPointerKind> Eq
+    for RleCatVec<T, ORD, LEAF, P>
+{
+}
+
+// Formats the runs rather than the elements, which may be far too many to list.
+impl<
+        T: Clone + PartialEq + core::fmt::Debug,
+        const ORD: usize,
+        const LEAF: usize,
+        P: SharedPointerKind,
+    > core::fmt::Debug for RleCatVec<T, ORD, LEAF, P>
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_list().entries(self.runs()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RleCatVec;
+
+    #[test]
+    fn run_length_encoding() {
+        // Mostly zeroes, with a few scattered ones, like a sparse bitmap.
+        let mut shadow = vec![0u8; 100_000];
+        let mut v: RleCatVec<u8, 4, 4> = RleCatVec::from_elem(0, 100_000);
+        for k in 0..300usize {
+            let i = k * 7919 % 100_000;
+            shadow[i] = 1;
+            v.set(i, 1);
+        }
+        v.check_invariants();
+        assert_eq!(v.len(), shadow.len());
+        assert!(v.run_count() < 601);
+        for i in (0..shadow.len()).step_by(97) {
+            assert_eq!(v.get(i), Some(&shadow[i]));
+        }
+        assert_eq!(v.get(shadow.len()), None);
+
+        let snapshot = v.clone();
+        for k in 0..50usize {
+            let i = k * 104_729 % v.len();
+            v.insert(i, (k % 3) as u8);
+            shadow.insert(i, (k % 3) as u8);
+        }
+        v.check_invariants();
+        assert_eq!(Vec::from(v.clone()), shadow);
+        assert_eq!(snapshot.len(), 100_000);
+
+        for (start, end) in [(0, 0), (3, 4), (10, 70_000), (500, shadow.len())] {
+            let sub = v.subvec(start..end);
+            sub.check_invariants();
+            assert_eq!(Vec::from(sub), &shadow[start..end]);
+        }
+
+        // Appending merges the runs that meet at the seam.
+        let mut w: RleCatVec<u8, 4, 4> = v.subvec(..50_000);
+        w.append(v.subvec(50_000..));
+        w.check_invariants();
+        assert_eq!(w, v);
+
+        let collected: RleCatVec<char, 4> = "aaabccccd".chars().collect();
+        assert_eq!(
+            collected.runs().map(|(n, &c)| (n, c)).collect::<Vec<_>>(),
+            [(3, 'a'), (1, 'b'), (4, 'c'), (1, 'd')]
+        );
+    }
+}
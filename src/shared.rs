@@ -0,0 +1,200 @@
+use alloc::vec::Vec;
+use core::{iter::FromIterator, ops::RangeBounds};
+
+use archery::{ArcK, SharedPointer, SharedPointerKind};
+
+use crate::CatVec;
+
+/// A [CatVec] that keeps each element behind its own reference-counted pointer, so that elements don't have to be Clone. Whenever the vector would clone an element, such as when copying a shared leaf before writing to it, it only bumps a reference count instead.
+///
+/// The underlying vector of pointers is available through [SharedCatVec::as_catvec] for everything not wrapped here.
+pub struct SharedCatVec<T, const ORD: usize, const LEAF: usize = 32, P: SharedPointerKind = ArcK> {
+    inner: CatVec<SharedPointer<T, P>, ORD, LEAF, P>,
+}
+
+impl<T, const ORD: usize, const LEAF: usize, P: SharedPointerKind> Clone
+    for SharedCatVec<T, ORD, LEAF, P>
+{
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<T, const ORD: usize, const LEAF: usize, P: SharedPointerKind> SharedCatVec<T, ORD, LEAF, P> {
+    /// Creates a new empty vector.
+    pub fn new() -> Self {
+        Self {
+            inner: CatVec::new(),
+        }
+    }
+
+    /// The underlying vector of element pointers.
+    pub fn as_catvec(&self) -> &CatVec<SharedPointer<T, P>, ORD, LEAF, P> {
+        &self.inner
+    }
+
+    /// Mutable access to the underlying vector of element pointers.
+    pub fn as_catvec_mut(&mut self) -> &mut CatVec<SharedPointer<T, P>, ORD, LEAF, P> {
+        &mut self.inner
+    }
+
+    /// Gets a reference to the element at a particular position.
+    pub fn get(&self, i: usize) -> Option<&T> {
+        self.inner.get(i).map(|x| &**x)
+    }
+
+    /// Gets the pointer to the element at a particular position, which can be cloned to share the element.
+    pub fn get_shared(&self, i: usize) -> Option<&SharedPointer<T, P>> {
+        self.inner.get(i)
+    }
+
+    /// Gets a mutable reference to the element at a particular position. The element is cloned first if another vector shares it.
+    pub fn get_mut(&mut self, i: usize) -> Option<&mut T>
+    where
+        T: Clone,
+    {
+        self.inner.get_mut(i).map(SharedPointer::make_mut)
+    }
+
+    /// Replaces the element at a particular position, returning the pointer to the old one. Returns the given value back if the position is out of range.
+    pub fn replace(&mut self, i: usize, val: T) -> Result<SharedPointer<T, P>, T> {
+        match self.inner.get_mut(i) {
+            Some(x) => Ok(core::mem::replace(x, SharedPointer::new(val))),
+            None => Err(val),
+        }
+    }
+
+    /// Iterates over references to the elements.
+    pub fn iter(&self) -> impl ExactSizeIterator<Item = &T> + Clone + '_ {
+        self.inner.iter().map(|x| &**x)
+    }
+
+    /// Inserts the given element at the given position, shifting all elements after that rightwards.
+    pub fn insert(&mut self, idx: usize, val: T) {
+        self.inner.insert(idx, SharedPointer::new(val))
+    }
+
+    /// Pushes to the back of the vector.
+    pub fn push_back(&mut self, val: T) {
+        self.inner.push_back(SharedPointer::new(val))
+    }
+
+    /// Pushes to the front of the vector.
+    pub fn push_front(&mut self, val: T) {
+        self.inner.push_front(SharedPointer::new(val))
+    }
+
+    /// Removes the first element, returning the pointer to it.
+    pub fn pop_front(&mut self) -> Option<SharedPointer<T, P>> {
+        self.inner.pop_front()
+    }
+
+    /// Slices a subset of the vector. "Zooms into" a part of the vector.
+    pub fn slice_into(&mut self, range: impl RangeBounds<usize>) {
+        self.inner.slice_into(range)
+    }
+
+    /// Concatenates another vector onto this one.
+    pub fn append(&mut self, other: Self) {
+        self.inner.append(other.inner)
+    }
+
+    /// Returns true if the two vectors are known to be the same version. See [CatVec::ptr_eq].
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        self.inner.ptr_eq(&other.inner)
+    }
+
+    /// Length of the vector.
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    /// Returns true if the vector is empty.
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
+    /// Checks invariants.
+    pub fn check_invariants(&self) {
+        self.inner.check_invariants()
+    }
+}
+
+impl<T, const ORD: usize, const LEAF: usize, P: SharedPointerKind> Default
+    for SharedCatVec<T, ORD, LEAF, P>
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, const ORD: usize, const LEAF: usize, P: SharedPointerKind> FromIterator<T>
+    for SharedCatVec<T, ORD, LEAF, P>
+{
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let ptrs: Vec<_> = iter.into_iter().map(SharedPointer::new).collect();
+        Self { inner: ptrs.into() }
+    }
+}
+
+impl<T, const ORD: usize, const LEAF: usize, P: SharedPointerKind> From<Vec<T>>
+    for SharedCatVec<T, ORD, LEAF, P>
+{
+    fn from(v: Vec<T>) -> Self {
+        v.into_iter().collect()
+    }
+}
+
+impl<T: PartialEq, const ORD: usize, const LEAF: usize, P: SharedPointerKind> PartialEq
+    for SharedCatVec<T, ORD, LEAF, P>
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.iter().eq(other.iter())
+    }
+}
+
+impl<T: Eq, const ORD: usize, const LEAF: usize, P: SharedPointerKind> Eq
+    for SharedCatVec<T, ORD, LEAF, P>
+{
+}
+
+impl<T: core::fmt::Debug, const ORD: usize, const LEAF: usize, P: SharedPointerKind>
+    core::fmt::Debug for SharedCatVec<T, ORD, LEAF, P>
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SharedCatVec;
+
+    /// Deliberately not Clone.
+    #[derive(Debug, PartialEq)]
+    struct Token(usize);
+
+    #[test]
+    fn non_clone_elements() {
+        let mut v: SharedCatVec<Token, 4, 4> = (0..100).map(Token).collect();
+        let w = v.clone();
+        v.insert(50, Token(1000));
+        v.push_front(Token(2000));
+        v.check_invariants();
+        assert_eq!(v.get(0), Some(&Token(2000)));
+        assert_eq!(v.get(51), Some(&Token(1000)));
+        assert_eq!(w.get(50), Some(&Token(50)));
+        assert!(core::ptr::eq(v.get(1).unwrap(), w.get(0).unwrap()));
+        let old = v.replace(2, Token(3000)).unwrap();
+        assert_eq!(*old, Token(1));
+        v.slice_into(1..);
+        v.append(w);
+        assert_eq!(v.len(), 201);
+        assert_eq!(
+            v.iter().map(|t| t.0).take(4).collect::<Vec<_>>(),
+            vec![0, 3000, 2, 3]
+        );
+    }
+}
@@ -0,0 +1,290 @@
+use alloc::{collections::BTreeSet, vec};
+
+use archery::{SharedPointer, SharedPointerKind};
+use arrayvec::ArrayVec;
+
+use crate::{btree::Tree, Measure};
+
+/// How much structure two vectors share, as returned by [crate::CatVec::shared_bytes_with]. Sharing is determined by pointer identity of tree nodes and of the head and tail buffers; the root node of a vector is never shared.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SharingStats {
+    /// Number of nodes also reachable from the other vector.
+    pub shared_nodes: usize,
+    /// Number of nodes only reachable from this vector.
+    pub unique_nodes: usize,
+    /// Number of elements stored in shared leaves.
+    pub shared_elements: usize,
+    /// Number of elements stored in unshared leaves.
+    pub unique_elements: usize,
+    /// Heap bytes taken up by shared nodes.
+    pub shared_bytes: usize,
+    /// Heap bytes taken up by unshared nodes.
+    pub unique_bytes: usize,
+}
+
+impl SharingStats {
+    pub(crate) fn compute<
+        T: Clone,
+        const ORD: usize,
+        const LEAF: usize,
+        P: SharedPointerKind,
+        M: Measure<T>,
+    >(
+        this: &Tree<T, ORD, LEAF, P, M>,
+        other: &Tree<T, ORD, LEAF, P, M>,
+    ) -> Self {
+        let mut others = BTreeSet::new();
+        collect_ptrs(other, &mut others);
+        let mut stats = Self::default();
+        stats.add_unique(this);
+        stats.walk(this, &others);
+        stats
+    }
+
+    /// Counts one of the vector's head and tail buffers, unless it is empty.
+    pub(crate) fn add_buffer<T, const LEAF: usize>(
+        &mut self,
+        buffer: &ArrayVec<T, LEAF>,
+        shared: bool,
+    ) {
+        if buffer.is_empty() {
+            return;
+        }
+        let bytes = core::mem::size_of::<ArrayVec<T, LEAF>>() + 2 * core::mem::size_of::<usize>();
+        if shared {
+            self.shared_nodes += 1;
+            self.shared_bytes += bytes;
+            self.shared_elements += buffer.len();
+        } else {
+            self.unique_nodes += 1;
+            self.unique_bytes += bytes;
+            self.unique_elements += buffer.len();
+        }
+    }
+
+    fn walk<T: Clone, const ORD: usize, const LEAF: usize, P: SharedPointerKind, M: Measure<T>>(
+        &mut self,
+        node: &Tree<T, ORD, LEAF, P, M>,
+        others: &BTreeSet<*const Tree<T, ORD, LEAF, P, M>>,
+    ) {
+        for child in node.children().into_iter().flatten() {
+            if others.contains(&SharedPointer::as_ptr(child)) {
+                self.add_shared(child);
+            } else {
+                self.add_unique(child);
+                self.walk(child, others);
+            }
+        }
+    }
+
+    fn add_unique<
+        T: Clone,
+        const ORD: usize,
+        const LEAF: usize,
+        P: SharedPointerKind,
+        M: Measure<T>,
+    >(
+        &mut self,
+        node: &Tree<T, ORD, LEAF, P, M>,
+    ) {
+        self.unique_nodes += 1;
+        self.unique_bytes += node_bytes::<T, ORD, LEAF, P, M>();
+        if let Tree::Array(items) = node {
+            self.unique_elements += items.len();
+        }
+    }
+
+    /// Counts a shared node and everything below it.
+    fn add_shared<
+        T: Clone,
+        const ORD: usize,
+        const LEAF: usize,
+        P: SharedPointerKind,
+        M: Measure<T>,
+    >(
+        &mut self,
+        node: &Tree<T, ORD, LEAF, P, M>,
+    ) {
+        self.shared_nodes += 1;
+        self.shared_bytes += node_bytes::<T, ORD, LEAF, P, M>();
+        match node.children() {
+            Some(children) => children.iter().for_each(|c| self.add_shared(c)),
+            None => self.shared_elements += node.len(),
+        }
+    }
+}
+
+/// An estimate of the heap memory used by a vector, as returned by [crate::CatVec::memory_usage]. Nodes shared within the vector are counted once. Heap memory owned by the elements themselves is not included.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MemoryUsage {
+    /// Number of distinct nodes, including the root.
+    pub nodes: usize,
+    /// Bytes taken up by all nodes. Since leaves store elements inline, this includes the elements.
+    pub node_bytes: usize,
+    /// Bytes taken up by the elements stored in leaves. The rest of `node_bytes` is bookkeeping and unused leaf capacity.
+    pub element_bytes: usize,
+}
+
+impl MemoryUsage {
+    pub(crate) fn compute<
+        T: Clone,
+        const ORD: usize,
+        const LEAF: usize,
+        P: SharedPointerKind,
+        M: Measure<T>,
+    >(
+        root: &Tree<T, ORD, LEAF, P, M>,
+    ) -> Self {
+        let mut usage = Self {
+            nodes: 1,
+            node_bytes: core::mem::size_of::<Tree<T, ORD, LEAF, P, M>>(),
+            element_bytes: 0,
+        };
+        if let Tree::Array(items) = root {
+            usage.element_bytes += core::mem::size_of_val(items.as_slice());
+        }
+        let mut seen = BTreeSet::new();
+        usage.walk(root, &mut seen);
+        usage
+    }
+
+    fn walk<T: Clone, const ORD: usize, const LEAF: usize, P: SharedPointerKind, M: Measure<T>>(
+        &mut self,
+        node: &Tree<T, ORD, LEAF, P, M>,
+        seen: &mut BTreeSet<*const Tree<T, ORD, LEAF, P, M>>,
+    ) {
+        for child in node.children().into_iter().flatten() {
+            if !seen.insert(SharedPointer::as_ptr(child)) {
+                continue;
+            }
+            self.nodes += 1;
+            self.node_bytes += node_bytes::<T, ORD, LEAF, P, M>();
+            match child.as_ref() {
+                Tree::Array(items) => {
+                    self.element_bytes += core::mem::size_of_val(items.as_slice())
+                }
+                Tree::Internal(_) => self.walk(child, seen),
+            }
+        }
+    }
+}
+
+/// A summary of the shape of a vector's tree, as returned by [crate::CatVec::tree_stats]. Nodes are counted once per position in the tree, even if they are shared.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TreeStats {
+    /// Number of internal levels above the leaves.
+    pub height: usize,
+    /// Number of nodes, internal and leaf, including the root.
+    pub node_count: usize,
+    /// Number of leaves.
+    pub leaf_count: usize,
+    /// Average fraction of leaf capacity that is in use, from 0 to 1.
+    pub average_leaf_fill: f64,
+}
+
+impl TreeStats {
+    pub(crate) fn compute<
+        T: Clone,
+        const ORD: usize,
+        const LEAF: usize,
+        P: SharedPointerKind,
+        M: Measure<T>,
+    >(
+        root: &Tree<T, ORD, LEAF, P, M>,
+    ) -> Self {
+        let mut node_count = 0;
+        let mut leaf_count = 0;
+        let mut stack = vec![root];
+        while let Some(node) = stack.pop() {
+            node_count += 1;
+            match node.children() {
+                Some(children) => stack.extend(children.iter().map(|c| c.as_ref())),
+                None => leaf_count += 1,
+            }
+        }
+        Self {
+            height: root.height(),
+            node_count,
+            leaf_count,
+            average_leaf_fill: root.len() as f64 / (leaf_count * LEAF) as f64,
+        }
+    }
+}
+
+/// Heap footprint of one node, including the reference counts of its allocation.
+fn node_bytes<
+    T: Clone,
+    const ORD: usize,
+    const LEAF: usize,
+    P: SharedPointerKind,
+    M: Measure<T>,
+>() -> usize {
+    core::mem::size_of::<Tree<T, ORD, LEAF, P, M>>() + 2 * core::mem::size_of::<usize>()
+}
+
+fn collect_ptrs<
+    T: Clone,
+    const ORD: usize,
+    const LEAF: usize,
+    P: SharedPointerKind,
+    M: Measure<T>,
+>(
+    node: &Tree<T, ORD, LEAF, P, M>,
+    out: &mut BTreeSet<*const Tree<T, ORD, LEAF, P, M>>,
+) {
+    for child in node.children().into_iter().flatten() {
+        if out.insert(SharedPointer::as_ptr(child)) {
+            collect_ptrs(child, out);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::CatVec;
+
+    #[test]
+    fn sharing_stats() {
+        let v: CatVec<usize, 4, 4> = (0..100).collect::<Vec<_>>().into();
+        let mut w = v.clone();
+        *w.get_mut(50).unwrap() = 1000;
+        let stats = w.shared_bytes_with(&v);
+        assert!(stats.unique_elements < 10);
+        assert_eq!(stats.shared_elements + stats.unique_elements, 100);
+        let u: CatVec<usize, 4, 4> = (0..100).collect::<Vec<_>>().into();
+        assert_eq!(u.shared_bytes_with(&v).shared_nodes, 0);
+
+        let mut v = v;
+        v.push_front(1000);
+        v.push_back(2000);
+        let mut w = v.clone();
+        let stats = w.shared_bytes_with(&v);
+        assert_eq!(stats.unique_elements, 0);
+        assert_eq!(stats.shared_elements, v.len());
+        w.push_front(3000);
+        let stats = w.shared_bytes_with(&v);
+        assert_eq!(stats.unique_elements, 2);
+        assert_eq!(stats.shared_elements + stats.unique_elements, w.len());
+        w.push_back(4000);
+        let stats = w.shared_bytes_with(&v);
+        assert_eq!(stats.unique_elements, 4);
+        assert_eq!(stats.shared_elements + stats.unique_elements, w.len());
+    }
+
+    #[test]
+    fn memory_usage() {
+        let v: CatVec<usize, 4, 4> = (0..100).collect::<Vec<_>>().into();
+        let usage = v.memory_usage();
+        assert_eq!(usage.element_bytes, 100 * std::mem::size_of::<usize>());
+        assert!(usage.node_bytes > usage.element_bytes);
+    }
+
+    #[test]
+    fn tree_stats() {
+        let v: CatVec<usize, 4, 4> = (0..100).collect::<Vec<_>>().into();
+        let stats = v.tree_stats();
+        assert_eq!(stats.height, v.height());
+        assert!(stats.leaf_count >= 25 && stats.node_count > stats.leaf_count);
+        assert!(stats.average_leaf_fill > 0.5 && stats.average_leaf_fill <= 1.0);
+    }
+}
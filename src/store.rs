@@ -0,0 +1,573 @@
+use alloc::{boxed::Box, vec::Vec};
+use core::{
+    convert::{Infallible, TryFrom},
+    hash::Hash,
+};
+use std::collections::{HashMap, HashSet};
+
+use archery::{SharedPointer, SharedPointerKind};
+use arrayvec::ArrayVec;
+
+use crate::{
+    btree::{Child, Tree},
+    focus::Focus,
+    merkle::{leaf_hash, node_hash},
+    CatVec, Measure,
+};
+
+/// A node of a vector as written to a [NodeStore], under the hash that [CatVec::storage_hash] gives it. Nodes refer to their children by hash, so subtrees shared between vectors, or between versions of one vector, are stored once.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum StoredNode<T> {
+    /// A leaf of the tree, or the head or tail buffer of a vector.
+    Leaf(Vec<T>),
+    /// An internal node of the tree, or the root of a vector, as the length and hash of each child. The root of a vector always has three children: the head buffer, the tree and the tail buffer.
+    Internal(Vec<(usize, [u8; 32])>),
+}
+
+/// A content-addressed key-value store for the nodes of vectors, such as a table in an embedded database or a bucket in an object store. Nodes are never modified once they are written, since a node's hash covers its contents.
+pub trait NodeStore<T> {
+    /// The error the underlying storage fails with.
+    type Error;
+
+    /// Gets the node stored under a hash, if there is one.
+    fn get(&self, hash: &[u8; 32]) -> Result<Option<StoredNode<T>>, Self::Error>;
+
+    /// Stores a node under its hash.
+    fn put(&mut self, hash: [u8; 32], node: StoredNode<T>) -> Result<(), Self::Error>;
+
+    /// Returns true if a node is stored under a hash. Stores that can check for a key without reading its value should override this.
+    fn contains(&self, hash: &[u8; 32]) -> Result<bool, Self::Error> {
+        Ok(self.get(hash)?.is_some())
+    }
+
+    /// The hashes of every node reachable from the given roots, as returned by [CatVec::persist]: the roots themselves and everything under them. Each shared subtree is walked once. Fails if any of them refers to a node that isn't in the store.
+    fn reachable<'r>(
+        &self,
+        roots: impl IntoIterator<Item = &'r [u8; 32]>,
+    ) -> Result<HashSet<[u8; 32]>, LoadError<Self::Error>>
+    where
+        Self: Sized,
+    {
+        let mut seen = HashSet::new();
+        walk(self, roots, |hash, _| {
+            seen.insert(hash);
+            Ok(())
+        })?;
+        Ok(seen)
+    }
+
+    /// Copies every node reachable from the given roots into another store, such as a fresh table that then replaces this one, and returns how many nodes were copied. Nodes already in the other store are kept rather than written again.
+    fn compact_into<'r, D: NodeStore<T, Error = Self::Error>>(
+        &self,
+        roots: impl IntoIterator<Item = &'r [u8; 32]>,
+        into: &mut D,
+    ) -> Result<usize, LoadError<Self::Error>>
+    where
+        Self: Sized,
+    {
+        let mut copied = 0;
+        walk(self, roots, |hash, node| {
+            if !into.contains(&hash)? {
+                into.put(hash, node)?;
+                copied += 1;
+            }
+            Ok(())
+        })?;
+        Ok(copied)
+    }
+}
+
+/// A [NodeStore] that can list and delete its nodes, so that the nodes of versions nobody needs any more can be collected.
+pub trait CollectableStore<T>: NodeStore<T> {
+    /// The hashes of all the nodes in the store.
+    fn hashes(&self) -> Result<Vec<[u8; 32]>, Self::Error>;
+
+    /// Deletes the node stored under a hash, if there is one.
+    fn remove(&mut self, hash: &[u8; 32]) -> Result<(), Self::Error>;
+
+    /// Deletes every node that isn't reachable from the given roots and returns how many were deleted. Every vector that is still needed must be among the roots; its nodes are found before anything is deleted, so a missing node leaves the store untouched.
+    fn collect_garbage<'r>(
+        &mut self,
+        roots: impl IntoIterator<Item = &'r [u8; 32]>,
+    ) -> Result<usize, LoadError<Self::Error>>
+    where
+        Self: Sized,
+    {
+        let live = self.reachable(roots)?;
+        let mut removed = 0;
+        for hash in self.hashes().map_err(LoadError::Store)? {
+            if !live.contains(&hash) {
+                self.remove(&hash).map_err(LoadError::Store)?;
+                removed += 1;
+            }
+        }
+        Ok(removed)
+    }
+}
+
+/// Visits every node reachable from the given roots once, parents before children.
+fn walk<'r, T, S: NodeStore<T>>(
+    store: &S,
+    roots: impl IntoIterator<Item = &'r [u8; 32]>,
+    mut visit: impl FnMut([u8; 32], StoredNode<T>) -> Result<(), S::Error>,
+) -> Result<(), LoadError<S::Error>> {
+    let mut seen = HashSet::new();
+    let mut stack: Vec<[u8; 32]> = roots.into_iter().copied().collect();
+    while let Some(hash) = stack.pop() {
+        if !seen.insert(hash) {
+            continue;
+        }
+        let node = store
+            .get(&hash)
+            .map_err(LoadError::Store)?
+            .ok_or(LoadError::Missing(hash))?;
+        if let StoredNode::Internal(children) = &node {
+            stack.extend(children.iter().map(|&(_, child)| child));
+        }
+        visit(hash, node).map_err(LoadError::Store)?;
+    }
+    Ok(())
+}
+
+/// An in-memory store, which is mostly useful for tests and for staging nodes before writing them out in bulk.
+impl<T: Clone> NodeStore<T> for HashMap<[u8; 32], StoredNode<T>> {
+    type Error = Infallible;
+
+    fn get(&self, hash: &[u8; 32]) -> Result<Option<StoredNode<T>>, Infallible> {
+        Ok(HashMap::get(self, hash).cloned())
+    }
+
+    fn put(&mut self, hash: [u8; 32], node: StoredNode<T>) -> Result<(), Infallible> {
+        self.insert(hash, node);
+        Ok(())
+    }
+
+    fn contains(&self, hash: &[u8; 32]) -> Result<bool, Infallible> {
+        Ok(self.contains_key(hash))
+    }
+}
+
+impl<T: Clone> CollectableStore<T> for HashMap<[u8; 32], StoredNode<T>> {
+    fn hashes(&self) -> Result<Vec<[u8; 32]>, Infallible> {
+        Ok(self.keys().copied().collect())
+    }
+
+    fn remove(&mut self, hash: &[u8; 32]) -> Result<(), Infallible> {
+        HashMap::remove(self, hash);
+        Ok(())
+    }
+}
+
+/// An error loading a vector from a [NodeStore].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum LoadError<E> {
+    /// The store failed.
+    Store(E),
+    /// A node that the vector refers to isn't in the store.
+    Missing([u8; 32]),
+    /// A node doesn't match the hash it is stored under, or the nodes don't make up a valid tree for a vector with these parameters.
+    Corrupt([u8; 32]),
+}
+
+impl<E: core::fmt::Display> core::fmt::Display for LoadError<E> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let hex = |f: &mut core::fmt::Formatter<'_>, hash: &[u8; 32]| {
+            hash.iter().try_for_each(|b| write!(f, "{:02x}", b))
+        };
+        match self {
+            LoadError::Store(e) => write!(f, "node store failed: {}", e),
+            LoadError::Missing(hash) => {
+                write!(f, "node ")?;
+                hex(f, hash)?;
+                write!(f, " is missing from the store")
+            }
+            LoadError::Corrupt(hash) => {
+                write!(f, "node ")?;
+                hex(f, hash)?;
+                write!(f, " is corrupt")
+            }
+        }
+    }
+}
+
+impl<E: core::fmt::Debug + core::fmt::Display> std::error::Error for LoadError<E> {}
+
+impl<T: Clone + Hash, const ORD: usize, const LEAF: usize, P: SharedPointerKind, M: Measure<T>>
+    CatVec<T, ORD, LEAF, P, M>
+{
+    /// Writes the nodes of the vector to a store and returns the [CatVec::storage_hash] it can be loaded from. Nodes come before the nodes that refer to them, and a subtree whose root is already in the store is skipped, so persisting a new version of a vector only writes the nodes that changed.
+    pub fn persist<S: NodeStore<T>>(&self, store: &mut S) -> Result<[u8; 32], S::Error> {
+        let head = persist_leaf(&self.head, store)?;
+        persist_tree(&self.inner, store)?;
+        let tail = persist_leaf(&self.tail, store)?;
+        let pieces = Vec::from([
+            (self.head.len(), head),
+            (self.inner.len(), self.inner.hash()),
+            (self.tail.len(), tail),
+        ]);
+        let root = node_hash(pieces.iter().copied());
+        if !store.contains(&root)? {
+            store.put(root, StoredNode::Internal(pieces))?;
+        }
+        Ok(root)
+    }
+
+    /// Reads a vector back from a store, given the storage hash returned by [CatVec::persist]. Every node is checked against its hash, and the whole tree against the invariants, so a store can't make this return a vector with a different storage hash. Subtrees stored once are loaded once and shared.
+    pub fn load<S: NodeStore<T>>(root: &[u8; 32], store: &S) -> Result<Self, LoadError<S::Error>> {
+        let mut loader = Loader::new(store);
+        let pieces = loader.pieces(root)?;
+        let head = loader.leaf(&pieces[0])?;
+        let tail = loader.leaf(&pieces[2])?;
+        let inner = loader.subtree(&pieces[1])?;
+        Ok(CatVec {
+            inner: Box::new(inner),
+            head: SharedPointer::new(head),
+            tail: SharedPointer::new(tail),
+            focus: Focus::new(),
+        })
+    }
+}
+
+fn persist_leaf<T: Clone + Hash, S: NodeStore<T>>(
+    items: &[T],
+    store: &mut S,
+) -> Result<[u8; 32], S::Error> {
+    let hash = leaf_hash(items);
+    if !store.contains(&hash)? {
+        store.put(hash, StoredNode::Leaf(items.to_vec()))?;
+    }
+    Ok(hash)
+}
+
+/// Writes the nodes of a tree that aren't in the store yet, children first.
+fn persist_tree<
+    T: Clone + Hash,
+    const ORD: usize,
+    const LEAF: usize,
+    P: SharedPointerKind,
+    M: Measure<T>,
+    S: NodeStore<T>,
+>(
+    tree: &Tree<T, ORD, LEAF, P, M>,
+    store: &mut S,
+) -> Result<(), S::Error> {
+    let children = match tree {
+        Tree::Array(items) => return persist_leaf(items, store).map(drop),
+        Tree::Internal(_) => tree.children().unwrap(),
+    };
+    let hash = tree.hash();
+    if store.contains(&hash)? {
+        return Ok(());
+    }
+    for child in children {
+        persist_tree(child, store)?;
+    }
+    let children = children.iter().map(|c| (c.len(), c.hash())).collect();
+    store.put(hash, StoredNode::Internal(children))
+}
+
+/// The length and hash of each child of a stored node.
+pub(crate) type Links = Vec<(usize, [u8; 32])>;
+
+/// A node fetched by [Loader::open].
+pub(crate) enum Opened<
+    T: Clone,
+    const ORD: usize,
+    const LEAF: usize,
+    P: SharedPointerKind,
+    M: Measure<T>,
+> {
+    /// The length and hash of each child of an internal node.
+    Internal(Links),
+    /// A node that was loaded whole.
+    Loaded(Child<T, ORD, LEAF, P, M>),
+}
+
+/// Rebuilds trees from a store, keeping the nodes it has loaded so far so that shared subtrees stay shared.
+pub(crate) struct Loader<
+    'a,
+    T: Clone,
+    const ORD: usize,
+    const LEAF: usize,
+    P: SharedPointerKind,
+    M: Measure<T>,
+    S,
+> {
+    store: &'a S,
+    pub(crate) loaded: HashMap<[u8; 32], Child<T, ORD, LEAF, P, M>>,
+}
+
+impl<
+        'a,
+        T: Clone + Hash,
+        const ORD: usize,
+        const LEAF: usize,
+        P: SharedPointerKind,
+        M: Measure<T>,
+        S: NodeStore<T>,
+    > Loader<'a, T, ORD, LEAF, P, M, S>
+{
+    pub(crate) fn new(store: &'a S) -> Self {
+        Self {
+            store,
+            loaded: HashMap::new(),
+        }
+    }
+
+    pub(crate) fn fetch(&self, hash: &[u8; 32]) -> Result<StoredNode<T>, LoadError<S::Error>> {
+        self.store
+            .get(hash)
+            .map_err(LoadError::Store)?
+            .ok_or(LoadError::Missing(*hash))
+    }
+
+    /// Fetches the root of a vector, which lists the length and hash of its head buffer, tree and tail buffer.
+    pub(crate) fn pieces(&self, root: &[u8; 32]) -> Result<Links, LoadError<S::Error>> {
+        match self.fetch(root)? {
+            StoredNode::Internal(pieces)
+                if pieces.len() == 3 && node_hash(pieces.iter().copied()) == *root =>
+            {
+                Ok(pieces)
+            }
+            _ => Err(LoadError::Corrupt(*root)),
+        }
+    }
+
+    /// Fetches a node of the tree without loading its children: a leaf is loaded and kept like any other, while an internal node is returned as the length and hash of each child.
+    pub(crate) fn open(
+        &mut self,
+        hash: &[u8; 32],
+    ) -> Result<Opened<T, ORD, LEAF, P, M>, LoadError<S::Error>> {
+        if let Some(node) = self.loaded.get(hash) {
+            return Ok(Opened::Loaded(node.clone()));
+        }
+        match self.fetch(hash)? {
+            StoredNode::Internal(children) if node_hash(children.iter().copied()) == *hash => {
+                Ok(Opened::Internal(children))
+            }
+            StoredNode::Internal(_) => Err(LoadError::Corrupt(*hash)),
+            StoredNode::Leaf(_) => self.child(hash, 0).map(Opened::Loaded),
+        }
+    }
+
+    /// Loads a head or tail buffer.
+    pub(crate) fn leaf(
+        &self,
+        &(len, hash): &(usize, [u8; 32]),
+    ) -> Result<ArrayVec<T, LEAF>, LoadError<S::Error>> {
+        match self.fetch(&hash)? {
+            StoredNode::Leaf(items) if items.len() == len && leaf_hash(&items) == hash => {
+                ArrayVec::try_from(items.as_slice()).map_err(|_| LoadError::Corrupt(hash))
+            }
+            _ => Err(LoadError::Corrupt(hash)),
+        }
+    }
+
+    /// Loads a whole tree that can stand on its own, such as the tree of a vector, checking that it has the given length and satisfies the invariants.
+    pub(crate) fn subtree(
+        &mut self,
+        &(len, hash): &(usize, [u8; 32]),
+    ) -> Result<Tree<T, ORD, LEAF, P, M>, LoadError<S::Error>> {
+        let tree = self.node(&hash, true, max_height::<ORD>(len))?;
+        if tree.len() != len || tree.validate(&mut Vec::new()).is_err() {
+            return Err(LoadError::Corrupt(hash));
+        }
+        Ok(tree)
+    }
+
+    /// Loads a node of the tree and everything under it, which may be at most `height` levels tall. The node is checked against its hash before any of its children are loaded, so a store can't make this follow links that the hash doesn't commit to.
+    fn node(
+        &mut self,
+        hash: &[u8; 32],
+        root: bool,
+        height: usize,
+    ) -> Result<Tree<T, ORD, LEAF, P, M>, LoadError<S::Error>> {
+        let corrupt = || LoadError::Corrupt(*hash);
+        let node = match self.fetch(hash)? {
+            StoredNode::Leaf(items) => {
+                if leaf_hash(&items) != *hash {
+                    return Err(corrupt());
+                }
+                Tree::Array(ArrayVec::try_from(items.as_slice()).map_err(|_| corrupt())?)
+            }
+            StoredNode::Internal(children) => {
+                if children.is_empty()
+                    || children.len() > ORD
+                    || height == 0
+                    || node_hash(children.iter().copied()) != *hash
+                {
+                    return Err(corrupt());
+                }
+                let children = children
+                    .iter()
+                    .map(|(len, child)| match self.child(child, height - 1)? {
+                        c if c.len() == *len => Ok(c),
+                        _ => Err(corrupt()),
+                    })
+                    .collect::<Result<_, _>>()?;
+                Tree::from_children(children, root)
+            }
+        };
+        if node.hash() != *hash {
+            return Err(corrupt());
+        }
+        Ok(node)
+    }
+
+    /// Loads a node below the top of the tree, at most `height` levels tall, or shares it if it was loaded before.
+    pub(crate) fn child(
+        &mut self,
+        hash: &[u8; 32],
+        height: usize,
+    ) -> Result<Child<T, ORD, LEAF, P, M>, LoadError<S::Error>> {
+        if let Some(node) = self.loaded.get(hash) {
+            if node.height() > height {
+                return Err(LoadError::Corrupt(*hash));
+            }
+            return Ok(node.clone());
+        }
+        let node = SharedPointer::new(self.node(hash, false, height)?);
+        self.loaded.insert(*hash, node.clone());
+        Ok(node)
+    }
+}
+
+/// The most levels of internal nodes that a stored tree of `len` elements may have, which bounds how deep loading it recurses. For orders of 4 or more, every internal node below the root has at least two children and every leaf at least one element, so each level at least doubles the length and a taller tree is corrupt. Smaller orders allow chains of single-child nodes, which the length doesn't bound, so they get a fixed limit instead, low enough that loading stays within the stack of a spawned thread; a vector of such an order whose tree grew taller than that can be persisted but not loaded.
+pub(crate) fn max_height<const ORD: usize>(len: usize) -> usize {
+    if ORD >= 4 {
+        (usize::BITS - len.leading_zeros()) as usize + 1
+    } else {
+        256
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::{CollectableStore, LoadError, NodeStore, StoredNode};
+    use crate::{
+        merkle::{leaf_hash, node_hash},
+        CatVec, StoredCatVec,
+    };
+
+    #[test]
+    fn persist_and_load() {
+        let mut store: HashMap<[u8; 32], StoredNode<u64>> = HashMap::new();
+        let mut v: CatVec<u64, 4, 4> = (0..1000).collect::<Vec<_>>().into();
+        v.push_front(5);
+        v.push_back(6);
+        let root = v.persist(&mut store).unwrap();
+        assert_eq!(root, v.storage_hash());
+        let nodes = store.len();
+        let loaded: CatVec<u64, 4, 4> = CatVec::load(&root, &store).unwrap();
+        loaded.check_invariants();
+        assert_eq!(loaded, v);
+        assert_eq!(loaded.storage_hash(), root);
+        assert_eq!(loaded.root_hash(), v.root_hash());
+
+        // a new version only adds the nodes along the edited path
+        *v.get_mut(500).unwrap() = 7;
+        let new_root = v.persist(&mut store).unwrap();
+        assert!(store.len() - nodes <= v.tree_stats().height + 2);
+        assert_eq!(CatVec::<u64, 4, 4>::load(&new_root, &store).unwrap(), v);
+        assert_eq!(CatVec::<u64, 4, 4>::load(&root, &store).unwrap(), loaded);
+        assert_eq!(
+            CatVec::<u64, 4, 4>::new().persist(&mut store).unwrap(),
+            CatVec::<u64, 4, 4>::new().storage_hash()
+        );
+
+        assert_eq!(
+            CatVec::<u64, 4, 4>::load(&[0; 32], &store),
+            Err(LoadError::Missing([0; 32]))
+        );
+        let (&leaf, _) = store
+            .iter()
+            .find(|(_, node)| matches!(node, StoredNode::Leaf(items) if items.len() == 4))
+            .unwrap();
+        store.put(leaf, StoredNode::Leaf(vec![1, 2, 3, 4])).unwrap();
+        assert!(CatVec::<u64, 4, 4>::load(&root, &store).is_err());
+        // different parameters make the same nodes an invalid tree
+        store.clear();
+        let root = v.persist(&mut store).unwrap();
+        assert!(CatVec::<u64, 3, 4>::load(&root, &store).is_err());
+    }
+
+    #[test]
+    fn self_referencing_node() {
+        let mut store: HashMap<[u8; 32], StoredNode<u64>> = HashMap::new();
+        let empty = leaf_hash::<u64>(&[]);
+        store.put(empty, StoredNode::Leaf(Vec::new())).unwrap();
+        let vector_root = |store: &mut HashMap<_, _>, tree: (usize, [u8; 32])| {
+            let pieces = vec![(0, empty), tree, (0, empty)];
+            let root = node_hash(pieces.iter().copied());
+            store.put(root, StoredNode::Internal(pieces)).unwrap();
+            root
+        };
+
+        // a node listing itself as its own child
+        let cycle = [7; 32];
+        store
+            .put(cycle, StoredNode::Internal(vec![(5, cycle)]))
+            .unwrap();
+        let root = vector_root(&mut store, (5, cycle));
+        assert_eq!(
+            CatVec::<u64, 4, 4>::load(&root, &store),
+            Err(LoadError::Corrupt(cycle))
+        );
+        let stored = StoredCatVec::<u64, _, 4, 4>::open(&root, store.clone()).unwrap();
+        assert_eq!(stored.get(2), Err(LoadError::Corrupt(cycle)));
+        assert!(stored.slice(1..3).is_err());
+        assert!(stored.slice(..).is_err());
+
+        // a correctly hashed chain of single-child nodes, far taller than its length allows
+        let leaf = leaf_hash(&[1u64]);
+        store.put(leaf, StoredNode::Leaf(vec![1])).unwrap();
+        let mut top = leaf;
+        for _ in 0..100_000 {
+            let links = vec![(1, top)];
+            top = node_hash(links.iter().copied());
+            store.put(top, StoredNode::Internal(links)).unwrap();
+        }
+        let root = vector_root(&mut store, (1, top));
+        assert!(matches!(
+            CatVec::<u64, 4, 4>::load(&root, &store),
+            Err(LoadError::Corrupt(_))
+        ));
+        assert!(CatVec::<u64, 2, 4>::load(&root, &store).is_err());
+    }
+
+    #[test]
+    fn garbage_collection() {
+        let mut store: HashMap<[u8; 32], StoredNode<u64>> = HashMap::new();
+        let mut v: CatVec<u64, 4, 4> = (0..1000).collect::<Vec<_>>().into();
+        let mut roots = Vec::new();
+        for i in 0..10 {
+            *v.get_mut(i * 97).unwrap() = 5000 + i as u64;
+            roots.push(v.persist(&mut store).unwrap());
+        }
+        let everything = store.len();
+        let keep = [roots[3], roots[9]];
+        let live = store.reachable(&keep).unwrap();
+        assert!(live.len() < everything);
+
+        let mut copy = HashMap::new();
+        assert_eq!(store.compact_into(&keep, &mut copy).unwrap(), live.len());
+        assert_eq!(
+            store.collect_garbage(&keep).unwrap(),
+            everything - live.len()
+        );
+        assert_eq!(store, copy);
+        for root in keep {
+            let loaded = CatVec::<u64, 4, 4>::load(&root, &store).unwrap();
+            assert_eq!(loaded.storage_hash(), root);
+        }
+        assert!(CatVec::<u64, 4, 4>::load(&roots[0], &store).is_err());
+        assert_eq!(
+            store.collect_garbage(&[[0; 32]]),
+            Err(LoadError::Missing([0; 32]))
+        );
+        assert_eq!(store.len(), live.len());
+    }
+}
@@ -0,0 +1,298 @@
+use alloc::boxed::Box;
+use core::{hash::Hash, ops::RangeBounds};
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    sync::Mutex,
+};
+
+use archery::{ArcK, SharedPointerKind};
+use arrayvec::ArrayVec;
+
+use crate::{
+    btree::{Child, Tree},
+    focus::Focus,
+    range_bounds,
+    store::{max_height, Links, Loader, Opened},
+    CatVec, LoadError, Measure, NodeStore,
+};
+
+/// A vector persisted in a [NodeStore] that is read without loading it: nodes are only fetched when a lookup or a slice first reaches them, and are kept for later accesses. This makes it possible to work with vectors far bigger than memory, as long as the part actually touched fits.
+///
+/// Reading from the vector may fail, since the store can. Every node is checked against its hash when it is fetched, as in [CatVec::load]. Editing is done by taking a [StoredCatVec::slice] of the part to change, which is an ordinary [CatVec] that shares the loaded nodes.
+pub struct StoredCatVec<
+    T: Clone,
+    S,
+    const ORD: usize,
+    const LEAF: usize = 32,
+    P: SharedPointerKind = ArcK,
+    M: Measure<T> = (),
+> {
+    store: S,
+    root: [u8; 32],
+    /// The length and hash of the head buffer, the tree and the tail buffer.
+    pieces: Links,
+    head: ArrayVec<T, LEAF>,
+    tail: ArrayVec<T, LEAF>,
+    cache: Mutex<Cache<T, ORD, LEAF, P, M>>,
+}
+
+/// The nodes fetched so far.
+struct Cache<T: Clone, const ORD: usize, const LEAF: usize, P: SharedPointerKind, M: Measure<T>> {
+    /// Internal nodes passed through on the way to the parts that were read, as the length and hash of each child.
+    internal: HashMap<[u8; 32], Links>,
+    /// Subtrees loaded in full, including every leaf that was read.
+    loaded: HashMap<[u8; 32], Child<T, ORD, LEAF, P, M>>,
+}
+
+impl<
+        T: Clone + Hash,
+        S: NodeStore<T>,
+        const ORD: usize,
+        const LEAF: usize,
+        P: SharedPointerKind,
+        M: Measure<T>,
+    > StoredCatVec<T, S, ORD, LEAF, P, M>
+{
+    /// Opens the vector with the given storage hash, as returned by [CatVec::persist]. Only the root and the head and tail buffers are fetched.
+    pub fn open(root: &[u8; 32], store: S) -> Result<Self, LoadError<S::Error>> {
+        let loader = Loader::<T, ORD, LEAF, P, M, S>::new(&store);
+        let pieces = loader.pieces(root)?;
+        let head = loader.leaf(&pieces[0])?;
+        let tail = loader.leaf(&pieces[2])?;
+        Ok(Self {
+            store,
+            root: *root,
+            pieces,
+            head,
+            tail,
+            cache: Mutex::new(Cache {
+                internal: HashMap::new(),
+                loaded: HashMap::new(),
+            }),
+        })
+    }
+
+    /// The storage hash the vector was opened with.
+    pub fn storage_hash(&self) -> [u8; 32] {
+        self.root
+    }
+
+    /// The store the vector is read from.
+    pub fn store(&self) -> &S {
+        &self.store
+    }
+
+    /// Length of the vector.
+    pub fn len(&self) -> usize {
+        self.pieces.iter().map(|&(len, _)| len).sum()
+    }
+
+    /// Returns true if the vector is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Forgets the nodes fetched so far, so that their memory is freed once no slices share them.
+    pub fn clear_cache(&self) {
+        let mut cache = self.cache.lock().unwrap();
+        cache.internal.clear();
+        cache.loaded.clear();
+    }
+
+    /// Gets a copy of the element at a particular position, fetching the nodes on the path to it that haven't been fetched yet.
+    pub fn get(&self, i: usize) -> Result<Option<T>, LoadError<S::Error>> {
+        let (head, (tree_len, mut hash)) = (self.head.len(), self.pieces[1]);
+        if i < head {
+            return Ok(Some(self.head[i].clone()));
+        }
+        let mut i = i - head;
+        if i >= tree_len {
+            return Ok(self.tail.get(i - tree_len).cloned());
+        }
+        let mut cache = self.cache.lock().unwrap();
+        let mut loader = Loader::new(&self.store);
+        loader.loaded = core::mem::take(&mut cache.loaded);
+        let result = (|| loop {
+            let children = match cache.internal.entry(hash) {
+                Entry::Occupied(entry) => entry.into_mut(),
+                Entry::Vacant(entry) => match loader.open(&hash)? {
+                    Opened::Loaded(node) => return Ok(node.get(i).cloned()),
+                    Opened::Internal(children) => entry.insert(children),
+                },
+            };
+            let mut offset = 0;
+            let &(len, child) = children
+                .iter()
+                .find(|&&(len, _)| {
+                    offset += len;
+                    offset > i
+                })
+                .ok_or(LoadError::Corrupt(hash))?;
+            i -= offset - len;
+            hash = child;
+        })();
+        cache.loaded = core::mem::take(&mut loader.loaded);
+        result
+    }
+
+    /// Loads a range of elements as a [CatVec], fetching only the subtrees that overlap it. Subtrees that lie entirely inside the range are shared with the cache, rather than copied, and so are shared with other slices covering them. Panics if the range is out of bounds.
+    pub fn slice(
+        &self,
+        range: impl RangeBounds<usize>,
+    ) -> Result<CatVec<T, ORD, LEAF, P, M>, LoadError<S::Error>> {
+        let (start, end) = range_bounds(range, self.len());
+        assert!(
+            start <= end && end <= self.len(),
+            "range {}..{} out of bounds for a vector of length {}",
+            start,
+            end,
+            self.len()
+        );
+        let (head, tree_len) = (self.head.len(), self.pieces[1].0);
+        let clamp = |lo: usize, hi: usize| (start.clamp(lo, hi) - lo, end.clamp(lo, hi) - lo);
+        let mut out: CatVec<T, ORD, LEAF, P, M> = CatVec::new();
+        let (s, e) = clamp(0, head);
+        out.append(CatVec::from(&self.head[s..e]));
+        let (s, e) = clamp(head, head + tree_len);
+        if s < e {
+            let mut cache = self.cache.lock().unwrap();
+            let mut loader = Loader::new(&self.store);
+            loader.loaded = core::mem::take(&mut cache.loaded);
+            let height = max_height::<ORD>(tree_len);
+            let result = slice_tree(&mut loader, self.pieces[1], s, e, height, &mut out);
+            cache.loaded = core::mem::take(&mut loader.loaded);
+            result?;
+        }
+        let (s, e) = clamp(head + tree_len, self.len());
+        out.append(CatVec::from(&self.tail[s..e]));
+        Ok(out)
+    }
+}
+
+/// Appends the elements in `start..end` of a stored subtree, at most `height` levels tall, to `out`. Subtrees inside the range are loaded whole and appended in O(log n) time; only the nodes along the two ends of the range are taken apart.
+fn slice_tree<
+    T: Clone + Hash,
+    const ORD: usize,
+    const LEAF: usize,
+    P: SharedPointerKind,
+    M: Measure<T>,
+    S: NodeStore<T>,
+>(
+    loader: &mut Loader<'_, T, ORD, LEAF, P, M, S>,
+    (len, hash): (usize, [u8; 32]),
+    start: usize,
+    end: usize,
+    height: usize,
+    out: &mut CatVec<T, ORD, LEAF, P, M>,
+) -> Result<(), LoadError<S::Error>> {
+    if start == 0 && end == len {
+        let tree = loader.subtree(&(len, hash))?;
+        out.append(CatVec {
+            inner: Box::new(tree),
+            head: Default::default(),
+            tail: Default::default(),
+            focus: Focus::new(),
+        });
+        return Ok(());
+    }
+    let children = match loader.open(&hash)? {
+        Opened::Internal(children) => children,
+        Opened::Loaded(node) => match &*node {
+            Tree::Array(items) if items.len() == len => {
+                out.append(CatVec::from(&items[start..end]));
+                return Ok(());
+            }
+            _ => return Err(LoadError::Corrupt(hash)),
+        },
+    };
+    if height == 0 || children.iter().map(|&(len, _)| len).sum::<usize>() != len {
+        return Err(LoadError::Corrupt(hash));
+    }
+    let mut offset = 0;
+    for (child_len, child) in children {
+        let (child_start, child_end) = (offset, offset + child_len);
+        offset = child_end;
+        if start < child_end && end > child_start {
+            slice_tree(
+                loader,
+                (child_len, child),
+                start.max(child_start) - child_start,
+                end.min(child_end) - child_start,
+                height - 1,
+                out,
+            )?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::StoredCatVec;
+    use crate::{CatVec, LoadError, NodeStore, StoredNode};
+
+    /// Counts the nodes fetched from an in-memory store.
+    struct CountingStore {
+        nodes: HashMap<[u8; 32], StoredNode<u64>>,
+        fetches: std::cell::Cell<usize>,
+    }
+
+    impl NodeStore<u64> for CountingStore {
+        type Error = core::convert::Infallible;
+
+        fn get(&self, hash: &[u8; 32]) -> Result<Option<StoredNode<u64>>, Self::Error> {
+            self.fetches.set(self.fetches.get() + 1);
+            NodeStore::get(&self.nodes, hash)
+        }
+
+        fn put(&mut self, hash: [u8; 32], node: StoredNode<u64>) -> Result<(), Self::Error> {
+            self.nodes.put(hash, node)
+        }
+    }
+
+    #[test]
+    fn lazy_reads() {
+        let mut store = CountingStore {
+            nodes: HashMap::new(),
+            fetches: Default::default(),
+        };
+        let mut v: CatVec<u64, 4, 4> = (0..10000).collect::<Vec<_>>().into();
+        v.push_front(7);
+        v.push_back(8);
+        let root = v.persist(&mut store).unwrap();
+        store.fetches.set(0);
+        let stored: StoredCatVec<u64, _, 4, 4> = StoredCatVec::open(&root, store).unwrap();
+        assert_eq!(stored.len(), v.len());
+        assert_eq!(stored.get(5000).unwrap(), v.get(5000).copied());
+        let fetched = stored.store().fetches.get();
+        assert!(fetched < 15);
+        assert_eq!(stored.get(4999).unwrap(), v.get(4999).copied());
+        assert_eq!(stored.store().fetches.get(), fetched);
+        assert_eq!(stored.get(0).unwrap(), Some(7));
+        assert_eq!(stored.get(v.len() - 1).unwrap(), Some(8));
+        assert_eq!(stored.get(v.len()).unwrap(), None);
+
+        for (start, end) in [
+            (0, 0),
+            (0, 1),
+            (3, 700),
+            (4000, 4100),
+            (9990, v.len()),
+            (0, v.len()),
+        ] {
+            let slice = stored.slice(start..end).unwrap();
+            slice.check_invariants();
+            assert!(slice.iter().eq(v.iter().skip(start).take(end - start)));
+        }
+        stored.clear_cache();
+        let before = stored.store().fetches.get();
+        stored.slice(100..200).unwrap();
+        assert!(stored.store().fetches.get() - before < 60);
+
+        let missing: Result<StoredCatVec<u64, _, 4, 4>, _> =
+            StoredCatVec::open(&[1; 32], HashMap::<[u8; 32], StoredNode<u64>>::new());
+        assert!(matches!(missing, Err(LoadError::Missing(_))));
+    }
+}
@@ -0,0 +1,483 @@
+use alloc::string::String;
+use core::{
+    fmt::Write,
+    ops::{ControlFlow, RangeBounds},
+};
+
+use archery::{ArcK, SharedPointerKind};
+
+use crate::{range_bounds, zip_leaves, CatBytes, CatVec, TextMetrics};
+
+/// A persistent rope: a string stored as a [CatBytes] that is always valid UTF-8. Concatenating, slicing, inserting and removing text take O(log n) time plus the length of any new text, and clones share all their structure.
+///
+/// Positions are byte offsets, as with [str], and must fall on character boundaries; methods panic otherwise. The tree keeps count of the characters and line breaks under every node, so converting between byte offsets and character or line numbers takes O(log n) time as well.
+pub struct CatString<const ORD: usize = 32, const LEAF: usize = 256, P: SharedPointerKind = ArcK> {
+    bytes: CatBytes<ORD, LEAF, P, TextMetrics>,
+}
+
+impl<const ORD: usize, const LEAF: usize, P: SharedPointerKind> Clone for CatString<ORD, LEAF, P> {
+    fn clone(&self) -> Self {
+        Self {
+            bytes: self.bytes.clone(),
+        }
+    }
+}
+
+impl<const ORD: usize, const LEAF: usize, P: SharedPointerKind> CatString<ORD, LEAF, P> {
+    /// Creates an empty string.
+    pub fn new() -> Self {
+        Self {
+            bytes: CatVec::new(),
+        }
+    }
+
+    /// The underlying bytes, which are valid UTF-8.
+    pub fn as_bytes(&self) -> &CatBytes<ORD, LEAF, P, TextMetrics> {
+        &self.bytes
+    }
+
+    /// Length of the string in bytes.
+    pub fn len(&self) -> usize {
+        self.bytes.len()
+    }
+
+    /// Returns true if the string is empty.
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    /// Returns true if the given byte offset is the start of a character or the end of the string.
+    pub fn is_char_boundary(&self, idx: usize) -> bool {
+        match self.bytes.get(idx) {
+            // continuation bytes look like 0b10xxxxxx
+            Some(&b) => (b as i8) >= -0x40,
+            None => idx == self.len(),
+        }
+    }
+
+    fn assert_char_boundary(&self, idx: usize) {
+        assert!(
+            self.is_char_boundary(idx),
+            "byte index {} is not a char boundary of a string of length {}",
+            idx,
+            self.len()
+        );
+    }
+
+    /// Resolves a range of byte offsets, checking that both ends are character boundaries.
+    fn char_range(&self, range: impl RangeBounds<usize>) -> (usize, usize) {
+        let (start, end) = range_bounds(range, self.len());
+        assert!(
+            start <= end,
+            "range starts at {} but ends at {}",
+            start,
+            end
+        );
+        self.assert_char_boundary(start);
+        self.assert_char_boundary(end);
+        (start, end)
+    }
+
+    /// Appends a string slice.
+    pub fn push_str(&mut self, s: &str) {
+        self.bytes.extend_from_slice(s.as_bytes())
+    }
+
+    /// Appends a character.
+    pub fn push(&mut self, c: char) {
+        self.push_str(c.encode_utf8(&mut [0; 4]))
+    }
+
+    /// Inserts a string slice at a byte offset, by splitting the rope there and joining it back around the new text.
+    pub fn insert_str(&mut self, idx: usize, s: &str) {
+        self.assert_char_boundary(idx);
+        let mut rest = self.bytes.clone();
+        rest.slice_into(idx..);
+        self.bytes.slice_into(..idx);
+        self.bytes.extend_from_slice(s.as_bytes());
+        self.bytes.append(rest);
+    }
+
+    /// Removes a range of bytes.
+    pub fn remove_range(&mut self, range: impl RangeBounds<usize>) {
+        let (start, end) = self.char_range(range);
+        let mut rest = self.bytes.clone();
+        rest.slice_into(end..);
+        self.bytes.slice_into(..start);
+        self.bytes.append(rest);
+    }
+
+    /// Returns a range of bytes as a new string, sharing structure with this one.
+    pub fn slice(&self, range: impl RangeBounds<usize>) -> Self {
+        let (start, end) = self.char_range(range);
+        let mut bytes = self.bytes.clone();
+        bytes.slice_into(start..end);
+        Self { bytes }
+    }
+
+    /// Concatenates another string onto this one in O(log n) time.
+    pub fn append(&mut self, other: Self) {
+        self.bytes.append(other.bytes)
+    }
+
+    /// Iterates over the characters.
+    pub fn chars(&self) -> impl Iterator<Item = char> + '_ {
+        let mut bytes = self.bytes.iter();
+        core::iter::from_fn(move || decode_char(&mut bytes))
+    }
+
+    /// Iterates over the characters along with the byte offsets where they start.
+    pub fn char_indices(&self) -> impl Iterator<Item = (usize, char)> + '_ {
+        let mut bytes = self.bytes.iter();
+        let mut pos = 0;
+        core::iter::from_fn(move || {
+            let c = decode_char(&mut bytes)?;
+            let start = pos;
+            pos += c.len_utf8();
+            Some((start, c))
+        })
+    }
+}
+
+impl<const ORD: usize, const LEAF: usize, P: SharedPointerKind> CatString<ORD, LEAF, P> {
+    /// The number of characters.
+    pub fn char_count(&self) -> usize {
+        self.bytes.measure_prefix(self.len()).chars
+    }
+
+    /// The byte offset where the character with the given index starts, or the length of the string if the index is the number of characters. Returns None if the index is past that.
+    pub fn char_to_byte(&self, char_idx: usize) -> Option<usize> {
+        self.bytes
+            .find_by_measure(|s| s.chars > char_idx)
+            .or_else(|| (char_idx == self.char_count()).then(|| self.len()))
+    }
+
+    /// The index of the character that starts at the given byte offset, or the number of characters if the offset is the length of the string. Panics if the offset isn't a character boundary.
+    pub fn byte_to_char(&self, offset: usize) -> usize {
+        self.assert_char_boundary(offset);
+        self.bytes.measure_prefix(offset).chars
+    }
+
+    /// Returns a range of characters, by index rather than byte offset, as a new string sharing structure with this one. Panics if the range is out of bounds.
+    pub fn slice_chars(&self, range: impl RangeBounds<usize>) -> Self {
+        let chars = self.char_count();
+        let (start, end) = range_bounds(range, chars);
+        assert!(
+            start <= end && end <= chars,
+            "char range {}..{} out of bounds for a string of {} chars",
+            start,
+            end,
+            chars
+        );
+        let to_byte = |i| self.char_to_byte(i).unwrap();
+        self.slice(to_byte(start)..to_byte(end))
+    }
+}
+
+impl<const ORD: usize, const LEAF: usize, P: SharedPointerKind> CatString<ORD, LEAF, P> {
+    /// The number of lines, which is one more than the number of line breaks: a final line counts even if it is empty.
+    pub fn line_count(&self) -> usize {
+        self.bytes.measure_prefix(self.len()).line_breaks + 1
+    }
+
+    /// The byte offset where the given line starts, counting from zero, or None if there is no such line.
+    pub fn line_to_byte(&self, line: usize) -> Option<usize> {
+        if line == 0 {
+            return Some(0);
+        }
+        let newline = self.bytes.find_by_measure(|s| s.line_breaks >= line)?;
+        Some(newline + 1)
+    }
+
+    /// The line containing the given byte offset, counting from zero. An offset just past a line break is on the next line. Panics if the offset is out of bounds.
+    pub fn byte_to_line(&self, offset: usize) -> usize {
+        self.bytes.measure_prefix(offset).line_breaks
+    }
+
+    /// Iterates over the lines, like [str::lines]: lines end with `\n` or `\r\n`, which isn't included, and a final empty line is left out. Each line shares structure with this string.
+    pub fn lines(&self) -> impl Iterator<Item = Self> + '_ {
+        let mut line = 0;
+        core::iter::from_fn(move || {
+            let start = self.line_to_byte(line)?;
+            let end = match self.line_to_byte(line + 1) {
+                // A `\r` is only part of the line ending if a `\n` follows it.
+                Some(next) if next - 1 > start && self.bytes.get(next - 2) == Some(&b'\r') => {
+                    next - 2
+                }
+                Some(next) => next - 1,
+                None if start == self.len() => return None,
+                None => self.len(),
+            };
+            line += 1;
+            Some(self.slice(start..end))
+        })
+    }
+}
+
+#[cfg(feature = "unicode-segmentation")]
+impl<const ORD: usize, const LEAF: usize, P: SharedPointerKind> CatString<ORD, LEAF, P> {
+    /// Iterates over the extended grapheme clusters, the units a user perceives as single characters, along with the byte offsets where they start.
+    pub fn grapheme_indices(&self) -> impl Iterator<Item = (usize, String)> + '_ {
+        use unicode_segmentation::UnicodeSegmentation;
+        let mut chars = self.chars();
+        let mut pending = String::new();
+        let mut pos = 0;
+        core::iter::from_fn(move || {
+            // A boundary only depends on the text since the previous one and the character right after it, so the first cluster is complete once another one starts.
+            while pending.graphemes(true).nth(1).is_none() {
+                match chars.next() {
+                    Some(c) => pending.push(c),
+                    None => break,
+                }
+            }
+            let len = pending.graphemes(true).next()?.len();
+            let cluster: String = pending.drain(..len).collect();
+            let start = pos;
+            pos += len;
+            Some((start, cluster))
+        })
+    }
+
+    /// Iterates over the extended grapheme clusters.
+    pub fn graphemes(&self) -> impl Iterator<Item = String> + '_ {
+        self.grapheme_indices().map(|(_, g)| g)
+    }
+}
+
+/// Decodes the next character from an iterator over valid UTF-8.
+fn decode_char<'a>(bytes: &mut impl Iterator<Item = &'a u8>) -> Option<char> {
+    let first = *bytes.next()?;
+    let width = match first {
+        0x00..=0x7f => return Some(first as char),
+        0x80..=0xdf => 2,
+        0xe0..=0xef => 3,
+        _ => 4,
+    };
+    let mut buf = [first, 0, 0, 0];
+    for b in buf[1..width].iter_mut() {
+        *b = *bytes.next().expect("truncated UTF-8");
+    }
+    core::str::from_utf8(&buf[..width])
+        .expect("invalid UTF-8")
+        .chars()
+        .next()
+}
+
+impl<const ORD: usize, const LEAF: usize, P: SharedPointerKind> Default
+    for CatString<ORD, LEAF, P>
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const ORD: usize, const LEAF: usize, P: SharedPointerKind> From<&str>
+    for CatString<ORD, LEAF, P>
+{
+    fn from(s: &str) -> Self {
+        let mut string = Self::new();
+        string.push_str(s);
+        string
+    }
+}
+
+impl<const ORD: usize, const LEAF: usize, P: SharedPointerKind> From<String>
+    for CatString<ORD, LEAF, P>
+{
+    fn from(s: String) -> Self {
+        s.as_str().into()
+    }
+}
+
+impl<const ORD: usize, const LEAF: usize, P: SharedPointerKind> From<CatString<ORD, LEAF, P>>
+    for String
+{
+    fn from(s: CatString<ORD, LEAF, P>) -> Self {
+        String::from_utf8(s.bytes.into()).expect("CatString holds invalid UTF-8")
+    }
+}
+
+impl<const ORD: usize, const LEAF: usize, P: SharedPointerKind> Write for CatString<ORD, LEAF, P> {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        self.push_str(s);
+        Ok(())
+    }
+}
+
+impl<const ORD: usize, const LEAF: usize, P: SharedPointerKind> core::fmt::Display
+    for CatString<ORD, LEAF, P>
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        self.chars().try_for_each(|c| f.write_char(c))
+    }
+}
+
+impl<const ORD: usize, const LEAF: usize, P: SharedPointerKind> core::fmt::Debug
+    for CatString<ORD, LEAF, P>
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_char('"')?;
+        for c in self.chars() {
+            for e in c.escape_debug() {
+                f.write_char(e)?;
+            }
+        }
+        f.write_char('"')
+    }
+}
+
+impl<const ORD: usize, const LEAF: usize, P: SharedPointerKind> PartialEq
+    for CatString<ORD, LEAF, P>
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.bytes == other.bytes
+    }
+}
+
+impl<const ORD: usize, const LEAF: usize, P: SharedPointerKind> Eq for CatString<ORD, LEAF, P> {}
+
+impl<const ORD: usize, const LEAF: usize, P: SharedPointerKind> PartialEq<str>
+    for CatString<ORD, LEAF, P>
+{
+    fn eq(&self, other: &str) -> bool {
+        self.len() == other.len()
+            && zip_leaves(
+                self.bytes.leaves(),
+                core::iter::once(other.as_bytes()),
+                |x, y| {
+                    if x == y {
+                        ControlFlow::Continue(())
+                    } else {
+                        ControlFlow::Break(())
+                    }
+                },
+            )
+            .is_continue()
+    }
+}
+
+impl<const ORD: usize, const LEAF: usize, P: SharedPointerKind> PartialEq<&str>
+    for CatString<ORD, LEAF, P>
+{
+    fn eq(&self, other: &&str) -> bool {
+        self == *other
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CatString;
+
+    #[test]
+    fn rope_editing() {
+        let mut s: CatString<4, 8> = CatString::new();
+        let mut shadow = String::new();
+        for i in 0..100 {
+            let piece = format!("{}: héllo wörld ✓ ", i);
+            let at = shadow
+                .char_indices()
+                .map(|(i, _)| i)
+                .nth((i * 7) % (shadow.chars().count() + 1))
+                .unwrap_or(shadow.len());
+            s.insert_str(at, &piece);
+            shadow.insert_str(at, &piece);
+        }
+        s.as_bytes().check_invariants();
+        assert_eq!(s, shadow.as_str());
+        assert!(s.chars().eq(shadow.chars()));
+        assert!(s.char_indices().eq(shadow.char_indices()));
+        let floor = |s: &str, mut i: usize| {
+            while !s.is_char_boundary(i) {
+                i -= 1;
+            }
+            i
+        };
+        let (start, end) = (floor(&shadow, 10), floor(&shadow, 300));
+        s.remove_range(start..end);
+        shadow.replace_range(start..end, "");
+        assert_eq!(String::from(s.clone()), shadow);
+        let (start, end) = (floor(&shadow, 3), floor(&shadow, 20));
+        let sliced = s.slice(start..end);
+        assert_eq!(sliced.to_string(), shadow[start..end]);
+        let mut joined = sliced.clone();
+        joined.append("→ done".into());
+        assert_eq!(joined, format!("{}→ done", &shadow[start..end]).as_str());
+        assert_eq!(
+            format!("{:?}", CatString::<4, 8>::from("a\"b")),
+            "\"a\\\"b\""
+        );
+    }
+
+    #[test]
+    fn line_index() {
+        let mut s: CatString<4, 8> = CatString::new();
+        let mut shadow = String::new();
+        for i in 0..300 {
+            let line = format!("line {}{}", i, if i % 3 == 0 { "\r\n" } else { "\n" });
+            s.push_str(&line);
+            shadow.push_str(&line);
+        }
+        s.insert_str(0, "top\n");
+        shadow.insert_str(0, "top\n");
+        s.as_bytes().check_invariants();
+        assert_eq!(s.line_count(), 302);
+        assert!(s.lines().map(String::from).eq(shadow.lines()));
+        let starts: Vec<usize> = core::iter::once(0)
+            .chain(shadow.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+        for (line, &start) in starts.iter().enumerate() {
+            assert_eq!(s.line_to_byte(line), Some(start));
+            assert_eq!(s.byte_to_line(start), line);
+        }
+        assert_eq!(s.line_to_byte(starts.len()), None);
+        assert_eq!(s.byte_to_line(s.len()), 301);
+        s.remove_range(starts[10]..starts[20]);
+        assert_eq!(s.line_count(), 292);
+        assert_eq!(s.byte_to_line(starts[10]), 10);
+        let tail = s.slice(starts[5]..);
+        assert_eq!(tail.line_count(), 287);
+        assert_eq!(tail.lines().next().unwrap(), "line 4");
+        for text in ["a\nz\r", "a\r\nb\r\r\n\r", "\r\n\n\r"] {
+            let s: CatString<4, 8> = text.into();
+            assert!(s.lines().map(String::from).eq(text.lines()), "{:?}", text);
+        }
+    }
+
+    #[test]
+    fn char_index() {
+        let shadow = "añb✓c\u{1F600}d".repeat(50);
+        let s: CatString<4, 8> = shadow.as_str().into();
+        s.as_bytes().check_invariants();
+        assert_eq!(s.char_count(), shadow.chars().count());
+        for (i, (offset, _)) in shadow.char_indices().enumerate() {
+            assert_eq!(s.char_to_byte(i), Some(offset));
+            assert_eq!(s.byte_to_char(offset), i);
+        }
+        assert_eq!(s.char_to_byte(s.char_count()), Some(s.len()));
+        assert_eq!(s.char_to_byte(s.char_count() + 1), None);
+        assert!(!s.is_char_boundary(2));
+        let expected: String = shadow.chars().skip(5).take(30).collect();
+        assert_eq!(s.slice_chars(5..35), expected.as_str());
+        assert_eq!(s.slice_chars(..).char_count(), s.char_count());
+    }
+
+    #[cfg(feature = "unicode-segmentation")]
+    #[test]
+    fn graphemes() {
+        use unicode_segmentation::UnicodeSegmentation;
+        let shadow = "e\u{301}\r\n\u{1F1EF}\u{1F1F5}\u{1F1FA}\u{1F1F8}\u{1F469}\u{200D}\u{1F4BB}x"
+            .repeat(20);
+        let s: CatString<4, 8> = shadow.as_str().into();
+        assert!(s
+            .grapheme_indices()
+            .eq(shadow.grapheme_indices(true).map(|(i, g)| (i, g.into()))));
+        assert_eq!(s.graphemes().count(), 6 * 20);
+    }
+
+    #[test]
+    #[should_panic(expected = "not a char boundary")]
+    fn split_char() {
+        let mut s: CatString<4, 8> = "aé".into();
+        s.insert_str(2, "x");
+    }
+}
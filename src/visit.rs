@@ -0,0 +1,83 @@
+use archery::{SharedPointer, SharedPointerKind};
+
+use crate::{btree::Tree, CatVec, Measure};
+
+/// Information about one node of a vector's tree, as passed to the callback of [CatVec::visit_nodes].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct NodeInfo {
+    /// Position of the node in pre-order traversal, starting from 0 for the root.
+    pub id: usize,
+    /// Id of the parent node, or None for the root.
+    pub parent: Option<usize>,
+    /// Distance from the root.
+    pub depth: usize,
+    /// Number of elements under the node.
+    pub len: usize,
+    /// Number of children for internal nodes, or elements for leaves.
+    pub fill: usize,
+    /// Maximum fill of the node.
+    pub capacity: usize,
+    pub is_leaf: bool,
+    /// Address of the node. Nodes with the same address are one and the same, shared between several places or versions.
+    pub address: usize,
+    /// Number of references to the node. Greater than 1 if the node is shared with other places or versions.
+    pub ref_count: usize,
+}
+
+impl<T: Clone, const ORD: usize, const LEAF: usize, P: SharedPointerKind, M: Measure<T>>
+    CatVec<T, ORD, LEAF, P, M>
+{
+    /// Calls the given function on every node of the tree, in pre-order.
+    pub fn visit_nodes(&self, mut f: impl FnMut(NodeInfo)) {
+        let mut next_id = 0;
+        visit(&self.inner, None, 0, 1, &mut next_id, &mut f);
+    }
+}
+
+fn visit<T: Clone, const ORD: usize, const LEAF: usize, P: SharedPointerKind, M: Measure<T>>(
+    node: &Tree<T, ORD, LEAF, P, M>,
+    parent: Option<usize>,
+    depth: usize,
+    ref_count: usize,
+    next_id: &mut usize,
+    f: &mut impl FnMut(NodeInfo),
+) {
+    let id = *next_id;
+    *next_id += 1;
+    let children = node.children();
+    f(NodeInfo {
+        id,
+        parent,
+        depth,
+        len: node.len(),
+        fill: children.map(|c| c.len()).unwrap_or_else(|| node.len()),
+        capacity: if children.is_some() { ORD } else { LEAF },
+        is_leaf: children.is_none(),
+        address: node as *const Tree<T, ORD, LEAF, P, M> as usize,
+        ref_count,
+    });
+    for child in children.into_iter().flatten() {
+        let ref_count = SharedPointer::strong_count(child);
+        visit(child, Some(id), depth + 1, ref_count, next_id, f);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::CatVec;
+
+    #[test]
+    fn visit_nodes() {
+        let v: CatVec<usize, 4, 4> = (0..100).collect::<Vec<_>>().into();
+        let mut w = v.clone();
+        *w.get_mut(50).unwrap() = 1000;
+        let mut leaves = 0;
+        let mut shared = 0;
+        w.visit_nodes(|node| {
+            leaves += node.is_leaf as usize;
+            shared += (node.ref_count > 1) as usize;
+        });
+        assert_eq!(leaves, w.tree_stats().leaf_count);
+        assert!(shared > 0);
+    }
+}
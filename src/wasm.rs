@@ -0,0 +1,84 @@
+use wasm_bindgen::prelude::*;
+
+use crate::CatBytes;
+
+/// A persistent byte vector for JavaScript, exported there as the class `CatVec`. wasm-bindgen can't export generic types, so this wraps a [CatBytes] with the default parameters. Every method but `push` leaves the vector alone, returning a new one that shares structure with it, so copies are cheap and never affect each other.
+#[wasm_bindgen(js_name = CatVec)]
+#[derive(Clone, Default)]
+pub struct JsCatVec {
+    inner: CatBytes,
+}
+
+#[wasm_bindgen(js_class = CatVec)]
+impl JsCatVec {
+    /// Creates an empty vector.
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a vector holding a copy of the bytes.
+    #[wasm_bindgen(js_name = fromUint8Array)]
+    pub fn from_uint8_array(bytes: &[u8]) -> Self {
+        Self {
+            inner: bytes.into(),
+        }
+    }
+
+    /// The number of bytes in the vector.
+    #[wasm_bindgen(getter)]
+    pub fn length(&self) -> usize {
+        self.inner.len()
+    }
+
+    /// The byte at a position, or `undefined` if it is out of bounds.
+    pub fn get(&self, idx: usize) -> Option<u8> {
+        self.inner.get(idx).copied()
+    }
+
+    /// A new vector of the bytes from `start` up to but not including `end`. Throws if the range is inverted or out of bounds.
+    pub fn slice(&self, start: usize, end: usize) -> Result<JsCatVec, JsError> {
+        Ok(Self {
+            inner: self.inner.try_slice(start..end)?,
+        })
+    }
+
+    /// A new vector of this one's bytes followed by the other's, in O(log n) time.
+    pub fn concat(&self, other: &JsCatVec) -> JsCatVec {
+        let mut inner = self.inner.clone();
+        inner.append(other.inner.clone());
+        Self { inner }
+    }
+
+    /// Appends a byte to this vector in place.
+    pub fn push(&mut self, byte: u8) {
+        self.inner.push_back(byte)
+    }
+
+    /// Copies the bytes out into a new `Uint8Array`, a leaf at a time.
+    #[wasm_bindgen(js_name = toUint8Array)]
+    pub fn to_uint8_array(&self) -> Vec<u8> {
+        let mut out = vec![0; self.inner.len()];
+        self.inner.copy_to_slice(.., &mut out);
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::JsCatVec;
+
+    #[test]
+    fn js_class() {
+        let mut v = JsCatVec::from_uint8_array(b"hello");
+        v.push(b' ');
+        let w = v.concat(&JsCatVec::from_uint8_array(b"world"));
+        assert_eq!(w.length(), 11);
+        assert_eq!(w.get(6), Some(b'w'));
+        assert_eq!(w.get(11), None);
+        assert_eq!(v.length(), 6);
+        let sliced = w.slice(3, 8).ok().unwrap();
+        assert_eq!(sliced.to_uint8_array(), b"lo wo");
+        assert_eq!(JsCatVec::new().to_uint8_array(), b"");
+    }
+}
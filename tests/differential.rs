@@ -0,0 +1,20 @@
+//! Feeds seeded random inputs through the fuzzing evaluator, so that every test run does a little fuzzing against `Vec` and `im::Vector`.
+
+#[path = "../fuzz/src/lib.rs"]
+mod fuzz;
+
+#[test]
+fn differential() {
+    let mut state = 0x9e37_79b9_7f4a_7c15u64;
+    let mut next = || {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        state
+    };
+    for _ in 0..500 {
+        let len = (next() % 1024) as usize;
+        let data: Vec<u8> = (0..len).map(|_| next() as u8).collect();
+        fuzz::run(&data);
+    }
+}
@@ -0,0 +1,117 @@
+//! Property tests checking that a [CatVec] behaves like a `Vec` under random sequences of operations, for a range of node orders. Small orders make deep trees out of few elements, which is where rebalancing goes wrong; large ones exercise wide nodes and their lookups.
+
+use catvec::CatVec;
+use proptest::prelude::*;
+
+#[derive(Debug, Clone)]
+enum Op {
+    Insert(usize, u16),
+    Append(Vec<u16>),
+    Prepend(Vec<u16>),
+    Slice(usize, usize),
+    PushBack(u16),
+    PushFront(u16),
+    PopFront,
+    Remove(usize),
+    SplitOff(usize, bool),
+}
+
+fn op() -> impl Strategy<Value = Op> {
+    prop_oneof![
+        (any::<usize>(), any::<u16>()).prop_map(|(i, x)| Op::Insert(i, x)),
+        prop::collection::vec(any::<u16>(), 0..300).prop_map(Op::Append),
+        prop::collection::vec(any::<u16>(), 0..300).prop_map(Op::Prepend),
+        (any::<usize>(), any::<usize>()).prop_map(|(i, j)| Op::Slice(i, j)),
+        any::<u16>().prop_map(Op::PushBack),
+        any::<u16>().prop_map(Op::PushFront),
+        Just(Op::PopFront),
+        any::<usize>().prop_map(Op::Remove),
+        (any::<usize>(), any::<bool>()).prop_map(|(i, keep)| Op::SplitOff(i, keep)),
+    ]
+}
+
+/// Applies the operations to a vector and to a model, checking the invariants and every element after each one.
+fn check<const ORD: usize>(initial: Vec<u16>, ops: Vec<Op>) -> Result<(), TestCaseError> {
+    let mut v: CatVec<u16, ORD, 4> = initial.as_slice().into();
+    let mut model = initial;
+    for op in ops {
+        let len = model.len();
+        match op {
+            Op::Insert(i, x) => {
+                let i = i % (len + 1);
+                v.insert(i, x);
+                model.insert(i, x);
+            }
+            Op::Append(xs) => {
+                v.append(xs.as_slice().into());
+                model.extend(xs);
+            }
+            Op::Prepend(mut xs) => {
+                let mut front: CatVec<u16, ORD, 4> = xs.as_slice().into();
+                front.append(v);
+                v = front;
+                xs.append(&mut model);
+                model = xs;
+            }
+            Op::Slice(i, j) => {
+                let i = i % (len + 1);
+                let j = i + j % (len - i + 1);
+                v.slice_into(i..j);
+                model = model[i..j].to_vec();
+            }
+            Op::PushBack(x) => {
+                v.push_back(x);
+                model.push(x);
+            }
+            Op::PushFront(x) => {
+                v.push_front(x);
+                model.insert(0, x);
+            }
+            Op::PopFront => {
+                let x = (!model.is_empty()).then(|| model.remove(0));
+                prop_assert_eq!(v.pop_front(), x);
+            }
+            Op::Remove(i) if len > 0 => {
+                prop_assert_eq!(v.remove(i % len), model.remove(i % len));
+            }
+            Op::Remove(_) => {}
+            Op::SplitOff(i, keep_left) => {
+                let i = i % (len + 1);
+                let right = v.split_off(i);
+                let model_right = model.split_off(i);
+                prop_assert!(right.validate().is_ok(), "{:?}", right.validate());
+                prop_assert!(right.iter().eq(model_right.iter()));
+                if !keep_left {
+                    v = right;
+                    model = model_right;
+                }
+            }
+        }
+        prop_assert!(v.validate().is_ok(), "{:?}", v.validate());
+        prop_assert_eq!(v.len(), model.len());
+        for (i, x) in model.iter().enumerate() {
+            prop_assert_eq!(v.get(i), Some(x));
+        }
+        prop_assert_eq!(v.get(model.len()), None);
+    }
+    prop_assert!(v.iter().eq(model.iter()));
+    Ok(())
+}
+
+macro_rules! orders {
+    ($($name:ident: $ord:expr),*) => {
+        proptest! {
+            $(
+                #[test]
+                fn $name(
+                    initial in prop::collection::vec(any::<u16>(), 0..500),
+                    ops in prop::collection::vec(op(), 0..40),
+                ) {
+                    check::<$ord>(initial, ops)?;
+                }
+            )*
+        }
+    };
+}
+
+orders!(order_2: 2, order_3: 3, order_5: 5, order_32: 32, order_128: 128);
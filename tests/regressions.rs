@@ -0,0 +1,77 @@
+//! Replays recorded fuzzer inputs through the fuzzing evaluator, which checks every result against `Vec` and `im::Vector`, and the invariants after every step, so a fixed bug can't quietly come back.
+//!
+//! Each case is a hex dump of an input to `catvec_fuzz::run`, as found in `fuzz/artifacts` after a crash; `xxd -p` prints one. Inputs are decoded according to the `Op` enum of the evaluator, so changing the enum changes what old inputs mean. When that happens, re-encode the cases to keep the operations they name.
+
+#[path = "../fuzz/src/lib.rs"]
+mod fuzz;
+
+/// The name of each case, the scenario it covers, and the input.
+const CASES: &[(&str, &str, &str)] = &[
+    (
+        "shared",
+        "Slices a clone of a vector and appends the slice back onto the original, so that both fringes of the seam are shared with another vector when they are repaired.",
+        concat!(
+            "01000000c80075d14517bbe8a28b25000000000000009600000000000000e9a2",
+            "8b2e46175d7405000000000000005e74d1456400000000000000098c2ebae8b4",
+            "00000000000000bbe8a28b01000000000000002c01000000000000"
+        ),
+    ),
+    (
+        "buffers",
+        "Pushes past the head buffer, then pops through the flushed leaf and on into the tail buffer.",
+        concat!(
+            "010000000300a38b2eba00a38b2eba01a38b2eba02a38b2eba03a38b2eba04a3",
+            "8b2eba05a38b2eba06a38b2eba07a38b2eba08185d74d1185d74d1185d74d118",
+            "5d74d1185d74d1185d74d1185d74d1185d74d1185d74d1185d74d1185d74d118",
+            "5d74d1185d74d12fbae8a2072fbae8a2072fbae8a2072fbae8a2072fbae8a207",
+            "a38b2eba018c2ebae8000000000000000046175d740200000000000000"
+        ),
+    ),
+    (
+        "empty",
+        "Slices vectors down to nothing and appends empty vectors on either side.",
+        concat!(
+            "010000003200bbe8a28b0a000000000000000a00000000000000010000000000",
+            "e9a28b2e01000000ff03e9a28b2e75d14517bbe8a28b00000000000000000000",
+            "000000000000e9a28b2e185d74d1"
+        ),
+    ),
+    (
+        "bounds",
+        "Inserts at every position from two before the end to two past it, in a tree of exactly full leaves.",
+        concat!(
+            "010000001000d245175d0000d245175d0101d245175d0202d245175d0303d245",
+            "175d04045e74d14512000000000000000146175d7411000000000000008c2eba",
+            "e81200000000000000"
+        ),
+    ),
+    (
+        "deep",
+        "Doubles a vector twice by appending it to itself, slices out a single element and appends that back.",
+        concat!(
+            "01000000ff0075d14517e9a28b2e75d14517e9a28b2e75d14517bbe8a28bff01",
+            "0000000000000002000000000000a38b2eba04e9a28b2e46175d74fb03000000",
+            "000000bbe8a28b0300000000000000e803000000000000"
+        ),
+    ),
+];
+
+fn unhex(hex: &str) -> Vec<u8> {
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).expect("cases are hex"))
+        .collect()
+}
+
+#[test]
+fn regressions() {
+    for (name, _, hex) in CASES {
+        let input = unhex(hex);
+        assert!(
+            !fuzz::decode(&input).is_empty(),
+            "case {} decodes to nothing",
+            name
+        );
+        fuzz::run(&input);
+    }
+}